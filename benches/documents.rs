@@ -0,0 +1,95 @@
+//! Measures opening and closing files in the engine with an increasing number of documents
+//! already open. The time per operation should stay flat as the number of open documents grows.
+//!
+//! Run with `cargo bench --bench documents`.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use rem::config::Config;
+use rem::editor_core::{DocID, Engine};
+
+const ROUNDS: u32 = 10_000;
+
+fn per_op(duration: Duration) -> f64 {
+    duration.as_nanos() as f64 / ROUNDS as f64
+}
+
+/// a directory of small files to open, removed when it is dropped.
+struct Files(PathBuf);
+
+impl Files {
+    fn new(count: usize) -> Self {
+        let dir = env::temp_dir().join(format!("rem-bench-documents-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        for i in 0..count {
+            fs::write(dir.join(format!("{}.txt", i)), "fn main() {}\n").unwrap();
+        }
+        Self(dir)
+    }
+
+    fn path(&self, i: usize) -> String {
+        self.0.join(format!("{}.txt", i)).to_string_lossy().to_string()
+    }
+}
+
+impl Drop for Files {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// opens the file the way the editor does, a file that is already open is not read again.
+fn open(engine: &mut Engine, path: &str) -> DocID {
+    match engine.find_document(path) {
+        Some(doc) => doc,
+        None => engine.open_document(path).unwrap(),
+    }
+}
+
+fn main() {
+    println!("{:>10} {:>12} {:>12} {:>12}", "open", "open ns", "close ns", "lookup ns");
+
+    for count in &[10, 1_000, 10_000] {
+        let files = Files::new(*count);
+        let mut engine = Engine::new(Rc::new(Config::default()));
+        let mut docs: Vec<DocID> = (0..*count).map(|i| open(&mut engine, &files.path(i))).collect();
+
+        // close and reopen files in the middle of the list.
+        let mut close = Duration::new(0, 0);
+        let mut reopen = Duration::new(0, 0);
+        for round in 0..ROUNDS {
+            let index = (round as usize * 7919) % docs.len();
+            let path = files.path(index);
+
+            let start = Instant::now();
+            engine.close_file(docs[index]).unwrap();
+            close += start.elapsed();
+
+            let start = Instant::now();
+            docs[index] = open(&mut engine, &path);
+            reopen += start.elapsed();
+        }
+
+        // opening a file that is already open only finds its document.
+        let start = Instant::now();
+        for round in 0..ROUNDS {
+            let index = (round as usize * 7919) % docs.len();
+            assert_eq!(open(&mut engine, &files.path(index)), docs[index]);
+        }
+        let lookup = start.elapsed();
+
+        println!(
+            "{:>10} {:>12.1} {:>12.1} {:>12.1}",
+            count,
+            per_op(reopen),
+            per_op(close),
+            per_op(lookup)
+        );
+    }
+}
@@ -2,6 +2,8 @@
 
 uniform sampler2D atlas;
 uniform int bg_pass;
+// the gamma of the display, 1.0 blends without correction.
+uniform float gamma;
 
 in vec2 Texcoords;
 flat in vec3 Fg;
@@ -20,8 +22,21 @@ void main() {
         alpha_mask = vec4(1);
     }
     else {
-        vec3 character = texture(atlas, Texcoords).rgb;
-        color = vec4(Fg, character.r);
-        alpha_mask = vec4(character, character.r);
+        vec3 coverage = texture(atlas, Texcoords).rgb;
+
+        // the blend is done by the hardware in gamma space, so the coverage is adjusted to give
+        // the color of blending the text with the background in linear space.
+        vec3 linear = mix(pow(Bg.rgb, vec3(gamma)), pow(Fg, vec3(gamma)), coverage);
+        vec3 target = pow(linear, vec3(1.0 / gamma));
+
+        vec3 alpha = coverage;
+        for(int i = 0; i < 3; ++i) {
+            float delta = Fg[i] - Bg[i];
+            if(abs(delta) > 0.001)
+                alpha[i] = clamp((target[i] - Bg[i]) / delta, 0.0, 1.0);
+        }
+
+        color = vec4(Fg, alpha.g);
+        alpha_mask = vec4(alpha, alpha.g);
     }
 }
@@ -0,0 +1,91 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::timer::Timer;
+use crate::window::EventsLoopProxy;
+
+/// Blinks the cursor. The event loop sleeps until there is an event, so a thread wakes it up
+/// each time the cursor is shown or hidden.
+pub struct Blink {
+    /// started when the cursor was last reset.
+    timer: Timer,
+    interval: Duration,
+    /// restarts the wakeups so they line up with the timer, the thread stops when it is dropped.
+    restart: Option<Sender<()>>,
+}
+
+impl Blink {
+    pub fn new(interval: Duration, proxy: EventsLoopProxy) -> Self {
+        let mut timer = Timer::new();
+        timer.start();
+
+        let restart = if interval == Duration::from_millis(0) {
+            None
+        } else {
+            let (restart, restarts) = mpsc::channel();
+
+            let spawned = thread::Builder::new().name("blink".to_string()).spawn(move || loop {
+                match restarts.recv_timeout(interval) {
+                    Ok(()) => continue,
+                    Err(RecvTimeoutError::Timeout) => {
+                        // the event loop is gone once the window is closed.
+                        if proxy.wakeup().is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            });
+
+            match spawned {
+                Ok(_) => Some(restart),
+                Err(e) => {
+                    println!("Failed to start the cursor blink: {}", e);
+                    None
+                }
+            }
+        };
+
+        Self {
+            timer,
+            interval,
+            restart,
+        }
+    }
+
+    /// shows the cursor and starts the blink over, after the user typed or clicked.
+    pub fn reset(&mut self) {
+        self.timer.start();
+
+        if let Some(restart) = self.restart.as_ref() {
+            let _ = restart.send(());
+        }
+    }
+
+    /// the cursor is shown at this point of the blink.
+    pub fn visible(&self) -> bool {
+        self.restart.is_none() || shown_after(self.timer.duration(), self.interval)
+    }
+}
+
+/// the cursor is shown for the first interval and every other interval after it.
+fn shown_after(elapsed: Duration, interval: Duration) -> bool {
+    (elapsed.as_millis() / interval.as_millis().max(1)).is_multiple_of(2)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn alternates_every_interval() {
+        let interval = Duration::from_millis(500);
+        let shown: Vec<bool> = [0, 499, 500, 999, 1000]
+            .iter()
+            .map(|ms| shown_after(Duration::from_millis(*ms), interval))
+            .collect();
+
+        assert_eq!(shown, vec![true, true, false, false, true]);
+    }
+}
@@ -1,7 +1,6 @@
-/// A color reprsentation in standard 255 different values for each channel.
-
 use std::str::FromStr;
 
+/// A color reprsentation in standard 255 different values for each channel.
 pub struct Color {
     channels: [u8; 3]
 }
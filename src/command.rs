@@ -0,0 +1,122 @@
+use crate::editor_core::{Encoding, LineEnding};
+
+/// Errors from parsing a command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// the command is not known.
+    Unknown(String),
+    /// the option of :set is not known.
+    UnknownOption(String),
+    /// the value given to an option is not valid.
+    InvalidValue(String, String),
+    /// the command needs an argument that was not given.
+    MissingArgument(String),
+}
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// An option that can be changed with :set
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetOption {
+    /// :set fileformat=unix|dos|mac
+    FileFormat(LineEnding),
+    /// :set fileencoding=utf-8|utf-8-bom|utf-16le|utf-16be
+    FileEncoding(Encoding),
+}
+
+/// The buffer :b switches to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BufferTarget {
+    /// :b 2
+    Number(usize),
+    /// :b name, which can be a part of the buffer's name.
+    Name(String),
+}
+
+/// A command entered in command input mode (after ':').
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// :w
+    Write,
+    /// :set option=value
+    Set(SetOption),
+    /// :ls, lists the open buffers.
+    ListBuffers,
+    /// :b N or :b name
+    Buffer(BufferTarget),
+    /// :bn
+    NextBuffer,
+    /// :bp
+    PreviousBuffer,
+}
+
+impl Command {
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let (name, args) = match input.find(char::is_whitespace) {
+            Some(index) => (&input[..index], input[index..].trim()),
+            None => (input, ""),
+        };
+
+        match name {
+            "w" | "write" => Ok(Command::Write),
+            "set" | "se" => Self::parse_set(args),
+            "ls" | "buffers" | "files" => Ok(Command::ListBuffers),
+            "b" | "buffer" => Self::parse_buffer(args),
+            "bn" | "bnext" => Ok(Command::NextBuffer),
+            "bp" | "bprevious" | "bN" | "bNext" => Ok(Command::PreviousBuffer),
+            // the number can follow :b without a space, like :b2
+            _ if name.starts_with('b') && name[1..].parse::<usize>().is_ok() => Self::parse_buffer(&name[1..]),
+            _ => Err(Error::Unknown(name.to_string())),
+        }
+    }
+
+    fn parse_buffer(args: &str) -> Result<Self> {
+        if args.is_empty() {
+            return Err(Error::MissingArgument("buffer".to_string()));
+        }
+
+        match args.parse::<usize>() {
+            Ok(number) => Ok(Command::Buffer(BufferTarget::Number(number))),
+            Err(_) => Ok(Command::Buffer(BufferTarget::Name(args.to_string()))),
+        }
+    }
+
+    fn parse_set(args: &str) -> Result<Self> {
+        let (option, value) = match args.find('=') {
+            Some(index) => (&args[..index], &args[index + 1..]),
+            None => (args, ""),
+        };
+
+        let invalid = || Error::InvalidValue(option.to_string(), value.to_string());
+
+        match option {
+            "fileformat" | "ff" => LineEnding::from_name(value)
+                .map(|e| Command::Set(SetOption::FileFormat(e)))
+                .ok_or_else(invalid),
+            "fileencoding" | "fenc" => Encoding::from_name(value)
+                .map(|e| Command::Set(SetOption::FileEncoding(e)))
+                .ok_or_else(invalid),
+            _ => Err(Error::UnknownOption(option.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn buffer_commands() {
+        assert_eq!(Command::parse("ls"), Ok(Command::ListBuffers));
+        assert_eq!(Command::parse("b 2"), Ok(Command::Buffer(BufferTarget::Number(2))));
+        assert_eq!(Command::parse("b12"), Ok(Command::Buffer(BufferTarget::Number(12))));
+        assert_eq!(
+            Command::parse("buffer main"),
+            Ok(Command::Buffer(BufferTarget::Name("main".to_string())))
+        );
+        assert_eq!(Command::parse("b"), Err(Error::MissingArgument("buffer".to_string())));
+        assert_eq!(Command::parse("bn"), Ok(Command::NextBuffer));
+        assert_eq!(Command::parse("bp"), Ok(Command::PreviousBuffer));
+    }
+}
@@ -1,14 +1,21 @@
-use super::font::{FontDesc, FontSize};
+use super::font::{Antialias, FontDesc, FontSize, Hinting, Style};
 use super::pane::CellSize;
 use crate::pane;
 use glm::Vec2;
 use std::default::Default;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct Font {
     pub font: FontDesc,
+    /// faces for the bold and italic styles, styles without a face are synthesized.
+    pub styles: Vec<FontDesc>,
     pub size: FontSize,
     pub offset: Vec2,
+    pub antialias: Antialias,
+    pub hinting: Hinting,
+    /// the gamma text is blended against the background with, 1.0 blends without correction.
+    pub gamma: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -25,13 +32,60 @@ pub struct Window {
 
 #[derive(Debug, Clone)]
 pub struct Tab {
+    /// the number of cells a tab character takes up.
     pub tab_width: u8,
-    // smart tabbing?
+    /// insert spaces instead of tab characters.
+    pub expand_tab: bool,
+    /// the number of spaces in a level of indentation, 0 uses tab_width.
+    pub shift_width: u8,
+    /// the number of spaces a tab inserts when expanding tabs, 0 uses shift_width.
+    pub soft_tab_stop: u8,
+    /// copy the indentation of the previous line when starting a new line.
+    pub auto_indent: bool,
+    /// indent after opening a block and dedent when closing it.
+    pub smart_indent: bool,
+    /// use the indentation style already used by a file when opening it.
+    pub detect_indent: bool,
+}
+
+impl Tab {
+    pub fn shift_width(&self) -> u8 {
+        if self.shift_width == 0 {
+            self.tab_width
+        } else {
+            self.shift_width
+        }
+    }
+
+    pub fn soft_tab_stop(&self) -> u8 {
+        if self.soft_tab_stop == 0 {
+            self.shift_width()
+        } else {
+            self.soft_tab_stop
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LargeFile {
+    /// files of at least this many bytes are memory mapped instead of loaded.
+    pub threshold: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// reopen the session of the working directory when the editor is started without -S.
+    pub restore: bool,
+    /// write the session when the editor exits.
+    pub save: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct Atlas {
     pub size: f32,
+    /// the most atlases glyphs are packed into, rarely used glyphs are evicted when they are full.
+    /// A frame that needs more glyphs than fit goes past it.
+    pub limit: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +94,8 @@ pub struct Cursor {
     pub insert: pane::CursorMode,
     /// the cursor mode in normal mode
     pub normal: pane::CursorMode,
+    /// how long the cursor is shown, and then hidden, while it blinks. Zero turns blinking off.
+    pub blink_interval: Duration,
 }
 // ColorScheme? Theme? Theses could be file names and the settings struct handles how they interact
 
@@ -51,6 +107,8 @@ pub struct Config {
     pub tabs: Tab,
     pub atlas: Atlas,
     pub cursor: Cursor,
+    pub large_file: LargeFile,
+    pub session: Session,
     pub cell_size: CellSize,
 }
 
@@ -61,11 +119,22 @@ impl Default for Config {
         Self {
             font: Font {
                 font: FontDesc {
-                    name: "DroidSansMono".to_string(),
-                    path: std::path::Path::new("dev/DroidSansMono.ttf").to_path_buf(),
+                    style: Style::Normal,
+                    name: "Droid Sans Mono".to_string(),
+                    path: Some(std::path::Path::new("dev/DroidSansMono.ttf").to_path_buf()),
+                    fallback: vec![FontDesc {
+                        style: Style::Normal,
+                        name: "Droid Sans".to_string(),
+                        path: Some(std::path::Path::new("dev/DroidSans.ttf").to_path_buf()),
+                        fallback: Vec::new(),
+                    }],
                 },
+                styles: Vec::new(),
                 size: FontSize { pixel_size: 20.0 },
                 offset: Vec2::new(0.0, 0.0),
+                antialias: Antialias::Grayscale,
+                hinting: Hinting::Slight,
+                gamma: 2.2,
             },
             colors: Colors {
                 bg: [0.0, 0.0, 0.0],
@@ -75,11 +144,30 @@ impl Default for Config {
                 width: 1024f32,
                 height: 864f32,
             },
-            tabs: Tab { tab_width: 2 },
-            atlas: Atlas { size: 1024f32 },
+            tabs: Tab {
+                tab_width: 2,
+                expand_tab: false,
+                shift_width: 0,
+                soft_tab_stop: 0,
+                auto_indent: true,
+                smart_indent: true,
+                detect_indent: true,
+            },
+            atlas: Atlas {
+                size: 1024f32,
+                limit: 4,
+            },
             cursor: Cursor {
                 insert: pane::CursorMode::Line,
                 normal: pane::CursorMode::Box,
+                blink_interval: Duration::from_millis(530),
+            },
+            large_file: LargeFile {
+                threshold: 64 * 1024 * 1024,
+            },
+            session: Session {
+                restore: true,
+                save: true,
             },
             cell_size: CellSize::new(0f32, 0f32),
         }
@@ -1,25 +1,45 @@
 use std::collections::HashMap;
 // main rendering crate
-#[macro_use] use crate::render;
+use crate::render;
 // editor area
-use crate::pane;
+use crate::pane::{self, PaneID};
 //use pane::{CellSize, Cells, Cursor, HorizontalLayout, Pane, PaneID, PaneKind, VerticalLayout, Layout};
-use render::PaneState;
 
 // editing engine
 use crate::editor_core;
 // user configs
 use crate::config;
+use crate::editor_core::indent;
 // main window
-use crate::window::{Window, WindowSize};
+use crate::window::Window;
 
-use crate::timer::Timer;
+// cursor blinking
+use crate::blink::Blink;
+
+// layout of the panes
+use crate::main_window::MainWindow;
+
+// fuzzy file finder
+use crate::finder::Finder;
+
+// directory tree
+use crate::file_tree::{self, FileTree};
+
+// command line
+use crate::command::{self, Command};
+
+// saved panes and documents
+use crate::session::{self, Session};
+
+// mouse handling
+use crate::mouse::{self, Drag, MouseState};
 
 // font managment(for now)
 use crate::font;
 
 use super::size::Size;
 use std::convert::{From, Into};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 // key bindings
@@ -30,6 +50,8 @@ pub enum Error {
     CreationError(crate::window::Error),
     RenderError(render::Error),
     EngineError(editor_core::Error),
+    /// the pane made by a split is not in the split tree.
+    MissingPane(PaneID),
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -42,39 +64,54 @@ pub enum EditorMode {
     Visual,
 }
 
-macro_rules! check {
-    () => {{
-        if cfg!(debug_assertions) {
-            let err = unsafe { gl::GetError() };
-            // println!("Error {:?}", err);
-            if err != gl::NO_ERROR {
-                let err_str = match err {
-                    gl::INVALID_ENUM => "GL_INVALID_ENUM",
-                    gl::INVALID_VALUE => "GL_INVALID_VALUE",
-                    gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
-                    gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
-                    gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
-                    gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
-                    gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
-                    _ => "unknown error",
-                };
-                println!("{}:{} error {}", file!(), line!(), err_str);
-                panic!();
-            }
-        }
-    }};
+const ESCAPE: char = '\u{1b}';
+const BACKSPACE: char = '\u{8}';
+/// the delete key, which removes the character after the cursor.
+const DELETE: char = '\u{7f}';
+
+/// the change in pixel size of a single zoom step.
+const ZOOM_STEP: f32 = 2.0;
+/// the smallest font size that can be zoomed to.
+const MIN_FONT_SIZE: f32 = 6.0;
+
+/// the fraction of the window the finder covers.
+const FINDER_WIDTH: f32 = 0.8;
+const FINDER_HEIGHT: f32 = 0.6;
+const FINDER_PROMPT: &str = "> ";
+
+/// the share of the window's width the file tree takes when it is shown.
+const TREE_WEIGHT: f32 = 0.2;
+
+/// printed when an edit or a write is refused because the document is read only.
+const READ_ONLY_WARNING: &str = "Warning: the document is read only, it could not be decoded without losing \
+     data or it mixes line endings until :set fileformat picks one";
+
+/// Where a pane was in a document, so switching back to the document shows the same place.
+#[derive(Debug, Clone, Copy)]
+struct DocumentView {
+    /// the (column, line) of the cursor in the document.
+    cursor: pane::Position,
+    start_line: usize,
+    view_offset: usize,
 }
 
-/// The state around the cursor and other info about the text.
-/// I do not know what this is yet.
-#[derive(Debug)]
-struct EditorState;
+impl DocumentView {
+    /// the start of a document that was not shown in the pane before.
+    fn start() -> Self {
+        Self {
+            cursor: pane::Position::new(0, 0),
+            start_line: 0,
+            view_offset: 0,
+        }
+    }
+}
 
 
 /// Main structure of the application
 pub struct App {
-    /// renders the entire application
-    renderer: render::Renderer,
+    /// draws the panes on its own thread. It is dropped first so the thread is done with the
+    /// OpenGL context before the window is closed.
+    renderer: render::RenderThread,
     ///  main editing engine, owns the open documents
     engine: editor_core::Engine,
     /// main windows pane
@@ -83,12 +120,44 @@ pub struct App {
     mode: EditorMode,
     /// pane and document association.
     docs: HashMap<pane::PaneID, editor_core::DocID>,
-    /// until I know where they should actually go.
-    cache: render::GlyphCache<font::FreeTypeRasterizer>,
+    /// the metrics of the font at its current size.
+    metrics: font::Metrics,
+    /// the size of the font after zooming.
+    font_size: font::FontSize,
+    /// something changed since the last frame was sent to the render thread.
+    redraw: bool,
     /// the current settings of the application
     config: Rc<config::Config>,
-    /// 
-    timer: Timer,
+    /// shows and hides the cursor of the focused pane.
+    blink: Blink,
+    /// the cursor was shown in the last frame sent to the render thread.
+    cursor_visible: bool,
+    /// the state of the mouse between events.
+    mouse: MouseState,
+    /// the text typed in command input mode.
+    command: String,
+    /// the commands entered, oldest first.
+    history: Vec<String>,
+    /// the command of the history recalled with up and down, None while a new one is typed.
+    history_index: Option<usize>,
+    /// the text searched for by adding cursors at its occurrences, oldest first.
+    search_history: Vec<String>,
+    /// the text copied with Ctrl-C, pasted at every cursor with Ctrl-V.
+    register: String,
+    /// the modifier keys held during the last key event.
+    modifiers: glutin::ModifiersState,
+    /// the fuzzy file finder, drawn over the panes while it is open.
+    finder: Option<Finder>,
+    /// the buffers listed by the finder when it picks a buffer rather than a file.
+    finder_buffers: Option<Vec<editor_core::DocID>>,
+    /// where each pane was in the documents it showed before.
+    views: HashMap<(PaneID, editor_core::DocID), DocumentView>,
+    /// the document each pane showed before its current one, for Ctrl-^.
+    alternate: HashMap<PaneID, editor_core::DocID>,
+    /// the directory tree shown left of the panes.
+    tree: Option<FileTree>,
+    /// keys go to the file tree rather than the active pane.
+    tree_focused: bool,
 }
 
 
@@ -98,28 +167,23 @@ impl App {
 
         println!("{:?}", config);
 
-        let window = Window::new(
+        let mut window = Window::new(
             event_loop,
             glutin::dpi::LogicalSize::new(config.window.width as f64, config.window.height as f64),
         )
-        .map_err(|e| Error::CreationError(e))?;
-
-        // Gl function cannot be loaded until I have a context
-        window.init_gl().unwrap();
-        check!();
+        .map_err(Error::CreationError)?;
 
         let dpf = window.dpi_factor();
+        let blink = Blink::new(config.cursor.blink_interval, window.create_proxy());
 
         println!("Window DPI: {}", dpf);
 
-        let mut renderer = render::Renderer::new(&config).map_err(|e| Error::RenderError(e))?;
+        // the render thread makes the context current and loads the font.
+        let context = window.take_context().expect("the window's context was already taken");
+        let (renderer, metrics) = render::RenderThread::spawn(context, config.clone(), dpf as f32)
+            .map_err(Error::RenderError)?;
 
-        let cache = renderer
-            .prepare_font(dpf as f32, &config)
-            .map_err(|e| Error::RenderError(e))?;
-        check!();
-
-        let cell_size = Self::compute_cell_size(cache.metrics(), &config.font);
+        let cell_size = Self::compute_cell_size(&metrics, &config.font, dpf);
         config.cell_size = cell_size;
         println!("Cell Size: {:?}", cell_size);
 
@@ -137,7 +201,7 @@ impl App {
 
         let docid = engine
             .create_empty_document()
-            .map_err(|e| Error::EngineError(e))?;
+            .map_err(Error::EngineError)?;
 
         // }
 
@@ -145,62 +209,154 @@ impl App {
             renderer,
             engine,
             main_window,
-            /// @TODO change back to normal
+            // @TODO change back to normal
             mode: EditorMode::Insert,
             docs: HashMap::new(),
-            cache,
-            config
+            metrics,
+            font_size: config.font.size,
+            redraw: true,
+            config,
+            blink,
+            cursor_visible: true,
+            mouse: MouseState::new(),
+            command: String::new(),
+            history: Vec::new(),
+            history_index: None,
+            search_history: Vec::new(),
+            register: String::new(),
+            modifiers: glutin::ModifiersState::default(),
+            finder: None,
+            finder_buffers: None,
+            views: HashMap::new(),
+            alternate: HashMap::new(),
+            tree: None,
+            tree_focused: false,
         };
 
         // what if a default layout is allowed and this not an edit pane. @FUTUREPROOF
         app.register_document(app.main_window.pane().id(), docid);
+        app.main_window.set_cursor_mode(app.cursor_mode());
 
         app.prepare()?;
 
-        app.vertical_split_active_pane(docid);
+        app.vertical_split_active_pane(docid)?;
 
         Ok(app)
     }
 
-    fn prepare(&self) -> Result<()> {
+    fn prepare(&mut self) -> Result<()> {
         let (w, h): (f64, f64) = self.main_window.window().get_physical_size().into();
 
         println!("Window Size: {} {}", w, h);
-        self.renderer.set_view_port(w as f32, h as f32);
+        self.renderer
+            .prepare(self.config.cell_size, self.config.font.gamma)
+            .map_err(Error::RenderError)?;
+        self.redraw = true;
+
+        Ok(())
+    }
+
+    /// the size of a cell in physical pixels, the metrics are already scaled by the dpi factor.
+    pub fn compute_cell_size(metrics: &font::Metrics, font: &config::Font, dpi_factor: f64) -> pane::CellSize {
+        let width = metrics.average_advance + font.offset.x * dpi_factor as f32;
+        let height = metrics.line_height + font.offset.y * dpi_factor as f32;
+
+        pane::CellSize::new(width, height)
+    }
+
+    /// changes the font size, rebuilding the glyph atlases and the cells of every pane.
+    pub fn set_font_size(&mut self, size: font::FontSize) -> Result<()> {
+        if size == self.font_size {
+            return Ok(());
+        }
+
+        self.metrics = self
+            .renderer
+            .set_font_size(size)
+            .map_err(Error::RenderError)?;
+        self.font_size = size;
+
+        println!("Font Size: {:?}", size);
+        self.update_cell_size()
+    }
+
+    /// re-rasterizes every glyph for the new scale of the window and lays out the panes
+    /// for its new physical size, when the window moves to a monitor with a different dpi.
+    fn on_dpi_changed(&mut self, dpi_factor: f64) -> Result<()> {
+        println!("Window DPI: {}", dpi_factor);
+
+        self.metrics = self
+            .renderer
+            .set_dpi_factor(dpi_factor as f32)
+            .map_err(Error::RenderError)?;
+
+        self.update_cell_size()
+    }
+
+    /// recomputes the cell size from the font metrics and lays out every pane with it.
+    fn update_cell_size(&mut self) -> Result<()> {
+        let dpi_factor = self.main_window.window().dpi_factor();
+        let cell_size = Self::compute_cell_size(&self.metrics, &self.config.font, dpi_factor);
+        Rc::make_mut(&mut self.config).cell_size = cell_size;
+        self.engine.set_config(self.config.clone());
+        println!("Cell Size: {:?}", cell_size);
+
+        self.main_window.set_cell_size(cell_size);
+        self.main_window.fit_to_window()?;
 
-        let shader = self.renderer.text_shader();
+        // the panes fit a different number of cells so keep every cursor in view.
+        self.keep_cursors_in_view();
 
-        shader.activate();
-        shader.set_cell_size(self.config.cell_size);
-        shader.deactivate();
+        self.prepare()
+    }
 
-        self.renderer.prepare();
+    /// lays out the split tree for the new size of the window.
+    fn on_resize(&mut self) -> Result<()> {
+        self.main_window.fit_to_window()?;
+        self.keep_cursors_in_view();
+        self.redraw = true;
 
         Ok(())
     }
-    
-    /*
-	pub fn vertical_split_active_pane(&mut self, new_document: editor_core::DocID) {
-		let active_pane = self.main_window.active_pane_mut();
-		let id = active_pane.vertical_split();
-        if let Some(pane) = MainWindow::find_pane_by_id(self.main_window.pane(), id) {
-            let size = pane.size().clone();
 
-            self.register_document(id, new_document);
-            self.main_window.create_pane_state(size, id);
+    /// scrolls every pane so its cursor is within the cells it has after a change of layout.
+    fn keep_cursors_in_view(&mut self) {
+        for (id, _, _) in self.main_window.pane().edit_panes() {
+            if let Some(pos) = self.cursor_position(id) {
+                self.place_cursor(id, pos);
+            }
         }
-        else {
-            panic!("Failed to split pane");
+    }
+
+    /// grows or shrinks the font by a number of zoom steps, 0 returns to the configured size.
+    fn zoom(&mut self, steps: i32) {
+        let pixel_size = match steps {
+            0 => self.config.font.size.pixel_size,
+            _ => (self.font_size.pixel_size + steps as f32 * ZOOM_STEP).max(MIN_FONT_SIZE),
+        };
+
+        if let Err(e) = self.set_font_size(font::FontSize { pixel_size }) {
+            println!("Failed to change font size: {:?}", e);
         }
-	}
+    }
+    
+    /// splits the active pane in two side by side, the new pane on the right shows the document.
+    /// The active pane is narrowed to make room, so its cursor is kept in view.
+    pub fn vertical_split_active_pane(&mut self, new_document: editor_core::DocID) -> Result<PaneID> {
+        let id = self.main_window.active_pane_mut().vertical_split();
+        let size = match self.main_window.get_pane(id) {
+            Some(pane) => *pane.size(),
+            None => return Err(Error::MissingPane(id)),
+        };
 
-    pub fn compute_cell_size(metrics: &font::Metrics, font: &config::Font) -> CellSize {
-        let width = metrics.average_advance + font.offset.x;
-        let height = metrics.line_height + font.offset.y;
+        self.register_document(id, new_document);
+        self.main_window.create_pane_state(size, id)?;
+        self.main_window.update_pane_states()?;
+        self.keep_cursors_in_view();
+        self.redraw = true;
 
-        CellSize::new(width, height)
+        Ok(id)
     }
-    */
 
     /// registers a file to be rendered by an edit pane.
     pub fn register_document(&mut self, pane: PaneID, doc: editor_core::DocID) {
@@ -210,170 +366,373 @@ impl App {
            .and_modify(|e| *e = doc)
            .or_insert(doc); 
     }
-    
-    
-    /*
-    pub fn render_panes(&self) -> Result<()> {
-        let pane = self.main_window.pane();
-        self.render_all_panes(pane)
-    }
-    
-    fn render_all_panes(&self, current_pane: &Pane) -> Result<()> {
-        match *current_pane.kind() {
-            PaneKind::Edit => {
-                if let Some(state) = self.main_window.get_pane_state(current_pane.id()) {
-                    self.render_pane(current_pane, state)?
-                } else {
-                    panic!("Unable to find pane state for valid pane");
-                }
-            },
-            PaneKind::Vert(ref layout) => {
-                for pane in layout.iter() {
-                    self.render_all_panes(pane)?;
-                }
-            },
-            PaneKind::Hor(ref layout) => {
-                for pane in layout.iter() {
-                    self.render_all_panes(pane)?;
-                }
-            },
-        }
-        Ok(())
-    }
 
-    // rewrite this to handle the different layouts
-    pub fn render_pane(&self, pane: &Pane, state: &PaneState) -> Result<()> {
-        // if the pane doens't need to be redrawn then use the cached pane to blit the screen.
-        if !state.dirty {
-            return Ok(());
+    /// shows the document in the pane where the pane was left in it, or from its start. The
+    /// document the pane showed becomes its alternate.
+    pub fn show_document(&mut self, pane: PaneID, doc: editor_core::DocID) {
+        let current = self.docs.get(&pane).cloned();
+        if current == Some(doc) {
+            return;
         }
 
-        // Set the view, bind uniforms, and bind the frame buffer.
-        //        pane.ready_render(&self.renderer).map_err(|e| Error::RenderError(e))?;
-        //        pane.bind_frame_as_write();
-        
-        let (w, h): (f32, f32) = pane.size().clone().into();
-        let loc = pane.loc();
+        if let Some(current) = current {
+            if let Some(view) = self.document_view(pane) {
+                self.views.insert((pane, current), view);
+            }
+            self.alternate.insert(pane, current);
+        }
 
-        self.renderer.set_view_port_at(w, h, loc.x as f32, loc.y as f32);
+        self.register_document(pane, doc);
 
-        let shader = self.renderer.text_shader();
-        
-        let ortho = glm::ortho(0f32, w, h, 0f32, -1f32, 1f32);
-        let cell_size = self.config.cell_size;
+        let mut view = self.views.get(&(pane, doc)).cloned().unwrap_or_else(DocumentView::start);
 
-        shader.activate();
-        
-        shader.set_perspective(ortho);
-        shader.set_cell_size(cell_size);
+        // the document could have been shortened in another pane since.
+        if let Some(document) = self.engine.get_document(doc) {
+            let line = (view.cursor.y as usize).min(document.len_lines().saturating_sub(1));
+            let column = (view.cursor.x as usize).min(document.line_len(line));
+            view.cursor = pane::Position::new(column as u32, line as u32);
+            view.start_line = view.start_line.min(line);
+        }
 
-        shader.deactivate();
-        
-        state.frame.bind_write();
+        if let Some(state) = self.main_window.get_pane_state_mut(pane) {
+            state.start_line = view.start_line;
+            state.view_offset = view.view_offset;
+            state.selection = None;
+            state.cursors.clear();
+            state.cursor.set_pos(pane::Position::new(0, 0));
+            state.damage.add_all();
+        }
 
-        //
-        // Since the entire frame is to be drawn to, the frame doesnt need to be cleared?.
-        // clear the current view, in this case it will be the frame buffer.
-        self.renderer.clear_frame(None);
+        self.place_cursor(pane, view.cursor);
+    }
 
-        // get the document associated to the pane.
-        let document: &editor_core::Document = match self.docs.get(&pane.id()) {
-            Some(doc) => match self.engine.get_document(*doc) {
-                Some(doc) => doc,
-                None => panic!("invalid document id"),
-            },
-            _ => panic!("Invalid pane/document association"),
-        };
+    /// where the pane is in its document.
+    fn document_view(&self, pane: PaneID) -> Option<DocumentView> {
+        let state = self.main_window.get_pane_state(pane)?;
 
-        let mut batch = render::Batch::new();
-        let render = &self.renderer;
-        let cache = &self.cache;
-        let cursor = &state.cursor;
+        Some(DocumentView {
+            cursor: self.cursor_position(pane)?,
+            start_line: state.start_line,
+            view_offset: state.view_offset,
+        })
+    }
 
-        render.draw_pane_background(&mut batch, pane, cursor);
+    /// the panes, where they are in their documents and the commands typed, as they are saved.
+    pub fn session(&self) -> Session {
+        let active = self.main_window.active_pane().id();
+
+        Session::from_pane(self.main_window.pane(), active, &self.history, &self.search_history, |id| {
+            let view = self.document_view(id).unwrap_or_else(DocumentView::start);
+            // the session can be restored from another directory.
+            let path = self
+                .docs
+                .get(&id)
+                .and_then(|doc| self.engine.get_document(*doc))
+                .and_then(|document| document.path())
+                .map(|path| std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path)));
+
+            session::EditView {
+                path,
+                line: view.cursor.y as usize,
+                column: view.cursor.x as usize,
+                start_line: view.start_line,
+                view_offset: view.view_offset,
+            }
+        })
+    }
 
-        let lines =
-            document.line_slice(state.start_line, state.start_line + pane.cells().y as usize);
+    pub fn save_session(&self, path: &Path) -> session::Result<()> {
+        self.session().save(path)
+    }
 
-        // this should always be zero? depending on the cutter
-        let mut cell = (state.start_line as u32, 0 as u32);
+    /// replaces the panes with the ones of the session and opens the files they showed where
+    /// they were left. A file that cannot be opened anymore is shown as an empty document.
+    pub fn restore_session(&mut self, session: &Session) -> Result<()> {
+        let layout = match session.layout() {
+            Some(layout) => layout,
+            None => return Ok(()),
+        };
+        if session.tabs.len() > 1 {
+            println!("Only the active tab of the session is restored");
+        }
 
-        for line in lines {
-            for c in line.chars() {
-                if c == '\n' {
-                    continue;
-                }
+        let mut views = Vec::new();
+        let root = layout.to_pane(&mut views);
 
-                if c == ' ' {
-                    cell.0 += 1;
-                    continue;
-                }
+        if views.is_empty() {
+            println!("The session has no edit panes, it was not restored");
+            return Ok(());
+        }
 
-                if c == '\t' {
-                    cell.0 += self.config.tabs.tab_width as u32;
-                    continue;
+        let mut docs = Vec::new();
+        for (_, view) in views.iter() {
+            let opened = match view.path.as_ref() {
+                Some(path) => self.open_file(&path.to_string_lossy()),
+                None => self.engine.create_empty_document(),
+            };
+
+            let doc = match opened {
+                Ok(doc) => doc,
+                Err(e) => {
+                    println!("Unable to open {:?}: {:?}", view.path, e);
+                    self.engine.create_empty_document().map_err(Error::EngineError)?
                 }
+            };
+            docs.push(doc);
+        }
 
-                // @TODO: handle the case when c is not in the cache
-                let glyph = cache.get(c as u32).unwrap();
+        // the empty scratch buffers of the replaced panes are closed.
+        let replaced: Vec<_> = self.docs.drain().map(|(_, doc)| doc).collect();
+        for doc in replaced {
+            let scratch = match self.engine.get_document(doc) {
+                Some(document) => document.path().is_none() && document.as_str().is_empty() && !docs.contains(&doc),
+                None => false,
+            };
 
-                let text_color = if cell.0 == cursor.pos().x && cell.1 == cursor.pos().y {
-                    [0.0, 0.0, 0.0]
-                } else {
-                    [1.0, 1.0, 1.0]
-                };
+            if scratch {
+                let _ = self.engine.close_file(doc);
+            }
+        }
+        self.views.clear();
+        self.alternate.clear();
+
+        // the tree is read again from the working directory.
+        self.set_tree_focus(false);
+        self.tree = None;
+        if layout.has_tree() {
+            if let Ok(root) = std::env::current_dir() {
+                let mut tree = FileTree::new(root);
+                tree.watch(self.main_window.window().create_proxy());
+                self.tree = Some(tree);
+            }
+        }
 
-                let instance = render::InstanceData {
-                    x: cell.0 as f32,
-                    y: cell.1 as f32,
+        self.main_window.set_pane(root)?;
 
-                    // text metrics offsets for the character
-                    width: glyph.width,
-                    height: glyph.height,
-                    offset_x: glyph.bearing_x, // - 1.0,
-                    offset_y: glyph.bearing_y + 2.0,
+        for ((id, view), doc) in views.iter().zip(docs) {
+            let place = DocumentView {
+                cursor: pane::Position::new(view.column as u32, view.line as u32),
+                start_line: view.start_line,
+                view_offset: view.view_offset,
+            };
+            self.views.insert((*id, doc), place);
+            self.show_document(*id, doc);
+        }
 
-                    // texture coordinates
-                    uv_x: glyph.uv_x,
-                    uv_y: glyph.uv_y,
-                    uv_dx: glyph.uv_dx,
-                    uv_dy: glyph.uv_dy,
+        if let Some((id, _)) = views.get(session.active) {
+            self.main_window.focus_pane(*id);
+        }
 
-                    tr: text_color[0],
-                    tg: text_color[1],
-                    tb: text_color[2],
+        self.history = session.history.clone();
+        self.search_history = session.search.clone();
+        self.redraw = true;
 
-                    br: 0.0,
-                    bg: 0.0,
-                    bb: 0.0,
-                    ba: 1.0,
+        Ok(())
+    }
 
-                    texture_id: glyph.atlas as i32,
-                };
+    /// switches the active pane to the buffer with the number or name.
+    fn go_to_buffer(&mut self, target: command::BufferTarget) {
+        let doc = match target {
+            command::BufferTarget::Number(number) => self.engine.buffers().get(number),
+            command::BufferTarget::Name(name) => editor_core::buffers::match_name(&self.buffer_names(), &name),
+        };
 
-                if batch.push(instance) {
-                    render.draw_batch(&batch);
-                    batch.clear();
-                }
-                cell.0 += 1;
+        match doc {
+            Ok(doc) => {
+                let id = self.main_window.active_pane().id();
+                self.show_document(id, doc);
             }
-            cell.0 = 0;
-            cell.1 += 1;
+            Err(e) => println!("Unable to switch buffers: {:?}", e),
+        }
+    }
+
+    /// switches the active pane to the next or previous buffer of the list.
+    fn cycle_buffer(&mut self, forward: bool) {
+        let id = self.main_window.active_pane().id();
+        let next = self
+            .docs
+            .get(&id)
+            .and_then(|doc| self.engine.buffers().cycle(*doc, forward));
+
+        if let Some(doc) = next {
+            self.show_document(id, doc);
+        }
+    }
+
+    /// switches the active pane back to the document it showed before.
+    fn alternate_buffer(&mut self) {
+        let id = self.main_window.active_pane().id();
+
+        match self.alternate.get(&id).cloned() {
+            // the alternate could have been closed since.
+            Some(doc) if self.engine.get_document(doc).is_some() => self.show_document(id, doc),
+            _ => println!("No alternate buffer"),
+        }
+    }
+
+    /// the open buffers with the names they are listed and matched by.
+    fn buffer_names(&self) -> Vec<(editor_core::DocID, String)> {
+        self.engine
+            .buffers()
+            .iter()
+            .filter_map(|(_, doc)| self.engine.get_document(doc).map(|document| (doc, buffer_name(document))))
+            .collect()
+    }
+
+    /// lists the open buffers like vim's :ls and opens the finder to pick one of them.
+    fn open_buffer_picker(&mut self) {
+        let id = self.main_window.active_pane().id();
+        let current = self.docs.get(&id).cloned();
+        let alternate = self.alternate.get(&id).cloned();
+
+        let mut docs = Vec::new();
+        let mut items = Vec::new();
+
+        for (number, doc) in self.engine.buffers().iter() {
+            let document = match self.engine.get_document(doc) {
+                Some(document) => document,
+                None => continue,
+            };
+
+            let flag = if Some(doc) == current {
+                '%'
+            } else if Some(doc) == alternate {
+                '#'
+            } else {
+                ' '
+            };
+
+            let item = format!("{:>3} {} {}", number, flag, buffer_name(document));
+
+            docs.push(doc);
+            items.push(item);
         }
 
-        render.draw_batch(&batch).map_err(|e| Error::RenderError(e))?;
+        self.finder = Some(Finder::with_items(items, self.main_window.window().create_proxy()));
+        self.finder_buffers = Some(docs);
+        self.redraw = true;
+    }
+
+    /// sends the panes to the render thread when something changed since the last frame.
+    pub fn render_panes(&mut self) -> Result<()> {
+        let panes = self.main_window.pane().edit_panes();
+        let damaged = panes.iter().any(|(id, _, _)| match self.main_window.get_pane_state(*id) {
+            Some(state) => !state.damage.is_empty(),
+            None => false,
+        });
+
+        // nothing changed so the last frame is still on the window.
+        if !self.redraw && !damaged {
+            return Ok(());
+        }
+
+        let mut panes: Vec<render::PaneSnapshot> = panes.into_iter().map(|(id, _, _)| self.pane_snapshot(id)).collect();
+        panes.extend(self.tree_snapshot());
+        let separators = self.main_window.pane().separators();
+        let overlay = self.finder_snapshots();
+
+        let (w, h): (f64, f64) = self.main_window.window().get_physical_size().into();
+        let frame = render::FrameSnapshot {
+            window_size: Size::new(w as f32, h as f32),
+            panes,
+            separators,
+            overlay,
+        };
+
+        self.renderer.draw(frame).map_err(Error::RenderError)?;
+        self.redraw = false;
 
         Ok(())
     }
-    */
+
+    /// copies what is visible in the pane for the render thread, taking the damage of the pane.
+    pub fn pane_snapshot(&mut self, id: PaneID) -> render::PaneSnapshot {
+        let damage = match self.main_window.get_pane_state_mut(id) {
+            Some(state) => state.damage.take(),
+            None => render::Damage::full(),
+        };
+
+        let pane = match self.main_window.get_pane(id) {
+            Some(pane) => pane,
+            None => panic!("Unable to find pane of given id: {:?}", id),
+        };
+
+        let state = match self.main_window.get_pane_state(id) {
+            Some(state) => state,
+            None => panic!("Unable to find pane state for valid pane"),
+        };
+
+        // get the document associated to the pane.
+        let document: &editor_core::Document = match self.docs.get(&pane.id()) {
+            Some(doc) => match self.engine.get_document(*doc) {
+                Some(doc) => doc,
+                None => panic!("invalid document id"),
+            },
+            _ => panic!("Invalid pane/document association"),
+        };
+
+        // the other cursors that are in view, as cells of the pane.
+        let tab_width = self.config.tabs.tab_width as u32;
+        let cells = *pane.cells();
+        let other_cursors = state
+            .cursors
+            .iter()
+            .filter_map(|pos| {
+                let line = pos.y as usize;
+                let cell = document.column_to_cell(line, pos.x as usize, tab_width) as usize;
+                if line < state.start_line || cell < state.view_offset {
+                    return None;
+                }
+
+                let cell = pane::Position::new((cell - state.view_offset) as u32, (line - state.start_line) as u32);
+                if cell.x < cells.x && cell.y < cells.y {
+                    Some(cell)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let lines = document
+            .line_slice(state.start_line, state.start_line + pane.cells().y as usize)
+            .iter()
+            .map(|line| line.trim_end_matches(['\n', '\r']).to_string())
+            .collect();
+
+        render::PaneSnapshot {
+            id,
+            x: pane.loc().x,
+            y: pane.loc().y,
+            size: *pane.size(),
+            lines,
+            first_line: state.start_line,
+            view_offset: state.view_offset,
+            cursor: *state.cursor.pos(),
+            other_cursors,
+            // the cursor of the active pane is hollow while the file tree has the keys.
+            cursor_style: match (state.active && !self.tree_focused, self.cursor_visible) {
+                (true, true) => render::CursorStyle::Solid(state.cursor.mode()),
+                (true, false) => render::CursorStyle::Hidden,
+                (false, _) => render::CursorStyle::Hollow,
+            },
+            cells: *pane.cells(),
+            selection: state.selection.as_ref().map(|selection| selection_range(document, selection)),
+            highlights: Vec::new(),
+            tab_width: self.config.tabs.tab_width as u32,
+            // documents are not highlighted yet so all of the text uses the default style.
+            style: render::TextStyle::default(),
+            damage,
+        }
+    }
 
     pub fn process_input(&mut self) -> bool {
         let mut running = true;
         let mut events = Vec::new();
 
+        // nothing is drawn on this thread so it sleeps until there is something to handle.
         self.main_window
             .window_mut()
-            .poll_events(|event| events.push(event));
+            .wait_events(|event| events.push(event));
 
         if events.is_empty() {
             return true;
@@ -383,135 +742,1406 @@ impl App {
             running = self.process_event(&event);
         }
 
+        self.update_blink();
+        self.update_finder();
+        self.update_tree();
+
         running
     }
 
-    pub fn process_event(&mut self, event: &glutin::Event) -> bool {
-        use glutin::*;
-        match *event {
-            // LoopDestroyed => running = false,
-            Event::DeviceEvent { .. } => true,
-            Event::WindowEvent { ref event, .. } => match event {
-                WindowEvent::KeyboardInput { ref input, .. } => {
-                    println!("{:?}", input);
-                    true
-                }
-                // Maybe using KeyboardInput and processing that would
-                // give a better using experience instead of using ReceivedCharacter
-                WindowEvent::ReceivedCharacter(ch) => {
-                    println!("{:?}", event);
-                    println!("Character Input: {}", *ch);
-                    self.process_character_input(*ch);
-                    true
-                }
-                WindowEvent::CloseRequested | WindowEvent::Destroyed => false,
-                _ => true,
-            },
-            _ => true,
+    /// takes the results the finder scored since the last events.
+    fn update_finder(&mut self) {
+        if let Some(finder) = self.finder.as_mut() {
+            if finder.poll() {
+                self.redraw = true;
+            }
         }
     }
-    /*
-    pub fn active_pane(&self) -> Option<&pane::Pane> {
-        None
+
+    /// reads the file tree again after the watcher saw its directories change on disk.
+    fn update_tree(&mut self) {
+        if let Some(tree) = self.tree.as_mut() {
+            if tree.refresh() {
+                self.redraw = true;
+            }
+        }
     }
 
-    fn get_active_pane(&mut self) -> Option<&mut pane::Pane> {
-        None
+    /// shows the file tree of the working directory and gives it the keys, or hides it.
+    fn toggle_tree(&mut self) {
+        let result = if self.tree.take().is_some() {
+            self.set_tree_focus(false);
+            self.main_window.hide_tree()
+        } else {
+            let root = match std::env::current_dir() {
+                Ok(root) => root,
+                Err(e) => {
+                    println!("Unable to find the project directory: {}", e);
+                    return;
+                }
+            };
+
+            let mut tree = FileTree::new(root);
+            tree.watch(self.main_window.window().create_proxy());
+            self.tree = Some(tree);
+            self.set_tree_focus(true);
+
+            self.main_window.show_tree(TREE_WEIGHT).map(|_| ())
+        };
+
+        if let Err(e) = result {
+            println!("Failed to lay out the file tree: {:?}", e);
+        }
+
+        // the panes were resized to make room for the tree or to take its place.
+        self.keep_cursors_in_view();
+        self.redraw = true;
     }
 
-    */
+    /// gives the keys to the file tree or back to the active pane.
+    fn set_tree_focus(&mut self, focused: bool) {
+        let focused = focused && self.tree.is_some();
+        if focused == self.tree_focused {
+            return;
+        }
 
-    pub fn editor_mode(&self) -> EditorMode {
-        self.mode
+        self.tree_focused = focused;
+        self.redraw = true;
+
+        // the cursor of the active pane changes between solid and hollow.
+        let id = self.main_window.active_pane().id();
+        if let Some(state) = self.main_window.get_pane_state_mut(id) {
+            state.damage.add_row(state.cursor.pos().y);
+        }
     }
 
-    fn get_pane_document_id(&self, pane_id: pane::PaneID) -> Option<&editor_core::DocID> {
-        self.docs.get(&pane_id)
+    fn process_tree_input(&mut self, ch: char) {
+        let tree = match self.tree.as_mut() {
+            Some(tree) => tree,
+            None => return,
+        };
+        self.redraw = true;
+
+        // a create, rename or delete takes the keys until it is confirmed or cancelled.
+        if tree.prompt().is_some() {
+            let result = match ch {
+                '\r' | '\n' => tree.confirm_prompt(),
+                ESCAPE => {
+                    tree.cancel_prompt();
+                    Ok(None)
+                }
+                BACKSPACE => {
+                    tree.prompt_backspace();
+                    Ok(None)
+                }
+                _ if ch.is_control() => Ok(None),
+                _ => tree.prompt_input(ch),
+            };
+
+            match result {
+                Ok(Some(change)) => self.follow_file_change(change),
+                Ok(None) => {}
+                Err(e) => println!("Unable to change the files: {}", e),
+            }
+            return;
+        }
+
+        match ch {
+            'j' => tree.move_selection(1),
+            'k' => tree.move_selection(-1),
+            'l' => tree.expand(),
+            'h' => tree.collapse(),
+            'a' => tree.prompt_create(),
+            'r' => tree.prompt_rename(),
+            'd' => tree.prompt_delete(),
+            'R' => tree.reload(),
+            '\r' | '\n' | 'o' => self.open_tree_entry(false),
+            's' | 'v' => self.open_tree_entry(true),
+            'q' => self.toggle_tree(),
+            ESCAPE => self.set_tree_focus(false),
+            _ => {}
+        }
     }
 
-    fn process_character_input(&mut self, ch: char) {
-        match self.editor_mode() {
-            EditorMode::Normal => {
-                if ch == ':' {
-                    self.mode = EditorMode::CommandInput;
+    /// points the documents of a file renamed in the tree at its new path, and closes the
+    /// documents of a deleted one.
+    fn follow_file_change(&mut self, change: file_tree::Change) {
+        match change {
+            file_tree::Change::Renamed(from, to) => {
+                // the file was already moved, so its old path is found through the new one.
+                let to = match std::fs::canonicalize(&to) {
+                    Ok(to) => to,
+                    Err(_) => return,
+                };
+                if let Some(name) = from.file_name() {
+                    self.engine.rename_path(&to.with_file_name(name), &to);
+                }
+            }
+            file_tree::Change::Deleted(path) => {
+                let path = match (path.parent().map(std::fs::canonicalize), path.file_name()) {
+                    (Some(Ok(dir)), Some(name)) => dir.join(name),
+                    _ => return,
+                };
+
+                for doc in self.engine.documents_under(&path) {
+                    self.close_document(doc);
                 }
-                // otherwise the input is ignored.
             }
-            EditorMode::CommandInput => self.process_command_input(ch),
-            EditorMode::Insert => self.process_character_insert(ch),
-            _ => (),
         }
+
+        self.redraw = true;
     }
 
-    fn process_command_input(&mut self, ch: char) {}
+    /// closes the document. The panes that showed it switch to their alternate, the next
+    /// buffer or an empty document.
+    fn close_document(&mut self, doc: editor_core::DocID) {
+        let panes: Vec<PaneID> = self.docs.iter().filter(|(_, d)| **d == doc).map(|(id, _)| *id).collect();
+
+        for id in panes {
+            let next = match self.alternate.get(&id).cloned() {
+                Some(alternate) if alternate != doc && self.engine.get_document(alternate).is_some() => Some(alternate),
+                _ => self.engine.buffers().cycle(doc, true).filter(|next| *next != doc),
+            };
+
+            let next = match next {
+                Some(next) => next,
+                None => match self.engine.create_empty_document() {
+                    Ok(empty) => empty,
+                    Err(e) => {
+                        println!("Unable to create a document: {:?}", e);
+                        continue;
+                    }
+                },
+            };
+            self.show_document(id, next);
+        }
 
-    fn process_character_insert(&mut self, ch: char) {
-        let id = self.main_window.active_pane_mut().id();
+        self.views.retain(|(_, d), _| *d != doc);
+        self.alternate.retain(|_, d| *d != doc);
 
-        if let Some(state) = self.main_window.get_pane_state_mut(id) {
-            let start_index = state.start_line;
-            let (x, y) = state.cursor.pos().clone().into();
-            state.cursor.advance(if ch == '\t' {
-                self.config.tabs.tab_width as u32
-            }
-            else {
-                1
-            });
-
-            if let Some(doc_id) = self.get_pane_document_id(id) {
-                let op = editor_core::Operation::insert(doc_id.clone(), start_index, x, y, ch);
-                match self.engine.execute_on(op) {
-                    Err(_) => {
-                        // handle the error here
-                    },
-                    _ => {},
-                }
-                // this function needs document context information. No, because this is just
-                // moving the cursor. The context is needed when inserting an new line, to know
-                // if and how many tab need to be inserted.
+        if let Err(e) = self.engine.close_file(doc) {
+            println!("Unable to close the document: {:?}", e);
+        }
+    }
+
+    /// the arrow keys move around the file tree while it has the keys.
+    fn process_tree_key(&mut self, key: glutin::VirtualKeyCode) {
+        use glutin::VirtualKeyCode;
+
+        let tree = match self.tree.as_mut() {
+            Some(tree) if tree.prompt().is_none() => tree,
+            _ => return,
+        };
+
+        match key {
+            VirtualKeyCode::Down => tree.move_selection(1),
+            VirtualKeyCode::Up => tree.move_selection(-1),
+            VirtualKeyCode::Right => tree.expand(),
+            VirtualKeyCode::Left => tree.collapse(),
+            _ => return,
+        }
+        self.redraw = true;
+    }
+
+    /// opens the selected file of the tree in the active pane, or in a new split of it, and
+    /// gives the keys to the pane. A directory is expanded or collapsed instead.
+    fn open_tree_entry(&mut self, split: bool) {
+        let entry = match self.tree.as_mut() {
+            Some(tree) => match tree.selected_entry() {
+                Some(entry) if entry.is_dir => return tree.toggle(),
+                Some(entry) => entry.clone(),
+                None => return,
+            },
+            None => return,
+        };
+
+        let doc = match self.open_file(&entry.path.to_string_lossy()) {
+            Ok(doc) => doc,
+            Err(e) => {
+                println!("Failed to open {}: {:?}", entry.path.display(), e);
+                return;
             }
-            else {
-                panic!("Corrupted pane and document association");
+        };
+
+        if split {
+            match self.vertical_split_active_pane(doc) {
+                Ok(id) => self.main_window.focus_pane(id),
+                Err(e) => println!("Failed to split the pane: {:?}", e),
             }
+        } else {
+            let id = self.main_window.active_pane().id();
+            self.show_document(id, doc);
         }
+
+        self.set_tree_focus(false);
     }
-    
-    /*
-    pub fn render_window(&self) {
-        let pane = self.main_window.pane();
-        let (w, h): (f32, f32) = pane.size().clone().into();
 
-        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0); }
+    /// the file tree as the render thread draws it, with its selected entry marked.
+    fn tree_snapshot(&mut self) -> Option<render::PaneSnapshot> {
+        let (id, loc, size, cells) = {
+            let pane = self.main_window.tree_pane()?;
+            (pane.id(), *pane.loc(), *pane.size(), *pane.cells())
+        };
+        let tree = self.tree.as_mut()?;
+
+        let lines = tree.lines(cells.y as usize);
+        let row = tree.selected_row() as u32;
+
+        // the cursor is only shown at the end of a prompt.
+        let (cursor, cursor_style) = match tree.prompt() {
+            Some(prompt) => (
+                pane::Position::new(prompt.line().chars().count() as u32, cells.y.saturating_sub(1)),
+                render::CursorStyle::Solid(pane::CursorMode::Line),
+            ),
+            None => (pane::Position::new(0, row), render::CursorStyle::Hidden),
+        };
+
+        let selection = if tree.entries().is_empty() {
+            None
+        } else {
+            Some((pane::Position::new(0, row), pane::Position::new(0, row + 1)))
+        };
 
-        self.renderer.clear_frame(None);
-        self.renderer.set_view_port(w as f32, h as f32);
-        
-        self.blit_panes(pane);
+        Some(render::PaneSnapshot {
+            id,
+            x: loc.x,
+            y: loc.y,
+            size,
+            lines,
+            first_line: 0,
+            view_offset: 0,
+            cursor,
+            other_cursors: Vec::new(),
+            cursor_style,
+            cells,
+            selection,
+            highlights: Vec::new(),
+            tab_width: self.config.tabs.tab_width as u32,
+            style: render::TextStyle::default(),
+            damage: render::Damage::full(),
+        })
     }
 
-    fn blit_panes(&self, pane: &Pane) {
-        match pane.kind() {
-            PaneKind::Edit => {
-                let state = self.main_window.get_pane_state(pane.id()).unwrap();
-                self.renderer.draw_rendered_pane(self.main_window.window(), pane, state);
-            }
-            PaneKind::Vert(ref layout) => {
-                for pane in layout.iter() {
-                    let state = self.main_window.get_pane_state(pane.id()).unwrap();
-                    self.renderer.draw_rendered_pane(self.main_window.window(), pane, state);
-                }
-            },
-            PaneKind::Hor(ref layout) => {
-                for pane in layout.iter() {
-                    let state = self.main_window.get_pane_state(pane.id()).unwrap();
-                    self.renderer.draw_rendered_pane(self.main_window.window(), pane, state);
-                }
+    /// opens the finder over the panes, listing the files under the working directory.
+    fn open_finder(&mut self) {
+        let root = match std::env::current_dir() {
+            Ok(root) => root,
+            Err(e) => {
+                println!("Unable to find the project directory: {}", e);
+                return;
             }
+        };
+
+        self.finder = Some(Finder::open(root, self.main_window.window().create_proxy()));
+        self.redraw = true;
+    }
+
+    fn move_finder_selection(&mut self, down: bool) {
+        if let Some(finder) = self.finder.as_mut() {
+            finder.move_selection(down);
+            self.redraw = true;
         }
     }
-    */
 
-    pub fn swap_buffers(&self) {
-        self.main_window.window().swap_buffers()
+    fn close_finder(&mut self) {
+        self.finder = None;
+        self.finder_buffers = None;
+        self.redraw = true;
+    }
+
+    fn process_finder_input(&mut self, ch: char) {
+        match ch {
+            '\r' | '\n' => self.choose_finder_result(),
+            ESCAPE => self.close_finder(),
+            BACKSPACE => {
+                if let Some(finder) = self.finder.as_mut() {
+                    finder.pop();
+                }
+            }
+            _ if ch.is_control() => {}
+            _ => {
+                if let Some(finder) = self.finder.as_mut() {
+                    finder.push(ch);
+                }
+            }
+        }
+
+        self.redraw = true;
+    }
+
+    /// shows the file or buffer selected in the finder in the active pane.
+    fn choose_finder_result(&mut self) {
+        let finder = match self.finder.as_ref() {
+            Some(finder) => finder,
+            None => return,
+        };
+
+        let doc = match self.finder_buffers.as_ref() {
+            Some(buffers) => match finder.selected_item().and_then(|index| buffers.get(index)) {
+                Some(doc) => Some(*doc),
+                None => return,
+            },
+            None => None,
+        };
+        let path = finder.selected_path();
+
+        self.close_finder();
+
+        let doc = match (doc, path) {
+            (Some(doc), _) => doc,
+            (None, Some(path)) => match self.open_file(&path.to_string_lossy()) {
+                Ok(doc) => doc,
+                Err(e) => {
+                    println!("Failed to open {}: {:?}", path.display(), e);
+                    return;
+                }
+            },
+            (None, None) => return,
+        };
+
+        let id = self.main_window.active_pane().id();
+        self.show_document(id, doc);
+    }
+
+    /// the document of the file, opening it unless it is already open.
+    fn open_file(&mut self, path: &str) -> editor_core::Result<editor_core::DocID> {
+        match self.engine.find_document(path) {
+            Some(doc) => Ok(doc),
+            None => self.engine.open_document(path),
+        }
+    }
+
+    /// the panes of the finder, centered over the window: the query and the files that matched
+    /// it on the left, the start of the selected file on the right.
+    fn finder_snapshots(&self) -> Vec<render::PaneSnapshot> {
+        let finder = match self.finder.as_ref() {
+            Some(finder) => finder,
+            None => return Vec::new(),
+        };
+
+        let cell_size = self.config.cell_size;
+        let (w, h): (f64, f64) = self.main_window.window().get_physical_size().into();
+        let (w, h) = (w as f32, h as f32);
+
+        // the list and the preview each take half of the overlay, which covers most of the window.
+        let panes = if finder.has_preview() { 2.0 } else { 1.0 };
+        let cells = pane::Cells::new(
+            ((w * FINDER_WIDTH / panes) / cell_size.x).max(1.0) as u32,
+            ((h * FINDER_HEIGHT) / cell_size.y).max(2.0) as u32,
+        );
+        let size = Size::new(cells.x as f32 * cell_size.x, cells.y as f32 * cell_size.y);
+        let (x, y) = ((w - size.x * panes) / 2.0, (h - size.y) / 2.0);
+
+        // the first row is the query, the selected result stays in view below it.
+        let rows = cells.y as usize - 1;
+        let first = (finder.selected() + 1).saturating_sub(rows);
+        let results = finder.results().iter().skip(first).take(rows);
+
+        let mut lines = vec![format!("{}{}", FINDER_PROMPT, finder.query())];
+        let mut highlights = Vec::new();
+
+        for (row, candidate) in results.enumerate() {
+            lines.push(candidate.path.clone());
+            highlights.extend(candidate.positions.iter().map(|x| pane::Position::new(*x as u32, row as u32 + 1)));
+        }
+
+        let selected_row = (finder.selected() - first + 1) as u32;
+        let selection = if finder.results().is_empty() {
+            None
+        } else {
+            Some((pane::Position::new(0, selected_row), pane::Position::new(0, selected_row + 1)))
+        };
+
+        let query_width = (FINDER_PROMPT.chars().count() + finder.query().chars().count()) as u32;
+
+        let list = render::PaneSnapshot {
+            id: finder.list_pane(),
+            x,
+            y,
+            size,
+            lines,
+            first_line: 0,
+            view_offset: 0,
+            cursor: pane::Position::new(query_width.min(cells.x - 1), 0),
+            other_cursors: Vec::new(),
+            cursor_style: render::CursorStyle::Solid(pane::CursorMode::Line),
+            cells,
+            selection,
+            highlights,
+            tab_width: self.config.tabs.tab_width as u32,
+            style: render::TextStyle::default(),
+            damage: render::Damage::full(),
+        };
+
+        if !finder.has_preview() {
+            return vec![list];
+        }
+
+        let preview = render::PaneSnapshot {
+            id: finder.preview_pane(),
+            x: x + size.x,
+            lines: finder.preview().iter().take(cells.y as usize).cloned().collect(),
+            cursor: pane::Position::new(0, 0),
+            cursor_style: render::CursorStyle::Hidden,
+            selection: None,
+            highlights: Vec::new(),
+            ..list.clone()
+        };
+
+        vec![list, preview]
+    }
+
+    /// redraws the cursor of the focused pane when the blink shows or hides it.
+    fn update_blink(&mut self) {
+        let visible = self.blink.visible();
+        if visible == self.cursor_visible {
+            return;
+        }
+
+        self.cursor_visible = visible;
+
+        let id = self.main_window.active_pane().id();
+        if let Some(state) = self.main_window.get_pane_state_mut(id) {
+            state.damage.add_row(state.cursor.pos().y);
+        }
+    }
+
+    pub fn process_event(&mut self, event: &glutin::Event) -> bool {
+        use glutin::*;
+        match *event {
+            // LoopDestroyed => running = false,
+            Event::DeviceEvent { .. } => true,
+            Event::WindowEvent { ref event, .. } => match event {
+                WindowEvent::KeyboardInput { ref input, .. } => {
+                    println!("{:?}", input);
+                    self.modifiers = input.modifiers;
+                    self.blink.reset();
+
+                    if input.state == ElementState::Pressed && self.finder.is_some() {
+                        match input.virtual_keycode {
+                            Some(VirtualKeyCode::Up) => self.move_finder_selection(false),
+                            Some(VirtualKeyCode::Down) => self.move_finder_selection(true),
+                            _ => {}
+                        }
+                    } else if input.state == ElementState::Pressed && self.tree_focused {
+                        if let Some(key) = input.virtual_keycode {
+                            self.process_tree_key(key);
+                        }
+                    } else if input.state == ElementState::Pressed && matches!(self.mode, EditorMode::CommandInput) {
+                        match input.virtual_keycode {
+                            Some(VirtualKeyCode::Up) => self.recall_command(true),
+                            Some(VirtualKeyCode::Down) => self.recall_command(false),
+                            _ => {}
+                        }
+                    }
+
+                    if input.state == ElementState::Pressed && input.modifiers.ctrl {
+                        match input.virtual_keycode {
+                            Some(VirtualKeyCode::P) => self.open_finder(),
+                            Some(VirtualKeyCode::Key6) => self.alternate_buffer(),
+                            Some(VirtualKeyCode::B) => self.toggle_tree(),
+                            Some(VirtualKeyCode::Equals) | Some(VirtualKeyCode::Add) => self.zoom(1),
+                            Some(VirtualKeyCode::Minus) | Some(VirtualKeyCode::Subtract) => self.zoom(-1),
+                            Some(VirtualKeyCode::Key0) | Some(VirtualKeyCode::Numpad0) => self.zoom(0),
+                            Some(VirtualKeyCode::Up) if input.modifiers.alt => self.add_cursor_vertically(false),
+                            Some(VirtualKeyCode::Down) if input.modifiers.alt => self.add_cursor_vertically(true),
+                            Some(VirtualKeyCode::D) => self.add_cursor_at_next_occurrence(),
+                            Some(VirtualKeyCode::C) => self.copy_selection(),
+                            Some(VirtualKeyCode::V) => self.paste(),
+                            Some(VirtualKeyCode::L) if input.modifiers.shift => self.split_selection_into_lines(),
+                            Some(VirtualKeyCode::Z) if input.modifiers.shift => self.undo(false),
+                            Some(VirtualKeyCode::Z) => self.undo(true),
+                            Some(VirtualKeyCode::Y) => self.undo(false),
+                            _ => {}
+                        }
+                    }
+                    true
+                }
+                // Maybe using KeyboardInput and processing that would
+                // give a better using experience instead of using ReceivedCharacter
+                WindowEvent::ReceivedCharacter(ch) => {
+                    println!("{:?}", event);
+                    println!("Character Input: {}", *ch);
+
+                    // the zoom keys and the editing shortcuts were handled as key presses.
+                    let shortcut = self.modifiers.ctrl && (['=', '+', '-', '0', '6', '^'].contains(ch) || ch.is_control());
+                    if !shortcut {
+                        self.process_character_input(*ch);
+                    }
+                    true
+                }
+                WindowEvent::CursorMoved { position, .. } => {
+                    // panes are laid out in physical pixels.
+                    let dpi_factor = self.main_window.window().dpi_factor();
+                    let position = position.to_physical(dpi_factor);
+                    self.on_mouse_move(position.x as f32, position.y as f32);
+                    true
+                }
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => {
+                    self.blink.reset();
+                    match state {
+                        ElementState::Pressed => self.on_mouse_press(),
+                        ElementState::Released => self.mouse.release(),
+                    }
+                    true
+                }
+                WindowEvent::Resized(_) => {
+                    if let Err(e) = self.on_resize() {
+                        println!("Failed to resize: {:?}", e);
+                    }
+                    true
+                }
+                WindowEvent::HiDpiFactorChanged(dpi_factor) => {
+                    if let Err(e) = self.on_dpi_changed(*dpi_factor) {
+                        println!("Failed to change dpi: {:?}", e);
+                    }
+                    true
+                }
+                // the window has to be drawn again after it was uncovered.
+                WindowEvent::Refresh => {
+                    self.redraw = true;
+                    true
+                }
+                WindowEvent::CloseRequested | WindowEvent::Destroyed => false,
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+    /*
+    pub fn active_pane(&self) -> Option<&pane::Pane> {
+        None
+    }
+
+    fn get_active_pane(&mut self) -> Option<&mut pane::Pane> {
+        None
+    }
+
+    */
+
+    fn on_mouse_press(&mut self) {
+        let (x, y) = (self.mouse.pos.x, self.mouse.pos.y);
+
+        // grabbing a split border takes priority over the panes on either side of it.
+        if let Some(border) = self.main_window.border_at(x, y, mouse::BORDER_TOLERANCE) {
+            self.mouse.drag = Some(Drag::Border(border));
+            return;
+        }
+
+        if self.on_tree_press(x, y) {
+            return;
+        }
+
+        let id = match self.main_window.pane_at(x, y) {
+            Some(id) => id,
+            None => return,
+        };
+
+        self.main_window.focus_pane(id);
+        self.set_tree_focus(false);
+
+        let cell = match self.pane_cell_at(id, x, y) {
+            Some(cell) => cell,
+            None => return,
+        };
+
+        let count = self.mouse.click(cell);
+        let pos = match self.document_position(id, cell) {
+            Some(pos) => pos,
+            None => return,
+        };
+
+        let mode = match count {
+            1 => pane::SelectionMode::Char,
+            2 => pane::SelectionMode::Word,
+            _ => pane::SelectionMode::Line,
+        };
+
+        // the old selection is cleared from the lines it covered.
+        let old = self
+            .main_window
+            .get_pane_state(id)
+            .and_then(|state| state.selection.as_ref())
+            .map(|selection| (selection.anchor.y, selection.head.y));
+        if let Some((anchor, head)) = old {
+            self.damage_lines(id, anchor.min(head) as usize, anchor.max(head) as usize);
+        }
+
+        // a click leaves a single cursor.
+        self.set_cursors(id, Vec::new());
+
+        if let Some(state) = self.main_window.get_pane_state_mut(id) {
+            state.selection = Some(pane::Selection::new(pos, pos, mode));
+        }
+        self.damage_lines(id, pos.y as usize, pos.y as usize);
+
+        self.place_cursor(id, pos);
+        self.mouse.drag = Some(Drag::Select(id));
+    }
+
+    /// a click on the file tree selects the entry under it and gives the tree the keys, a
+    /// double click opens it. Returns false if the point is not on the tree.
+    fn on_tree_press(&mut self, x: f32, y: f32) -> bool {
+        let row = match self.main_window.tree_pane() {
+            Some(pane) if pane.contains(x, y) => ((y - pane.loc().y) / self.config.cell_size.y) as u32,
+            _ => return false,
+        };
+
+        let count = self.mouse.click(pane::Position::new(0, row));
+        self.set_tree_focus(true);
+        self.redraw = true;
+
+        let selected = match self.tree.as_mut() {
+            Some(tree) if tree.prompt().is_none() => tree.select_row(row as usize),
+            _ => false,
+        };
+
+        if selected && count >= 2 {
+            self.open_tree_entry(false);
+        }
+        true
+    }
+
+    fn on_mouse_move(&mut self, x: f32, y: f32) {
+        self.mouse.pos = Size::new(x, y);
+
+        match self.mouse.drag {
+            Some(Drag::Select(id)) => {
+                let pos = match self
+                    .pane_cell_at(id, x, y)
+                    .and_then(|cell| self.document_position(id, cell))
+                {
+                    Some(pos) => pos,
+                    None => return,
+                };
+
+                let mut moved = None;
+                if let Some(state) = self.main_window.get_pane_state_mut(id) {
+                    if let Some(ref mut selection) = state.selection {
+                        moved = Some(selection.head.y);
+                        selection.head = pos;
+                    }
+                }
+
+                // the lines between the old and new end of the selection were added or removed from it.
+                if let Some(head) = moved {
+                    self.damage_lines(id, head.min(pos.y) as usize, head.max(pos.y) as usize);
+                }
+
+                self.place_cursor(id, pos);
+            }
+            Some(Drag::Border(border)) => {
+                if let Err(e) = self.main_window.drag_border(&border, x, y) {
+                    println!("Failed to resize split: {:?}", e);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// the cell of the pane under the point. Points outside of the pane are clamped
+    /// to its edges so dragging past a pane keeps extending the selection.
+    fn pane_cell_at(&self, id: pane::PaneID, x: f32, y: f32) -> Option<pane::Position> {
+        let pane = self.main_window.get_pane(id)?;
+        let cell_size = self.config.cell_size;
+        let cells = pane.cells();
+        let loc = pane.loc();
+
+        let column = ((x - loc.x) / cell_size.x).max(0.0) as u32;
+        let row = ((y - loc.y) / cell_size.y).max(0.0) as u32;
+
+        Some(pane::Position::new(
+            column.min(cells.x.saturating_sub(1)),
+            row.min(cells.y.saturating_sub(1)),
+        ))
+    }
+
+    /// converts a cell of a pane into a (column, line) of its document.
+    fn document_position(&self, id: pane::PaneID, cell: pane::Position) -> Option<pane::Position> {
+        let state = self.main_window.get_pane_state(id)?;
+        let document = self.engine.get_document(*self.get_pane_document_id(id)?)?;
+        let tab_width = self.config.tabs.tab_width as u32;
+
+        let last_line = document.len_lines().saturating_sub(1);
+        let line = (state.start_line + cell.y as usize).min(last_line);
+        let column = document.cell_to_column(line, state.view_offset as u32 + cell.x, tab_width);
+
+        Some(pane::Position::new(column as u32, line as u32))
+    }
+
+    /// moves the cursor of the pane to a (column, line) of its document,
+    /// scrolling the pane if the position is not visible.
+    fn place_cursor(&mut self, id: pane::PaneID, pos: pane::Position) {
+        let tab_width = self.config.tabs.tab_width as u32;
+        // a pane squeezed below a cell still shows the row and column of its cursor.
+        let cells = match self.main_window.get_pane(id) {
+            Some(pane) => pane::Cells::new(pane.cells().x.max(1), pane.cells().y.max(1)),
+            None => return,
+        };
+
+        let document = match self.docs.get(&id).and_then(|doc| self.engine.get_document(*doc)) {
+            Some(document) => document,
+            None => return,
+        };
+
+        let cell = document.column_to_cell(pos.y as usize, pos.x as usize, tab_width) as usize;
+        let line = pos.y as usize;
+
+        if let Some(state) = self.main_window.get_pane_state_mut(id) {
+            let view = (state.start_line, state.view_offset);
+
+            if line < state.start_line {
+                state.start_line = line;
+            } else if line >= state.start_line + cells.y as usize {
+                state.start_line = line + 1 - cells.y as usize;
+            }
+
+            if cell < state.view_offset {
+                state.view_offset = cell;
+            } else if cell >= state.view_offset + cells.x as usize {
+                state.view_offset = cell + 1 - cells.x as usize;
+            }
+
+            // the rows the cursor left and moved to, every row when the pane scrolled.
+            if view != (state.start_line, state.view_offset) {
+                state.damage.add_all();
+            } else {
+                state.damage.add_row(state.cursor.pos().y);
+            }
+
+            state.cursor.set_pos(pane::Position::new(
+                (cell - state.view_offset) as u32,
+                (line - state.start_line) as u32,
+            ));
+            state.damage.add_row(state.cursor.pos().y);
+        }
+    }
+
+    pub fn editor_mode(&self) -> EditorMode {
+        self.mode
+    }
+
+    /// switches modes and gives the cursors the shape configured for the new mode.
+    fn set_mode(&mut self, mode: EditorMode) {
+        self.mode = mode;
+        self.main_window.set_cursor_mode(self.cursor_mode());
+    }
+
+    /// the shape of the cursor in the current mode, command input keeps the normal mode cursor.
+    fn cursor_mode(&self) -> pane::CursorMode {
+        match self.mode {
+            EditorMode::Insert => self.config.cursor.insert,
+            EditorMode::Normal | EditorMode::CommandInput | EditorMode::Visual => self.config.cursor.normal,
+        }
+    }
+
+    fn get_pane_document_id(&self, pane_id: pane::PaneID) -> Option<&editor_core::DocID> {
+        self.docs.get(&pane_id)
+    }
+
+    fn process_character_input(&mut self, ch: char) {
+        // the finder takes the input while it is open.
+        if self.finder.is_some() {
+            self.process_finder_input(ch);
+            return;
+        }
+
+        if self.tree_focused {
+            self.process_tree_input(ch);
+            return;
+        }
+
+        match self.editor_mode() {
+            EditorMode::Normal => {
+                if ch == ':' {
+                    self.set_mode(EditorMode::CommandInput);
+                } else if ch == ESCAPE {
+                    let id = self.main_window.active_pane().id();
+                    self.set_cursors(id, Vec::new());
+                }
+                // otherwise the input is ignored.
+            }
+            EditorMode::CommandInput => self.process_command_input(ch),
+            EditorMode::Insert if ch == ESCAPE => self.set_mode(EditorMode::Normal),
+            EditorMode::Insert => self.process_character_insert(ch),
+            _ => (),
+        }
+    }
+
+    fn process_command_input(&mut self, ch: char) {
+        // whatever is typed edits the recalled command as a new one.
+        self.history_index = None;
+
+        match ch {
+            '\r' | '\n' => {
+                let input = std::mem::take(&mut self.command);
+                self.set_mode(EditorMode::Normal);
+
+                if !input.is_empty() {
+                    push_history(&mut self.history, &input);
+                }
+
+                match Command::parse(&input) {
+                    Ok(command) => self.execute_command(command),
+                    Err(e) => println!("Invalid command '{}': {:?}", input, e),
+                }
+            }
+            ESCAPE => {
+                self.command.clear();
+                self.set_mode(EditorMode::Normal);
+            }
+            BACKSPACE => {
+                // backspacing past the start of the command leaves command input.
+                if self.command.pop().is_none() {
+                    self.set_mode(EditorMode::Normal);
+                }
+            }
+            _ => self.command.push(ch),
+        }
+    }
+
+    /// replaces the command being typed with an older or newer one of the history, going past
+    /// the newest one clears it.
+    fn recall_command(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        let index = match (self.history_index, older) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => return,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < self.history.len() => Some(i + 1),
+            (Some(_), false) => None,
+        };
+
+        self.history_index = index;
+        self.command = index.map(|i| self.history[i].clone()).unwrap_or_default();
+    }
+
+    fn execute_command(&mut self, command: Command) {
+        let id = self.main_window.active_pane().id();
+        let doc = match self.get_pane_document_id(id) {
+            Some(doc) => *doc,
+            None => return,
+        };
+
+        let op = match command {
+            Command::Write => editor_core::Operation::write_file(doc),
+            Command::Set(command::SetOption::FileFormat(line_ending)) => {
+                editor_core::Operation::set_line_ending(doc, line_ending)
+            }
+            Command::Set(command::SetOption::FileEncoding(encoding)) => {
+                editor_core::Operation::set_encoding(doc, encoding)
+            }
+            // the buffer commands change what the pane shows rather than the document.
+            Command::ListBuffers => return self.open_buffer_picker(),
+            Command::Buffer(target) => return self.go_to_buffer(target),
+            Command::NextBuffer => return self.cycle_buffer(true),
+            Command::PreviousBuffer => return self.cycle_buffer(false),
+        };
+
+        match self.engine.execute_on(op) {
+            Err(editor_core::Error::ReadOnly) => {
+                println!("{}", READ_ONLY_WARNING)
+            }
+            Err(e) => println!("Command failed: {:?}", e),
+            _ => {}
+        }
+    }
+
+    fn process_character_insert(&mut self, ch: char) {
+        let id = self.main_window.active_pane().id();
+        let config = self.config.clone();
+        let tabs = &config.tabs;
+
+        self.edit_at_cursors(id, |document, pos| {
+            // the context of the line is needed to know if and how much to indent.
+            let before = document.line_before(pos.y as usize, pos.x as usize);
+            let (style, language) = (document.indent_style(), document.language());
+
+            match ch {
+                BACKSPACE => (indent::backspace_len(&before, tabs), 0, String::new()),
+                DELETE => {
+                    // at the end of a line the line break is removed, there is nothing after the last line.
+                    let (line, column) = (pos.y as usize, pos.x as usize);
+                    let at_end = column >= document.line_len(line) && line + 1 >= document.len_lines();
+                    (0, if at_end { 0 } else { 1 }, String::new())
+                }
+                // Enter is reported as a carriage return on most platforms.
+                '\r' | '\n' => (0, 0, indent::newline(&before, style, language, tabs)),
+                '\t' if tabs.expand_tab => {
+                    let cell = before.chars().map(|c| editor_core::char_width(c, tabs.tab_width as u32)).sum();
+                    (0, 0, indent::soft_tab(cell, tabs))
+                }
+                _ => {
+                    // closing a block on an otherwise empty line moves it back a level.
+                    let only_indent = !before.is_empty() && indent::indentation(&before).len() == before.len();
+                    if tabs.smart_indent && only_indent && language.closes_block(ch) {
+                        (indent::dedent_len(&before, style), 0, ch.to_string())
+                    } else {
+                        (0, 0, ch.to_string())
+                    }
+                }
+            }
+        });
+    }
+
+    /// copies the selection of the active pane to the register pasted from.
+    fn copy_selection(&mut self) {
+        let id = self.main_window.active_pane().id();
+        let selection = match self.main_window.get_pane_state(id).and_then(|state| state.selection.clone()) {
+            Some(selection) => selection,
+            None => return,
+        };
+
+        let text = match self.get_pane_document_id(id).and_then(|doc| self.engine.get_document(*doc)) {
+            Some(document) => selection_text(document, &selection),
+            None => return,
+        };
+        self.register = text;
+    }
+
+    /// puts the text of the register at every cursor of the active pane.
+    fn paste(&mut self) {
+        if self.register.is_empty() {
+            return;
+        }
+
+        let id = self.main_window.active_pane().id();
+        let text = self.register.clone();
+        self.edit_at_cursors(id, |_, _| (0, 0, text.clone()));
+    }
+
+    /// makes an edit at every cursor of the pane as a single step of the document's history.
+    /// The edit gives the number of characters it removes before the cursor and after it, and
+    /// the text put in their place; the cursor is left after the text. Cursors that end up
+    /// together are merged.
+    fn edit_at_cursors<F>(&mut self, id: pane::PaneID, mut edit: F)
+    where
+        F: FnMut(&editor_core::Document, pane::Position) -> (usize, usize, String),
+    {
+        let doc_id = match self.get_pane_document_id(id) {
+            Some(doc_id) => *doc_id,
+            None => panic!("Corrupted pane and document association"),
+        };
+
+        let primary = match self.cursor_position(id) {
+            Some(pos) => pos,
+            None => return,
+        };
+        let positions = self.cursor_positions(id);
+
+        // the character index of each cursor, the characters removed before it and the text put there.
+        let mut edits: Vec<(usize, usize, String, bool)> = Vec::with_capacity(positions.len());
+        let lines = {
+            let document = match self.engine.get_mut_document(doc_id) {
+                Some(document) => document,
+                None => return,
+            };
+
+            // a mapped document is only loaded once it is known that it can be edited.
+            if document.is_read_only() {
+                println!("{}", READ_ONLY_WARNING);
+                return;
+            }
+            if let Err(e) = document.materialize() {
+                println!("Failed to load the document: {:?}", e);
+                return;
+            }
+
+            for pos in positions.iter() {
+                let (before, after, text) = edit(document, *pos);
+                // removing after the cursor is removing before the character index past it.
+                let index = document.char_index(pos.y as usize, pos.x as usize) + after;
+                edits.push((index, before + after, text, (pos.x, pos.y) == (primary.x, primary.y)));
+            }
+
+            document.len_lines()
+        };
+
+        // a cursor never removes text in front of the cursor before it.
+        let mut previous = 0;
+        for (index, remove, _, _) in edits.iter_mut() {
+            *remove = (*remove).min(*index - previous);
+            previous = *index;
+        }
+
+        // the edits are made from the bottom up so the indices above them stay the same.
+        let ops = edits
+            .iter()
+            .rev()
+            .map(|(index, remove, text, _)| editor_core::Operation::replace(doc_id, *index, *remove, text))
+            .collect();
+
+        if let Err(e) = self.engine.execute_group(doc_id, ops) {
+            match e {
+                editor_core::Error::ReadOnly => {
+                    println!("{}", READ_ONLY_WARNING)
+                }
+                e => println!("Failed to execute operation: {:?}", e),
+            }
+            return;
+        }
+
+        // each cursor moves along with the edits made before it.
+        let mut shift: isize = 0;
+        let mut ends = Vec::with_capacity(edits.len());
+        for (index, remove, text, primary) in edits.iter() {
+            let inserted = text.chars().count() as isize;
+            shift += inserted - *remove as isize;
+            ends.push(((*index as isize + shift) as usize, *primary));
+        }
+
+        let document = match self.engine.get_document(doc_id) {
+            Some(document) => document,
+            None => return,
+        };
+
+        let position = |index: usize| {
+            let (line, column) = document.position_of(index);
+            pane::Position::new(column as u32, line as u32)
+        };
+        let primary = ends.iter().find(|(_, primary)| *primary).map_or(primary, |(end, _)| position(*end));
+        let others: Vec<pane::Position> = ends.iter().filter(|(_, primary)| !*primary).map(|(end, _)| position(*end)).collect();
+
+        // the lines below move when lines are added or removed.
+        let first = positions[0].y as usize;
+        let last = positions[positions.len() - 1].y as usize;
+        if document.len_lines() == lines {
+            self.damage_document(doc_id, first, Some(last));
+        } else {
+            self.damage_document(doc_id, first, None);
+        }
+
+        self.place_cursor(id, primary);
+        self.set_cursors(id, others);
+    }
+
+    /// takes back, or makes again, the last step of the active pane's document. The pane is
+    /// left with a single cursor where the step was made.
+    fn undo(&mut self, undo: bool) {
+        let id = self.main_window.active_pane().id();
+        let doc_id = match self.get_pane_document_id(id) {
+            Some(doc_id) => *doc_id,
+            None => return,
+        };
+
+        let result = if undo { self.engine.undo(doc_id) } else { self.engine.redo(doc_id) };
+        let index = match result {
+            Ok(Some(index)) => index,
+            Ok(None) => return,
+            Err(e) => {
+                println!("Failed to undo: {:?}", e);
+                return;
+            }
+        };
+
+        let pos = match self.engine.get_document(doc_id) {
+            Some(document) => {
+                let (line, column) = document.position_of(index);
+                pane::Position::new(column as u32, line as u32)
+            }
+            None => return,
+        };
+
+        self.damage_document(doc_id, 0, None);
+        self.set_cursors(id, Vec::new());
+        self.place_cursor(id, pos);
+    }
+
+    /// adds a cursor on the line above the top cursor, or below the bottom one, in the same cell.
+    fn add_cursor_vertically(&mut self, down: bool) {
+        let id = self.main_window.active_pane().id();
+        let tab_width = self.config.tabs.tab_width as u32;
+        let positions = self.cursor_positions(id);
+
+        let document = match self.get_pane_document_id(id).and_then(|doc| self.engine.get_document(*doc)) {
+            Some(document) => document,
+            None => return,
+        };
+
+        let edge = if down { positions[positions.len() - 1] } else { positions[0] };
+        let line = match down {
+            true if (edge.y as usize) + 1 < document.len_lines() => edge.y + 1,
+            false if edge.y > 0 => edge.y - 1,
+            _ => return,
+        };
+
+        let cell = document.column_to_cell(edge.y as usize, edge.x as usize, tab_width);
+        let column = document.cell_to_column(line as usize, cell, tab_width);
+
+        let mut others = self.other_cursors(id);
+        others.push(pane::Position::new(column as u32, line));
+        self.set_cursors(id, others);
+    }
+
+    /// adds a cursor to the next occurrence of the selected text, or of the word under the
+    /// cursor, at the same place in it as the cursor.
+    fn add_cursor_at_next_occurrence(&mut self) {
+        let id = self.main_window.active_pane().id();
+        let doc_id = match self.get_pane_document_id(id) {
+            Some(doc_id) => *doc_id,
+            None => return,
+        };
+
+        let primary = match self.cursor_position(id) {
+            Some(pos) => pos,
+            None => return,
+        };
+        let selection = self.main_window.get_pane_state(id).and_then(|state| state.selection.clone());
+        let positions = self.cursor_positions(id);
+
+        // finding the occurrences by character index would load all of a mapped file.
+        let document = match self.engine.get_mut_document(doc_id) {
+            Some(document) if document.is_large() => {
+                println!("Adding cursors at occurrences is turned off for large files");
+                return;
+            }
+            Some(document) => document,
+            None => return,
+        };
+
+        // the occurrence the cursor is in, as character indices.
+        let (start, end) = match selection {
+            Some(ref selection) => {
+                let (start, end) = selection_range(document, selection);
+                (
+                    document.char_index(start.y as usize, start.x as usize),
+                    document.char_index(end.y as usize, end.x as usize),
+                )
+            }
+            None => {
+                let (start, end) = document.word_bounds(primary.y as usize, primary.x as usize);
+                let line = document.char_index(primary.y as usize, 0);
+                (line + start, line + end)
+            }
+        };
+
+        let text = document.text(start, end);
+        if text.trim().is_empty() {
+            return;
+        }
+        push_history(&mut self.search_history, &text);
+
+        let cursor = document.char_index(primary.y as usize, primary.x as usize);
+        let offset = cursor.saturating_sub(start).min(end - start);
+
+        // the search starts after the occurrence of the last cursor so each press adds the next one.
+        let last = positions[positions.len() - 1];
+        let from = document.char_index(last.y as usize, last.x as usize).saturating_sub(offset) + 1;
+
+        let index = match document.find(&text, from) {
+            Some(index) => index + offset,
+            None => return,
+        };
+
+        let (line, column) = document.position_of(index);
+        let pos = pane::Position::new(column as u32, line as u32);
+
+        // every occurrence already has a cursor.
+        if positions.iter().any(|p| (p.x, p.y) == (pos.x, pos.y)) {
+            return;
+        }
+
+        let mut others = self.other_cursors(id);
+        others.push(pos);
+        self.set_cursors(id, others);
+    }
+
+    /// replaces the selection with a cursor at the end of each of its lines.
+    fn split_selection_into_lines(&mut self) {
+        let id = self.main_window.active_pane().id();
+        let selection = match self.main_window.get_pane_state(id).and_then(|state| state.selection.clone()) {
+            Some(selection) => selection,
+            None => return,
+        };
+
+        let document = match self.get_pane_document_id(id).and_then(|doc| self.engine.get_document(*doc)) {
+            Some(document) => document,
+            None => return,
+        };
+
+        let (start, end) = selection_range(document, &selection);
+        let mut positions: Vec<pane::Position> = (start.y..=end.y)
+            .map(|line| match line == end.y {
+                true => pane::Position::new(end.x, line),
+                false => pane::Position::new(document.line_len(line as usize) as u32, line),
+            })
+            .collect();
+
+        if let Some(state) = self.main_window.get_pane_state_mut(id) {
+            state.selection = None;
+        }
+        self.damage_lines(id, start.y as usize, end.y as usize);
+
+        // the cursor stays at the end of the selection.
+        if let Some(last) = positions.pop() {
+            self.place_cursor(id, last);
+        }
+        self.set_cursors(id, positions);
+    }
+
+    /// the (column, line) of every cursor of the pane, from the top of the document down.
+    fn cursor_positions(&self, id: pane::PaneID) -> Vec<pane::Position> {
+        let mut positions: Vec<pane::Position> = self.cursor_position(id).into_iter().collect();
+        positions.extend(self.other_cursors(id));
+
+        positions.sort_by_key(|pos| (pos.y, pos.x));
+        positions.dedup_by_key(|pos| (pos.y, pos.x));
+        positions
+    }
+
+    fn other_cursors(&self, id: pane::PaneID) -> Vec<pane::Position> {
+        match self.main_window.get_pane_state(id) {
+            Some(state) => state.cursors.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /// replaces the other cursors of the pane, cursors on the same character are merged.
+    fn set_cursors(&mut self, id: pane::PaneID, mut cursors: Vec<pane::Position>) {
+        cursors.sort_by_key(|pos| (pos.y, pos.x));
+        cursors.dedup_by_key(|pos| (pos.y, pos.x));
+
+        if let Some(primary) = self.cursor_position(id) {
+            cursors.retain(|pos| (pos.x, pos.y) != (primary.x, primary.y));
+        }
+
+        let old = match self.main_window.get_pane_state_mut(id) {
+            Some(state) => std::mem::replace(&mut state.cursors, cursors.clone()),
+            None => return,
+        };
+
+        // the rows the cursors left and moved to.
+        for pos in old.iter().chain(cursors.iter()) {
+            self.damage_lines(id, pos.y as usize, pos.y as usize);
+        }
+    }
+
+    /// damages the rows of the pane showing the lines of its document, lines outside of
+    /// the view are ignored.
+    fn damage_lines(&mut self, id: pane::PaneID, first: usize, last: usize) {
+        let rows = match self.main_window.get_pane(id) {
+            Some(pane) => pane.cells().y as usize,
+            None => return,
+        };
+
+        if let Some(state) = self.main_window.get_pane_state_mut(id) {
+            let start = state.start_line;
+            if rows == 0 || last < start || first >= start + rows {
+                return;
+            }
+
+            let first = first.max(start) - start;
+            let last = last.min(start + rows - 1) - start;
+            state.damage.add_rows(first as u32..last as u32 + 1);
+        }
+    }
+
+    /// damages the lines of the document in every pane showing it. Without a last line
+    /// everything from the first line to the bottom of the panes is damaged.
+    fn damage_document(&mut self, doc_id: editor_core::DocID, first: usize, last: Option<usize>) {
+        let panes: Vec<pane::PaneID> = self
+            .docs
+            .iter()
+            .filter(|(_, doc)| **doc == doc_id)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in panes {
+            self.damage_lines(id, first, last.unwrap_or(usize::MAX));
+        }
+    }
+
+    /// the (column, line) of the document the pane's cursor is on.
+    fn cursor_position(&self, id: pane::PaneID) -> Option<pane::Position> {
+        let state = self.main_window.get_pane_state(id)?;
+        let cursor = state.cursor.pos();
+        self.document_position(id, pane::Position::new(cursor.x, cursor.y))
+    }
+}
+
+/// adds an entry to the end of a history, unless it is the last one already, keeping the most
+/// recent ones a session saves.
+fn push_history(history: &mut Vec<String>, entry: &str) {
+    if history.last().map(String::as_str) == Some(entry) {
+        return;
+    }
+
+    history.push(entry.to_string());
+    if history.len() > session::HISTORY_LIMIT {
+        history.remove(0);
+    }
+}
+
+/// the text of the selection, read a line at a time so a mapped document is not loaded.
+fn selection_text(document: &editor_core::Document, selection: &pane::Selection) -> String {
+    let (start, end) = selection_range(document, selection);
+    let mut text = String::new();
+
+    for line in start.y..=end.y {
+        let slice = match document.line(line as usize) {
+            Some(slice) => slice,
+            None => break,
+        };
+
+        let first = if line == start.y { start.x as usize } else { 0 };
+        let chars = slice.chars().skip(first);
+        if line == end.y {
+            text.extend(chars.take((end.x as usize).saturating_sub(first)));
+        } else {
+            text.extend(chars);
+        }
+    }
+
+    text
+}
+
+/// the name a buffer is listed by, its path from the working directory when it is under it.
+fn buffer_name(document: &editor_core::Document) -> String {
+    match (document.path(), std::env::current_dir()) {
+        (Some(path), Ok(dir)) => match std::path::Path::new(path).strip_prefix(&dir) {
+            Ok(relative) => relative.to_string_lossy().to_string(),
+            Err(_) => path.to_string(),
+        },
+        _ => document.name(),
+    }
+}
+
+/// the range covered by a selection as ordered (start, end) document positions.
+/// Word and line selections always include the whole word or line they started on.
+pub fn selection_range(
+    document: &editor_core::Document,
+    selection: &pane::Selection,
+) -> (pane::Position, pane::Position) {
+    use pane::{Position, SelectionMode};
+
+    let (anchor, head) = (selection.anchor, selection.head);
+    let forward = (anchor.y, anchor.x) <= (head.y, head.x);
+
+    match selection.mode {
+        SelectionMode::Char => selection.ordered(),
+        SelectionMode::Word => {
+            let (anchor_start, anchor_end) = document.word_bounds(anchor.y as usize, anchor.x as usize);
+            let (head_start, head_end) = document.word_bounds(head.y as usize, head.x as usize);
+
+            if forward {
+                (Position::new(anchor_start as u32, anchor.y), Position::new(head_end as u32, head.y))
+            } else {
+                (Position::new(head_start as u32, head.y), Position::new(anchor_end as u32, anchor.y))
+            }
+        }
+        SelectionMode::Line => {
+            let (first, last) = if forward { (anchor.y, head.y) } else { (head.y, anchor.y) };
+            (
+                Position::new(0, first),
+                Position::new(document.line_len(last as usize) as u32, last),
+            )
+        }
     }
 }
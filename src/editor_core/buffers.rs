@@ -0,0 +1,143 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{DocID, Error, Result};
+
+/// The open documents in the order they were opened. Like vim's buffer list each one has a
+/// number, which is not given to another document after it is closed.
+#[derive(Debug, Clone)]
+pub struct BufferList {
+    /// the documents by their number, which is the order they were opened in.
+    buffers: BTreeMap<usize, DocID>,
+    /// the number of each document, so closing one does not search the list.
+    numbers: HashMap<DocID, usize>,
+    next: usize,
+}
+
+impl BufferList {
+    pub fn new() -> Self {
+        Self {
+            buffers: BTreeMap::new(),
+            numbers: HashMap::new(),
+            next: 1,
+        }
+    }
+
+    /// adds the document to the end of the list, returning its number.
+    pub fn add(&mut self, doc: DocID) -> usize {
+        let number = self.next;
+        self.next += 1;
+        self.buffers.insert(number, doc);
+        self.numbers.insert(doc, number);
+
+        number
+    }
+
+    pub fn remove(&mut self, doc: DocID) {
+        if let Some(number) = self.numbers.remove(&doc) {
+            self.buffers.remove(&number);
+        }
+    }
+
+    /// the numbers and documents of the buffers in order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, DocID)> + '_ {
+        self.buffers.iter().map(|(number, doc)| (*number, *doc))
+    }
+
+    pub fn get(&self, number: usize) -> Result<DocID> {
+        self.buffers.get(&number).cloned().ok_or(Error::InvalidBuffer(number))
+    }
+
+    /// the buffer after the document, or before it, wrapping around the ends of the list.
+    pub fn cycle(&self, doc: DocID, forward: bool) -> Option<DocID> {
+        let number = *self.numbers.get(&doc)?;
+
+        let next = if forward {
+            self.buffers.range(number + 1..).next().or_else(|| self.buffers.iter().next())
+        } else {
+            self.buffers.range(..number).next_back().or_else(|| self.buffers.iter().next_back())
+        };
+        next.map(|(_, doc)| *doc)
+    }
+}
+
+impl Default for BufferList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// the buffer a name given to :b refers to. A buffer whose name is the query wins, otherwise
+/// the query has to be part of the name of exactly one buffer.
+pub fn match_name(names: &[(DocID, String)], query: &str) -> Result<DocID> {
+    let exact = names.iter().find(|(_, name)| name == query || file_name(name) == query);
+    if let Some((doc, _)) = exact {
+        return Ok(*doc);
+    }
+
+    let query = query.to_lowercase();
+    let mut partial = names.iter().filter(|(_, name)| name.to_lowercase().contains(&query));
+
+    match (partial.next(), partial.next()) {
+        (Some((doc, _)), None) => Ok(*doc),
+        (Some(_), Some(_)) => Err(Error::AmbiguousBuffer(query)),
+        (None, _) => Err(Error::NoMatchingBuffer(query)),
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::SlotMap;
+    use super::*;
+
+    fn docs(count: usize) -> Vec<DocID> {
+        let mut map = SlotMap::new();
+        (0..count).map(|_| DocID(map.insert(()))).collect()
+    }
+
+    #[test]
+    fn numbers_are_not_reused() {
+        let docs = docs(3);
+        let mut buffers = BufferList::new();
+
+        buffers.add(docs[0]);
+        buffers.add(docs[1]);
+        buffers.remove(docs[1]);
+
+        assert_eq!(buffers.add(docs[2]), 3);
+        assert_eq!(buffers.get(3).unwrap(), docs[2]);
+        assert!(buffers.get(2).is_err());
+    }
+
+    #[test]
+    fn cycling_wraps_around() {
+        let docs = docs(3);
+        let mut buffers = BufferList::new();
+        for doc in docs.iter() {
+            buffers.add(*doc);
+        }
+
+        assert_eq!(buffers.cycle(docs[2], true), Some(docs[0]));
+        assert_eq!(buffers.cycle(docs[0], false), Some(docs[2]));
+        assert_eq!(buffers.cycle(docs[1], true), Some(docs[2]));
+    }
+
+    #[test]
+    fn names_match_exactly_before_partially() {
+        let docs = docs(3);
+        let names = vec![
+            (docs[0], "src/main.rs".to_string()),
+            (docs[1], "src/main_window.rs".to_string()),
+            (docs[2], "README.md".to_string()),
+        ];
+
+        assert_eq!(match_name(&names, "main.rs").unwrap(), docs[0]);
+        assert_eq!(match_name(&names, "window").unwrap(), docs[1]);
+        assert_eq!(match_name(&names, "readme").unwrap(), docs[2]);
+        assert!(matches!(match_name(&names, "main"), Err(Error::AmbiguousBuffer(_))));
+        assert!(matches!(match_name(&names, "lib"), Err(Error::NoMatchingBuffer(_))));
+    }
+}
@@ -0,0 +1,325 @@
+/// The line ending a document uses on disk.
+/// Documents always use '\n' in memory, the line ending is restored on save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// unix, '\n'
+    Lf,
+    /// dos, "\r\n"
+    CrLf,
+    /// classic mac, '\r'
+    Cr,
+}
+
+/// The text encoding a document uses on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// utf-8 starting with a byte order mark.
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// The result of decoding the content of a file.
+#[derive(Debug)]
+pub struct Decoded {
+    /// the text with every line ending normalized to '\n'
+    pub text: String,
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    /// the file mixes line endings. Saving it would write every line break as line_ending.
+    pub mixed: bool,
+    /// the file was not valid in the detected encoding and invalid
+    /// sequences were replaced. Saving it would lose data.
+    pub lossy: bool,
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// the name used by `:set fileformat`
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "unix",
+            LineEnding::CrLf => "dos",
+            LineEnding::Cr => "mac",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unix" | "lf" => Some(LineEnding::Lf),
+            "dos" | "crlf" => Some(LineEnding::CrLf),
+            "mac" | "cr" => Some(LineEnding::Cr),
+            _ => None,
+        }
+    }
+
+    /// the most common line ending of the text, unix if there are no line breaks.
+    pub fn detect(text: &str) -> Self {
+        let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                    crlf += 1;
+                }
+                '\r' => cr += 1,
+                '\n' => lf += 1,
+                _ => {}
+            }
+        }
+
+        if crlf > lf && crlf >= cr {
+            LineEnding::CrLf
+        } else if cr > lf && cr > crlf {
+            LineEnding::Cr
+        } else {
+            LineEnding::Lf
+        }
+    }
+}
+
+impl Encoding {
+    /// the name used by `:set fileencoding`
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8-bom",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-8-bom" | "utf8-bom" => Some(Encoding::Utf8Bom),
+            "utf-16le" | "utf16le" | "ucs-2le" => Some(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" | "utf-16" => Some(Encoding::Utf16Be),
+            _ => None,
+        }
+    }
+
+    /// detects the encoding from the byte order mark. Without one utf-16 is guessed
+    /// if most of the even or odd bytes are zero, otherwise utf-8 is assumed.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(UTF8_BOM) {
+            return Encoding::Utf8Bom;
+        }
+        if bytes.starts_with(UTF16LE_BOM) {
+            return Encoding::Utf16Le;
+        }
+        if bytes.starts_with(UTF16BE_BOM) {
+            return Encoding::Utf16Be;
+        }
+
+        let sample = &bytes[..bytes.len().min(1024)];
+        if sample.len() >= 2 {
+            let pairs = sample.len() / 2;
+            let even = sample.iter().step_by(2).filter(|b| **b == 0).count();
+            let odd = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+
+            if odd * 10 > pairs * 7 && even == 0 {
+                return Encoding::Utf16Le;
+            }
+            if even * 10 > pairs * 7 && odd == 0 {
+                return Encoding::Utf16Be;
+            }
+        }
+
+        Encoding::Utf8
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[],
+            Encoding::Utf8Bom => UTF8_BOM,
+            Encoding::Utf16Le => UTF16LE_BOM,
+            Encoding::Utf16Be => UTF16BE_BOM,
+        }
+    }
+
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        let mut bytes = self.bom().to_vec();
+
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => bytes.extend_from_slice(text.as_bytes()),
+            Encoding::Utf16Le => text
+                .encode_utf16()
+                .for_each(|unit| bytes.extend_from_slice(&unit.to_le_bytes())),
+            Encoding::Utf16Be => text
+                .encode_utf16()
+                .for_each(|unit| bytes.extend_from_slice(&unit.to_be_bytes())),
+        }
+
+        bytes
+    }
+
+    /// decodes the bytes, replacing invalid sequences. The flag is set if anything was replaced.
+    fn decode(self, bytes: &[u8]) -> (String, bool) {
+        let bytes = if bytes.starts_with(self.bom()) {
+            &bytes[self.bom().len()..]
+        } else {
+            bytes
+        };
+
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => match std::str::from_utf8(bytes) {
+                Ok(text) => (text.to_string(), false),
+                Err(_) => (String::from_utf8_lossy(bytes).into_owned(), true),
+            },
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let units: Vec<u16> = bytes
+                    .chunks(2)
+                    .map(|pair| {
+                        let pair = [pair[0], *pair.get(1).unwrap_or(&0)];
+                        if self == Encoding::Utf16Le {
+                            u16::from_le_bytes(pair)
+                        } else {
+                            u16::from_be_bytes(pair)
+                        }
+                    })
+                    .collect();
+
+                let lossy = bytes.len() % 2 != 0
+                    || std::char::decode_utf16(units.iter().cloned()).any(|c| c.is_err());
+                (String::from_utf16_lossy(&units), lossy)
+            }
+        }
+    }
+}
+
+/// decodes the raw content of a file.
+pub fn decode(bytes: &[u8]) -> Decoded {
+    let encoding = Encoding::detect(bytes);
+    let (text, lossy) = encoding.decode(bytes);
+    let line_ending = LineEnding::detect(&text);
+    let (text, mixed) = normalize(&text);
+
+    Decoded {
+        text,
+        encoding,
+        line_ending,
+        mixed,
+        lossy,
+    }
+}
+
+/// replaces every line break, "\r\n" or a '\r' on its own, with '\n' in a single pass, so a
+/// break of one kind is never read as part of another. The flag is set if the text has more
+/// than one kind of line break.
+pub fn normalize(text: &str) -> (String, bool) {
+    let (mut lf, mut crlf, mut cr) = (false, false, false);
+    let mut normalized = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' if chars.peek() == Some(&'\n') => {
+                chars.next();
+                crlf = true;
+            }
+            '\r' => cr = true,
+            '\n' => lf = true,
+            c => {
+                normalized.push(c);
+                continue;
+            }
+        }
+        normalized.push('\n');
+    }
+
+    let kinds = [lf, crlf, cr].iter().filter(|kind| **kind).count();
+    (normalized, kinds > 1)
+}
+
+/// encodes the in memory text for writing to disk.
+pub fn encode(text: &str, encoding: Encoding, line_ending: LineEnding) -> Vec<u8> {
+    match line_ending {
+        LineEnding::Lf => encoding.encode(text),
+        _ => encoding.encode(&text.replace('\n', line_ending.as_str())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detects_line_endings() {
+        assert_eq!(LineEnding::detect("a\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\rb\r"), LineEnding::Cr);
+        assert_eq!(LineEnding::detect("no breaks"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn round_trips_crlf_with_bom() {
+        let bytes = b"\xEF\xBB\xBFfn main() {\r\n}\r\n".to_vec();
+        let decoded = decode(&bytes);
+
+        assert_eq!(decoded.encoding, Encoding::Utf8Bom);
+        assert_eq!(decoded.line_ending, LineEnding::CrLf);
+        assert_eq!(decoded.text, "fn main() {\n}\n");
+        assert!(!decoded.lossy);
+
+        assert_eq!(encode(&decoded.text, decoded.encoding, decoded.line_ending), bytes);
+    }
+
+    #[test]
+    fn round_trips_utf16() {
+        for encoding in &[Encoding::Utf16Le, Encoding::Utf16Be] {
+            let bytes = encode("héllo\nwörld\n", *encoding, LineEnding::Lf);
+            let decoded = decode(&bytes);
+
+            assert_eq!(decoded.encoding, *encoding);
+            assert_eq!(decoded.text, "héllo\nwörld\n");
+            assert_eq!(encode(&decoded.text, decoded.encoding, decoded.line_ending), bytes);
+        }
+    }
+
+    #[test]
+    fn normalizes_mixed_line_endings_in_one_pass() {
+        let decoded = decode(b"a\r\nb\rc\nd\r\n\r\ne");
+
+        assert_eq!(decoded.text, "a\nb\nc\nd\n\ne");
+        assert_eq!(decoded.line_ending, LineEnding::CrLf);
+        assert!(decoded.mixed);
+
+        // every line break is written with the line ending of the file.
+        let bytes = encode(&decoded.text, decoded.encoding, decoded.line_ending);
+        assert_eq!(bytes, b"a\r\nb\r\nc\r\nd\r\n\r\ne".to_vec());
+
+        let again = decode(&bytes);
+        assert_eq!(again.text, decoded.text);
+        assert!(!again.mixed);
+    }
+
+    #[test]
+    fn a_single_line_ending_is_not_mixed() {
+        for bytes in &[&b"a\nb\n"[..], b"a\r\nb\r\n", b"a\rb\r", b"no breaks"] {
+            let decoded = decode(bytes);
+            assert!(!decoded.mixed);
+            assert_eq!(encode(&decoded.text, decoded.encoding, decoded.line_ending), bytes.to_vec());
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_is_lossy() {
+        let decoded = decode(b"abc\xFF\xFEdef\n\x80");
+        assert!(decoded.lossy);
+    }
+}
@@ -0,0 +1,122 @@
+/// A change to the text of a document, at a character index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    Insert(usize, String),
+    Remove(usize, String),
+}
+
+impl Edit {
+    /// the edit that takes this one back.
+    pub fn inverse(&self) -> Edit {
+        match self {
+            Edit::Insert(index, text) => Edit::Remove(*index, text.clone()),
+            Edit::Remove(index, text) => Edit::Insert(*index, text.clone()),
+        }
+    }
+
+    /// the character index just after the edit, where the cursor is left.
+    pub fn end(&self) -> usize {
+        match self {
+            Edit::Insert(index, text) => index + text.chars().count(),
+            Edit::Remove(index, _) => *index,
+        }
+    }
+}
+
+/// The edits of a document, grouped into the steps they are undone and redone in.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: Vec<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+    /// the edits of the group being recorded.
+    group: Option<Vec<Edit>>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the edits recorded until end_group are undone in a single step.
+    pub fn begin_group(&mut self) {
+        if self.group.is_none() {
+            self.group = Some(Vec::new());
+        }
+    }
+
+    pub fn end_group(&mut self) {
+        if let Some(group) = self.group.take() {
+            self.push(group);
+        }
+    }
+
+    /// records an edit that was made, it is a step of its own when no group is being recorded.
+    pub fn record(&mut self, edit: Edit) {
+        match self.group {
+            Some(ref mut group) => group.push(edit),
+            None => self.push(vec![edit]),
+        }
+    }
+
+    /// the edits that take back the last step, in the order they are made.
+    pub fn undo(&mut self) -> Option<Vec<Edit>> {
+        let group = self.undo.pop()?;
+        let inverse = group.iter().rev().map(Edit::inverse).collect();
+        self.redo.push(group);
+
+        Some(inverse)
+    }
+
+    /// the edits of the last step that was undone.
+    pub fn redo(&mut self) -> Option<Vec<Edit>> {
+        let group = self.redo.pop()?;
+        self.undo.push(group.clone());
+
+        Some(group)
+    }
+
+    fn push(&mut self, group: Vec<Edit>) {
+        if group.is_empty() {
+            return;
+        }
+
+        // a new edit branches off from the undone steps, they cannot be redone anymore.
+        self.redo.clear();
+        self.undo.push(group);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn groups_are_undone_together() {
+        let mut history = History::new();
+        history.record(Edit::Insert(0, "a".to_string()));
+
+        history.begin_group();
+        history.record(Edit::Insert(4, "b".to_string()));
+        history.record(Edit::Remove(1, "c".to_string()));
+        history.end_group();
+
+        let undo = history.undo().unwrap();
+        assert_eq!(undo, vec![Edit::Insert(1, "c".to_string()), Edit::Remove(4, "b".to_string())]);
+
+        assert_eq!(history.undo().unwrap(), vec![Edit::Remove(0, "a".to_string())]);
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn new_edits_drop_the_redo_steps() {
+        let mut history = History::new();
+        history.record(Edit::Insert(0, "a".to_string()));
+        history.undo();
+
+        assert_eq!(history.redo().unwrap(), vec![Edit::Insert(0, "a".to_string())]);
+        history.undo();
+
+        history.record(Edit::Insert(0, "b".to_string()));
+        assert!(history.redo().is_none());
+    }
+}
@@ -0,0 +1,271 @@
+use std::path::Path;
+
+use crate::config;
+
+/// How a document is indented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// a single tab per level
+    Tabs,
+    /// the given number of spaces per level
+    Spaces(u8),
+}
+
+/// The language of a document, this only decides how indentation is handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// Languages that use braces for blocks. (Rust, C, C++, JavaScript...)
+    Brace,
+    /// Languages where a block starts after a ':'
+    Python,
+    /// Anything else, only the previous indentation is copied.
+    Plain,
+}
+
+/// The number of lines looked at when detecting the indentation of a file.
+const DETECT_LINES: usize = 1000;
+
+impl Language {
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+
+        match extension {
+            "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "js" | "ts" | "java" | "go" | "cs"
+            | "json" | "glsl" | "css" => Language::Brace,
+            "py" => Language::Python,
+            _ => Language::Plain,
+        }
+    }
+
+    /// does the line (up to the cursor) open a new block.
+    pub fn opens_block(self, line: &str) -> bool {
+        let line = line.trim_end();
+        match self {
+            Language::Brace => line.ends_with('{') || line.ends_with('(') || line.ends_with('['),
+            Language::Python => line.ends_with(':'),
+            Language::Plain => false,
+        }
+    }
+
+    /// does typing this character close a block.
+    pub fn closes_block(self, ch: char) -> bool {
+        match self {
+            Language::Brace => ch == '}' || ch == ')' || ch == ']',
+            _ => false,
+        }
+    }
+}
+
+impl IndentStyle {
+    /// the style to use when nothing can be detected from the document.
+    pub fn from_config(tabs: &config::Tab) -> Self {
+        if tabs.expand_tab {
+            IndentStyle::Spaces(tabs.shift_width())
+        } else {
+            IndentStyle::Tabs
+        }
+    }
+
+    /// one level of indentation.
+    pub fn unit(self) -> String {
+        match self {
+            IndentStyle::Tabs => "\t".to_string(),
+            IndentStyle::Spaces(n) => " ".repeat(n as usize),
+        }
+    }
+}
+
+/// the leading whitespace of a line.
+pub fn indentation(line: &str) -> &str {
+    let end = line
+        .find(|c: char| c != ' ' && c != '\t')
+        .unwrap_or_else(|| line.trim_end_matches(['\n', '\r']).len());
+    &line[..end]
+}
+
+/// attempts to figure out the indentation style of the given lines.
+/// Returns None if there is not any indentation to go off of.
+pub fn detect<I>(lines: I) -> Option<IndentStyle>
+where
+    I: Iterator<Item = String>,
+{
+    let mut tabs = 0;
+    let mut spaces = 0;
+    // how often each change in indentation (in spaces) is seen.
+    let mut deltas = [0usize; 9];
+    let mut previous = 0;
+
+    for line in lines.take(DETECT_LINES) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let indent = indentation(&line);
+        if indent.starts_with('\t') {
+            tabs += 1;
+            continue;
+        }
+
+        let width = indent.len();
+        if width > 0 {
+            spaces += 1;
+        }
+
+        let delta = width.abs_diff(previous);
+
+        if delta > 1 && delta < deltas.len() {
+            deltas[delta] += 1;
+        }
+        previous = width;
+    }
+
+    if tabs == 0 && spaces == 0 {
+        return None;
+    }
+
+    if tabs >= spaces {
+        return Some(IndentStyle::Tabs);
+    }
+
+    let (width, _) = deltas
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, count)| **count)
+        .unwrap();
+
+    // a file with only a single level of odd indentation, use the common default.
+    Some(IndentStyle::Spaces(if deltas[width] == 0 { 4 } else { width as u8 }))
+}
+
+/// the text to insert when a new line is started after `before` (the line up to the cursor).
+pub fn newline(before: &str, style: IndentStyle, language: Language, tabs: &config::Tab) -> String {
+    let mut text = String::from("\n");
+
+    if !tabs.auto_indent {
+        return text;
+    }
+
+    text.push_str(indentation(before));
+
+    if tabs.smart_indent && language.opens_block(before) {
+        text.push_str(style.unit().as_str());
+    }
+
+    text
+}
+
+/// the number of characters to remove from the end of `indent` to dedent it by one level.
+pub fn dedent_len(indent: &str, style: IndentStyle) -> usize {
+    match style {
+        IndentStyle::Tabs if indent.ends_with('\t') => 1,
+        IndentStyle::Tabs => 0,
+        IndentStyle::Spaces(n) => {
+            let trailing = indent.len() - indent.trim_end_matches(' ').len();
+            // a width of 0 would leave no stops.
+            let n = (n as usize).max(1);
+            // removes up to the previous indent stop.
+            match trailing % n {
+                0 => std::cmp::min(trailing, n),
+                rem => rem,
+            }
+        }
+    }
+}
+
+/// the spaces inserted for a tab when tabs are expanded.
+/// `cell` is the cell column of the cursor.
+pub fn soft_tab(cell: u32, tabs: &config::Tab) -> String {
+    // a tab width of 0 would leave no stops.
+    let stop = (tabs.soft_tab_stop() as u32).max(1);
+    let width = stop - cell % stop;
+    " ".repeat(width as usize)
+}
+
+/// the number of characters backspace removes. When tabs are expanded and there are only
+/// spaces before the cursor, it removes them back to the previous soft tab stop.
+pub fn backspace_len(before: &str, tabs: &config::Tab) -> usize {
+    if !tabs.expand_tab || before.is_empty() || before.chars().any(|c| c != ' ') {
+        return 1;
+    }
+
+    let stop = (tabs.soft_tab_stop() as usize).max(1);
+    match before.len() % stop {
+        0 => stop,
+        rem => rem,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(text: &str) -> impl Iterator<Item = String> + '_ {
+        text.lines().map(|l| l.to_string())
+    }
+
+    #[test]
+    fn detects_tabs() {
+        let text = "fn main() {\n\tlet x = 1;\n\tif x {\n\t\tx;\n\t}\n}\n";
+        assert_eq!(detect(lines(text)), Some(IndentStyle::Tabs));
+    }
+
+    #[test]
+    fn detects_spaces() {
+        let text = "fn main() {\n    let x = 1;\n    if x {\n        x;\n    }\n}\n";
+        assert_eq!(detect(lines(text)), Some(IndentStyle::Spaces(4)));
+
+        let text = "a:\n  b:\n    c\n  d\n";
+        assert_eq!(detect(lines(text)), Some(IndentStyle::Spaces(2)));
+    }
+
+    #[test]
+    fn detects_nothing() {
+        assert_eq!(detect(lines("a\nb\nc\n")), None);
+    }
+
+    #[test]
+    fn dedent_to_previous_stop() {
+        assert_eq!(dedent_len("        ", IndentStyle::Spaces(4)), 4);
+        assert_eq!(dedent_len("      ", IndentStyle::Spaces(4)), 2);
+        assert_eq!(dedent_len("\t\t", IndentStyle::Tabs), 1);
+        assert_eq!(dedent_len("", IndentStyle::Spaces(4)), 0);
+    }
+
+    #[test]
+    fn dedent_with_no_width_removes_a_space() {
+        assert_eq!(dedent_len("   ", IndentStyle::Spaces(0)), 1);
+        assert_eq!(dedent_len("", IndentStyle::Spaces(0)), 0);
+    }
+
+    fn soft_tabs(stop: u8) -> config::Tab {
+        config::Tab {
+            tab_width: 0,
+            expand_tab: true,
+            shift_width: 0,
+            soft_tab_stop: stop,
+            auto_indent: true,
+            smart_indent: true,
+            detect_indent: true,
+        }
+    }
+
+    #[test]
+    fn soft_tabs_go_to_the_next_stop() {
+        assert_eq!(soft_tab(1, &soft_tabs(4)), "   ");
+        assert_eq!(soft_tab(4, &soft_tabs(4)), "    ");
+        // no stop at all is treated as a stop at every cell.
+        assert_eq!(soft_tab(3, &soft_tabs(0)), " ");
+    }
+
+    #[test]
+    fn backspace_goes_to_the_previous_stop() {
+        assert_eq!(backspace_len("        ", &soft_tabs(4)), 4);
+        assert_eq!(backspace_len("      ", &soft_tabs(4)), 2);
+        assert_eq!(backspace_len("  x ", &soft_tabs(4)), 1);
+        assert_eq!(backspace_len("    ", &soft_tabs(0)), 1);
+        assert_eq!(backspace_len("", &soft_tabs(4)), 1);
+    }
+}
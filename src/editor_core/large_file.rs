@@ -0,0 +1,155 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use memmap::Mmap;
+
+/// The number of line offsets found before they are published to the index.
+const INDEX_BATCH: usize = 64 * 1024;
+
+/// The start of every line of a mapped file, built in the background.
+#[derive(Debug)]
+struct LineIndex {
+    /// byte offset of the start of each line.
+    offsets: RwLock<Vec<usize>>,
+    /// every line of the file has been indexed.
+    done: AtomicBool,
+    /// the file was checked after it was indexed and is valid utf-8.
+    valid: AtomicBool,
+    /// the utf-8 check has finished, valid is only meaningful once this is set.
+    checked: AtomicBool,
+}
+
+/// A read only, memory mapped file. Lines are decoded on demand so a file
+/// of any size can be opened without reading it first.
+#[derive(Debug, Clone)]
+pub struct MappedFile {
+    map: Arc<Mmap>,
+    index: Arc<LineIndex>,
+    /// the number of bytes of the byte order mark, if any.
+    start: usize,
+}
+
+impl MappedFile {
+    pub fn open(path: &Path, start: usize) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        let map = Arc::new(unsafe { Mmap::map(&file)? });
+
+        let index = Arc::new(LineIndex {
+            offsets: RwLock::new(vec![start]),
+            done: AtomicBool::new(false),
+            valid: AtomicBool::new(false),
+            checked: AtomicBool::new(false),
+        });
+
+        {
+            let map = map.clone();
+            let index = index.clone();
+            thread::spawn(move || Self::build_index(&map, &index, start));
+        }
+
+        Ok(Self { map, index, start })
+    }
+
+    fn build_index(map: &Mmap, index: &LineIndex, start: usize) {
+        let mut batch = Vec::with_capacity(INDEX_BATCH);
+        let bytes = &map[start..];
+
+        // a line ends at '\n', "\r\n" or a '\r' on its own, like the lines of a rope.
+        for (offset, byte) in bytes.iter().enumerate() {
+            let end = match *byte {
+                b'\n' => true,
+                b'\r' => bytes.get(offset + 1) != Some(&b'\n'),
+                _ => false,
+            };
+            if end {
+                batch.push(start + offset + 1);
+            }
+
+            if batch.len() == INDEX_BATCH {
+                index.offsets.write().unwrap().extend(batch.drain(..));
+            }
+        }
+
+        index.offsets.write().unwrap().extend(batch.drain(..));
+        index.done.store(true, SeqCst);
+
+        index.valid.store(std::str::from_utf8(bytes).is_ok(), SeqCst);
+        index.checked.store(true, SeqCst);
+    }
+
+    /// has the entire file been indexed.
+    pub fn is_indexed(&self) -> bool {
+        self.index.done.load(SeqCst)
+    }
+
+    /// the file has been checked and can be decoded without losing anything. This is false until
+    /// the check is done, some time after the file is indexed.
+    pub fn is_valid(&self) -> bool {
+        self.index.valid.load(SeqCst)
+    }
+
+    /// has the file been checked to be utf-8, whether it is or not.
+    pub fn is_checked(&self) -> bool {
+        self.index.checked.load(SeqCst)
+    }
+
+    /// the size of the file in bytes
+    pub fn len_bytes(&self) -> usize {
+        self.map.len()
+    }
+
+    /// the number of lines indexed so far, this is all of them once is_indexed is true.
+    pub fn len_lines(&self) -> usize {
+        let offsets = self.index.offsets.read().unwrap();
+        if self.is_indexed() {
+            offsets.len()
+        } else {
+            // the last offset is a line that has not been fully found yet.
+            offsets.len() - 1
+        }
+    }
+
+    /// the text of a line, including its line break.
+    /// Returns None if the line does not exist or has not been indexed yet.
+    pub fn line(&self, line: usize) -> Option<Cow<'_, str>> {
+        let (start, end) = {
+            let offsets = self.index.offsets.read().unwrap();
+            let start = *offsets.get(line)?;
+            let end = match offsets.get(line + 1) {
+                Some(end) => *end,
+                None if self.is_indexed() => self.map.len(),
+                None => return None,
+            };
+            (start, end)
+        };
+
+        let bytes = &self.map[start..end];
+
+        // line breaks are always '\n' in memory
+        let bytes = if bytes.ends_with(b"\r\n") {
+            &bytes[..bytes.len() - 2]
+        } else if bytes.ends_with(b"\n") || bytes.ends_with(b"\r") {
+            &bytes[..bytes.len() - 1]
+        } else {
+            bytes
+        };
+        let has_break = end != start + bytes.len();
+
+        let text = String::from_utf8_lossy(bytes);
+        Some(if has_break {
+            Cow::Owned(text.into_owned() + "\n")
+        } else {
+            text
+        })
+    }
+
+    /// the whole file as text, skipping the byte order mark.
+    pub fn contents(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.map[self.start..])
+    }
+}
@@ -1,17 +1,39 @@
+pub mod buffers;
+pub mod encoding;
+pub mod history;
+pub mod indent;
+pub mod large_file;
+pub mod slot_map;
+
 use crate::ropey;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
 use std::io;
-use std::io::BufReader;
 use std::path;
 use std::rc::Rc;
 
-use ropey::iter::Bytes;
 use ropey::Rope;
+use unicode_width::UnicodeWidthChar;
 use crate::config;
 
-use std::sync::atomic::{AtomicU16, Ordering::SeqCst};
+pub use buffers::BufferList;
+pub use encoding::{Encoding, LineEnding};
+pub use history::{Edit, History};
+pub use indent::{IndentStyle, Language};
+pub use large_file::MappedFile;
+pub use slot_map::{SlotKey, SlotMap};
+
+/// the number of cells a character is drawn in.
+/// Tabs take up tab_width cells, wide characters (CJK, emoji) two and combining marks none.
+pub fn char_width(c: char, tab_width: u32) -> u32 {
+    if c == '\t' {
+        tab_width
+    } else {
+        // control characters are drawn as a single missing glyph.
+        c.width().unwrap_or(1) as u32
+    }
+}
 
 /// Engine Errors
 #[derive(Debug)]
@@ -21,6 +43,14 @@ pub enum Error {
     FileExists,
     InvalidDocID,
     MissingPath,
+    /// the document cannot be modified or written, see Document::read_only
+    ReadOnly,
+    /// no open buffer has the number.
+    InvalidBuffer(usize),
+    /// no open buffer has a name with the text in it.
+    NoMatchingBuffer(String),
+    /// more than one open buffer has a name with the text in it.
+    AmbiguousBuffer(String),
     FileError(io::Error),
 }
 
@@ -35,15 +65,24 @@ enum OperationKind {
     Delete(usize, u32, u32),
     /// Paste the content of the clipboard at .0, .1
     Paste(usize, u32, u32, String),
+    /// Replace the .1 characters before the character index .0 with .2
+    Replace(usize, usize, String),
     /// Writes buffer to disk
     WriteFile,
     /// Closes the file
     CloseFile,
+    /// Changes the line ending used when the file is written
+    SetLineEnding(LineEnding),
+    /// Changes the encoding used when the file is written
+    SetEncoding(Encoding),
     /// Invalid operation
+    #[allow(dead_code)]
     Invalid,
 
     // future operations
+    #[allow(dead_code)]
     CopySelection,
+    #[allow(dead_code)]
     DeleteSelection,
 }
 
@@ -69,6 +108,20 @@ impl Operation {
         }
     }
 
+    pub fn set_line_ending(doc: DocID, line_ending: LineEnding) -> Self {
+        Self {
+            doc,
+            kind: OperationKind::SetLineEnding(line_ending),
+        }
+    }
+
+    pub fn set_encoding(doc: DocID, encoding: Encoding) -> Self {
+        Self {
+            doc,
+            kind: OperationKind::SetEncoding(encoding),
+        }
+    }
+
     pub fn insert(doc: DocID, start_index: usize, x: u32, y: u32, ch: char) -> Self {
         Self {
             doc,
@@ -76,7 +129,7 @@ impl Operation {
         }
     }
 
-    pub fn delete(doc: DocID, start_index: usize, x: u32, y: u32, ch: char) -> Self {
+    pub fn delete(doc: DocID, start_index: usize, x: u32, y: u32) -> Self {
         Self {
             doc,
             kind: OperationKind::Delete(start_index, x, y),
@@ -89,37 +142,66 @@ impl Operation {
             kind: OperationKind::Paste(start_index, x, y, data.to_owned()),
         }
     }
+
+    /// replaces the characters before a character index with the text, an edit at one of
+    /// several cursors.
+    pub fn replace(doc: DocID, index: usize, remove: usize, text: &str) -> Self {
+        Self {
+            doc,
+            kind: OperationKind::Replace(index, remove, text.to_owned()),
+        }
+    }
 }
 
 pub struct Engine {
-    /// List of all open documents.
-    docs: Vec<Document>,
-
-    /// A acciciation of panes to indices in docs vector.
-    document_map: HashMap<DocID, usize>,
+    /// All of the open documents, keyed by their id.
+    docs: SlotMap<Document>,
+    /// the open documents in the order they were opened.
+    buffers: BufferList,
+    /// the document of each open file by its canonical path, which documents are given when
+    /// they are opened.
+    paths: HashMap<path::PathBuf, DocID>,
     config: Rc<config::Config>
 }
 
-#[derive(Debug, Clone)]
+/// Documents are owned by the engine and never copied.
+#[derive(Debug)]
 pub struct Document {
     /// file of the document
     path: Option<String>,
     /// string representation of the document
     content: ropey::Rope,
-    /// unique id
+    /// the file backing a large document. While this is set the content is
+    /// read from the file instead of the rope, the rope is only filled in
+    /// once the document is modified.
+    mapped: Option<MappedFile>,
+    /// unique id, this is assigned when the document is registered with the engine.
     id: DocID,
+    /// how new lines of this document are indented.
+    indent: IndentStyle,
+    /// the language of the document, only used for indentation.
+    language: Language,
+    /// the line ending of the file on disk.
+    line_ending: LineEnding,
+    /// the encoding of the file on disk.
+    encoding: Encoding,
+    /// the file could not be decoded without losing data, so it cannot be edited or saved.
+    read_only: bool,
+    /// the file mixes line endings, saving it would change some of them. It is read only until
+    /// a line ending is set for it.
+    mixed_endings: bool,
+    /// the edits made to the document, for undo and redo.
+    history: History,
 }
 
+/// Identifies an open document. Ids of closed documents are never valid again.
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-pub struct DocID(u16);
+pub struct DocID(SlotKey);
 
 impl DocID {
-    fn next() -> Self {
-        static TOKEN: AtomicU16 = AtomicU16::new(0);
-
-        Self {
-            0: TOKEN.fetch_add(0, SeqCst),
-        }
+    /// the id of a document that has not been registered with an engine.
+    const fn detached() -> Self {
+        DocID(SlotKey::null())
     }
 }
 
@@ -131,157 +213,719 @@ impl Document {
         Ok(Self {
             path: {
                 // @NOTE: maybe this path should be checked?
-                match path {
-                    Some(s) => Some(s.to_string()),
-                    None => None,
-                }
+                path.map(|s| s.to_string())
             },
             content: Rope::new(),
-            id: DocID::next(),
+            mapped: None,
+            id: DocID::detached(),
+            indent: IndentStyle::Tabs,
+            language: path.map_or(Language::Plain, Language::from_path),
+            line_ending: LineEnding::Lf,
+            encoding: Encoding::Utf8,
+            read_only: false,
+            mixed_endings: false,
+            history: History::new(),
         })
     }
 
     pub fn from_path(path: &str) -> Result<Self> {
         let path = path::Path::new(path);
-        let id = DocID::next();
+        let id = DocID::detached();
 
         let error_map = |e| Error::FileError(e);
 
-        let content = if path.exists() {
-            Rope::from_reader(BufReader::new(fs::File::open(path).map_err(error_map)?))
-                .map_err(error_map)?
+        // a file that does not exist yet keeps the path it was given, it is made when the
+        // document is written.
+        let (full_path, decoded) = if path.exists() {
+            (path.canonicalize().map_err(error_map)?, encoding::decode(&fs::read(path).map_err(error_map)?))
         } else {
-            Rope::new()
+            (path.to_path_buf(), encoding::decode(&[]))
         };
 
-        println!("Num Lines: {}", content.len_lines());
-        println!("Size: {}", content.len_bytes());
+        if decoded.lossy {
+            println!(
+                "Warning: {:?} is not valid {}, opening as read only",
+                path,
+                decoded.encoding.name()
+            );
+        }
+        if decoded.mixed {
+            println!(
+                "Warning: {:?} mixes line endings, opening as read only until :set fileformat picks one",
+                path
+            );
+        }
+
+        let content = Rope::from_str(&decoded.text);
+
+        Ok(Self {
+            path: Some(full_path.to_string_lossy().to_string()),
+            content,
+            mapped: None,
+            id,
+            indent: IndentStyle::Tabs,
+            language: Language::from_path(path.to_str().unwrap_or("")),
+            line_ending: decoded.line_ending,
+            encoding: decoded.encoding,
+            read_only: decoded.lossy,
+            mixed_endings: decoded.mixed,
+            history: History::new(),
+        })
+    }
+
+    /// opens a large file without reading it. The file is memory mapped and
+    /// its lines are indexed in the background.
+    /// Only utf-8 files can be mapped, other encodings have to be loaded with from_path.
+    pub fn mapped(path: &str) -> Result<Self> {
+        let path = path::Path::new(path);
+        let error_map = |e| Error::FileError(e);
+
+        // only the start of the file is read to find the encoding and line endings.
+        let mut head = [0u8; 4096];
+        let read = {
+            use std::io::Read;
+            fs::File::open(path)
+                .map_err(error_map)?
+                .read(&mut head)
+                .map_err(error_map)?
+        };
+        let head = &head[..read];
+
+        let start = match Encoding::detect(head) {
+            Encoding::Utf8 => 0,
+            Encoding::Utf8Bom => 3,
+            _ => return Self::from_path(path.to_str().unwrap_or("")),
+        };
+
+        let mapped = MappedFile::open(path, start).map_err(error_map)?;
+        // like the line ending, mixed line endings are only looked for at the start of the file.
+        let head = String::from_utf8_lossy(head);
+        let (_, mixed_endings) = encoding::normalize(&head);
 
         Ok(Self {
-            // @TODO: handle error from both canonicalizing and string unwrapping.
             path: Some(
                 path.canonicalize()
-                    .unwrap()
-                    .as_path()
-                    .to_str()
-                    .unwrap()
+                    .map_err(error_map)?
+                    .to_string_lossy()
                     .to_string(),
             ),
-            content,
-            id,
+            content: Rope::new(),
+            mapped: Some(mapped),
+            id: DocID::detached(),
+            indent: IndentStyle::Tabs,
+            language: Language::from_path(path.to_str().unwrap_or("")),
+            line_ending: LineEnding::detect(&head),
+            encoding: if start == 0 { Encoding::Utf8 } else { Encoding::Utf8Bom },
+            read_only: false,
+            mixed_endings,
+            history: History::new(),
         })
     }
 
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// the name the document is listed by, its path or [No Name] for a scratch buffer.
+    pub fn name(&self) -> String {
+        match self.path {
+            Some(ref path) => path.clone(),
+            None => "[No Name]".to_string(),
+        }
+    }
+
+    /// points the document at the file it was moved to.
+    fn set_path(&mut self, path: &path::Path) {
+        self.language = Language::from_path(path.to_str().unwrap_or(""));
+        self.path = Some(path.to_string_lossy().to_string());
+    }
+
     pub fn id(&self) -> DocID {
         self.id
     }
 
-    pub fn as_str<'a>(&'a self) -> Cow<str> {
-        self.content.clone().into()
+    /// is this document backed by a memory mapped file.
+    pub fn is_large(&self) -> bool {
+        self.mapped.is_some()
+    }
+
+    /// loads a mapped document into the rope so it can be edited.
+    /// This is the only time the whole of a large file is read. A file that is not valid utf-8
+    /// is made read only instead of being decoded with losses, and a file that is still being
+    /// checked is not loaded until the check is done.
+    pub fn materialize(&mut self) -> Result<()> {
+        if let Some(ref mapped) = self.mapped {
+            if mapped.is_checked() {
+                self.read_only |= !mapped.is_valid();
+            }
+            if self.is_read_only() {
+                return Err(Error::ReadOnly);
+            }
+        }
+
+        if let Some(mapped) = self.mapped.take() {
+            self.content = Rope::from_str(&Self::mapped_text(&mapped));
+        }
+        Ok(())
+    }
+
+    /// the text of the mapped file with its line endings normalized to '\n', like the text of a
+    /// document that was read.
+    fn mapped_text(mapped: &MappedFile) -> String {
+        encoding::normalize(&mapped.contents()).0
+    }
+
+    pub fn as_str(&self) -> Cow<'_, str> {
+        match self.mapped {
+            Some(ref mapped) => mapped.contents(),
+            None => self.content.clone().into(),
+        }
     }
 
-    pub fn line_slice(&self, start: usize, end: usize) -> Vec<ropey::RopeSlice> {
-        let first = self.content.lines().skip(start);
-        first.take(end).collect()
+    /// the lines in [start, end)
+    pub fn line_slice(&self, start: usize, end: usize) -> Vec<Cow<'_, str>> {
+        (start..end).map_while(|line| self.line(line)).collect()
     }
 
     pub fn write(&self) -> Result<()> {
         // this will error if the write inself fails or if there isnt a path associated with the
         // document.
-        Ok(())
+        if self.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+
+        let path = path::Path::new(self.path.as_ref().ok_or(Error::MissingPath)?);
+        let text = match self.mapped {
+            Some(ref mapped) => Self::mapped_text(mapped),
+            None => self.content.to_string(),
+        };
+        let bytes = encoding::encode(&text, self.encoding, self.line_ending);
+
+        // the file is written next to itself and moved over it, so a mapped document keeps
+        // reading the old file instead of one that is being truncated.
+        let name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().to_string());
+        let temp = path.with_file_name(format!(".{}.rem-save", name));
+
+        fs::write(&temp, bytes).map_err(Error::FileError)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            let _ = fs::set_permissions(&temp, metadata.permissions());
+        }
+
+        fs::rename(&temp, path).map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            Error::FileError(e)
+        })
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// picks the line ending the file is saved with, which also lets a file that mixed line
+    /// endings be edited.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+        self.mixed_endings = false;
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// a mapped file is read only until it has been checked to be valid utf-8, decoding it
+    /// before then could lose data when it is saved.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only || self.mixed_endings || self.mapped.as_ref().is_some_and(|mapped| !mapped.is_valid())
     }
 
     pub fn insert(&mut self, index: u64, ch: char) -> Result<()> {
         println!("Character '{}' inserted at {}", ch, index);
         self.content.insert_char(index as usize, ch);
+        self.history.record(Edit::Insert(index as usize, ch.to_string()));
         Ok(())
     }
 
     pub fn delete(&mut self, index: u64) -> Result<()> {
         println!("Deleting character at index {}", index);
+        let index = index as usize;
+        if index >= self.content.len_chars() {
+            return Err(Error::DeleteError);
+        }
+        let removed = self.content.slice(index..index + 1).to_string();
+        self.content.remove(index..index + 1);
+        self.history.record(Edit::Remove(index, removed));
         Ok(())
     }
 
     pub fn paste(&mut self, index: u64, data: &str) -> Result<()> {
         println!("Pasting {} at index {}", data, index);
+        if index as usize > self.content.len_chars() {
+            return Err(Error::InsertError);
+        }
+        self.content.insert(index as usize, data);
+        self.history.record(Edit::Insert(index as usize, data.to_string()));
         Ok(())
     }
 
-    pub fn cursor_index(&self, first_line: usize, mut x: u32, y: u32, tab_characters: u32) -> u64 {
-        let first_line = first_line + y as usize;
-        let line_index = self.content.line_to_char(first_line);
-        let line_slice = self.content.line(line_index);
-        
-        // simple solution to tab handling
-        for c in line_slice.chars() {
-            if c == '\t' {
-                x -= tab_characters - 1; 
+    /// replaces the characters in [index - remove, index) with the text.
+    pub fn replace(&mut self, index: usize, remove: usize, text: &str) -> Result<()> {
+        if index > self.content.len_chars() || remove > index {
+            return Err(Error::InsertError);
+        }
+
+        let start = index - remove;
+        if remove > 0 {
+            let removed = self.content.slice(start..index).to_string();
+            self.content.remove(start..index);
+            self.history.record(Edit::Remove(start, removed));
+        }
+
+        if !text.is_empty() {
+            self.content.insert(start, text);
+            self.history.record(Edit::Insert(start, text.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// the edits made until end_group are undone in a single step.
+    pub fn begin_group(&mut self) {
+        self.history.begin_group();
+    }
+
+    pub fn end_group(&mut self) {
+        self.history.end_group();
+    }
+
+    /// takes back the last step of the history, returning where the cursor is left.
+    pub fn undo(&mut self) -> Option<usize> {
+        let edits = self.history.undo()?;
+        self.apply(&edits)
+    }
+
+    /// makes the last undone step again, returning where the cursor is left.
+    pub fn redo(&mut self) -> Option<usize> {
+        let edits = self.history.redo()?;
+        self.apply(&edits)
+    }
+
+    /// makes the edits without recording them, returning the end of the last one.
+    fn apply(&mut self, edits: &[Edit]) -> Option<usize> {
+        for edit in edits {
+            match edit {
+                Edit::Insert(index, text) => self.content.insert(*index, text),
+                Edit::Remove(index, text) => self.content.remove(*index..*index + text.chars().count()),
+            }
+        }
+
+        edits.last().map(Edit::end)
+    }
+
+    pub fn indent_style(&self) -> IndentStyle {
+        self.indent
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// sets the indentation from the config, using what the document already uses if allowed.
+    pub fn set_indent_from(&mut self, tabs: &config::Tab) {
+        let detected = if tabs.detect_indent {
+            let lines = (0..self.len_lines()).map_while(|line| self.line(line));
+            indent::detect(lines.map(|l| l.into_owned()))
+        } else {
+            None
+        };
+
+        self.indent = detected.unwrap_or_else(|| IndentStyle::from_config(tabs));
+    }
+
+    /// the text of a line before the given column.
+    pub fn line_before(&self, line: usize, column: usize) -> String {
+        match self.line(line) {
+            Some(slice) => slice.chars().take(column).collect(),
+            None => String::new(),
+        }
+    }
+
+    pub fn cursor_index(&mut self, first_line: usize, x: u32, y: u32, tab_characters: u32) -> Result<u64> {
+        let line = first_line + y as usize;
+        self.materialize()?;
+        let line_index = self.content.line_to_char(line);
+
+        // the cursor is in cells, tabs take up more than one cell.
+        let column = self.cell_to_column(line, x, tab_characters);
+
+        Ok(line_index as u64 + column as u64)
+    }
+
+    /// the character index of a (line, column) of the document.
+    /// A mapped document has to be materialized first, the index is of the loaded text.
+    pub fn char_index(&self, line: usize, column: usize) -> usize {
+        let line = line.min(self.content.len_lines().saturating_sub(1));
+        (self.content.line_to_char(line) + column).min(self.content.len_chars())
+    }
+
+    /// the (line, column) of a character index of the document.
+    pub fn position_of(&self, index: usize) -> (usize, usize) {
+        let index = index.min(self.content.len_chars());
+        let line = self.content.char_to_line(index);
+        (line, index - self.content.line_to_char(line))
+    }
+
+    /// the text in [start, end) of the character indices.
+    pub fn text(&self, start: usize, end: usize) -> String {
+        let end = end.min(self.content.len_chars());
+        self.content.slice(start.min(end)..end).to_string()
+    }
+
+    /// the character index of the next occurrence of the text at or after from, wrapping around
+    /// to the start of the document.
+    pub fn find(&self, text: &str, from: usize) -> Option<usize> {
+        if let Some(ref mapped) = self.mapped {
+            return self.find_in_lines(mapped, text, from);
+        }
+
+        let len = self.content.len_bytes();
+        let start = self.content.char_to_byte(from.min(self.content.len_chars()));
+
+        // wrapping around only needs to reach the occurrences that start before from.
+        let byte = self.find_in_chunks(text, start, len).or_else(|| {
+            let end = self.content.byte_to_char((start + text.len()).min(len));
+            self.find_in_chunks(text, 0, self.content.char_to_byte(end))
+        })?;
+
+        Some(self.content.byte_to_char(byte))
+    }
+
+    /// the byte index of the first occurrence of the text in the bytes [start, end) of the rope.
+    /// Each chunk is searched with the end of the chunks before it, so an occurrence that spans
+    /// chunks is found without copying the rope.
+    fn find_in_chunks(&self, text: &str, start: usize, end: usize) -> Option<usize> {
+        let mut window = String::new();
+        // the byte index of the start of the window.
+        let mut window_start = start;
+        let mut byte = start;
+
+        while byte < end {
+            let (chunk, chunk_start, _, _) = self.content.chunk_at_byte(byte);
+            let chunk_end = (chunk_start + chunk.len()).min(end);
+            window.push_str(&chunk[byte - chunk_start..chunk_end - chunk_start]);
+
+            if let Some(found) = window.find(text) {
+                return Some(window_start + found);
+            }
+
+            // only the end that could be the start of an occurrence is kept.
+            let mut cut = window.len() - text.len().saturating_sub(1).min(window.len());
+            while !window.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            window.drain(..cut);
+            window_start += cut;
+            byte = chunk_end;
+        }
+
+        None
+    }
+
+    /// finds the text line by line so a mapped file is never read whole. Nothing is found until
+    /// the file is indexed, nor is text that spans a line break.
+    fn find_in_lines(&self, mapped: &MappedFile, text: &str, from: usize) -> Option<usize> {
+        if !mapped.is_indexed() || text.is_empty() || text.contains('\n') {
+            return None;
+        }
+
+        // the first occurrence is kept in case there are none after from.
+        let mut first = None;
+        let mut index = 0;
+
+        for line in 0..mapped.len_lines() {
+            let slice = mapped.line(line)?;
+            for (byte, _) in slice.trim_end_matches('\n').match_indices(text) {
+                let found = index + slice[..byte].chars().count();
+                if found >= from {
+                    return Some(found);
+                }
+                first.get_or_insert(found);
             }
+            index += slice.chars().count();
         }
-        
-        line_index as u64 + x as u64
+
+        first
+    }
+
+    pub fn len_lines(&self) -> usize {
+        match self.mapped {
+            Some(ref mapped) => mapped.len_lines(),
+            None => self.content.len_lines(),
+        }
+    }
+
+    pub fn line(&self, line: usize) -> Option<Cow<'_, str>> {
+        if let Some(ref mapped) = self.mapped {
+            return mapped.line(line);
+        }
+
+        if line < self.content.len_lines() {
+            Some(self.content.line(line).into())
+        } else {
+            None
+        }
+    }
+
+    /// number of characters in the line, not counting the line break.
+    pub fn line_len(&self, line: usize) -> usize {
+        match self.line(line) {
+            Some(slice) => slice.chars().take_while(|c| *c != '\n' && *c != '\r').count(),
+            None => 0,
+        }
+    }
+
+    /// converts a cell column of a line into a character column.
+    /// A cell in the middle of a tab or wide character snaps to it, cells past the end snap to the end.
+    pub fn cell_to_column(&self, line: usize, cell: u32, tab_width: u32) -> usize {
+        let slice = match self.line(line) {
+            Some(slice) => slice,
+            None => return 0,
+        };
+
+        let mut current = 0;
+        for (column, c) in slice.chars().enumerate() {
+            if c == '\n' || c == '\r' {
+                return column;
+            }
+
+            let width = char_width(c, tab_width);
+            if cell < current + width {
+                return column;
+            }
+            current += width;
+        }
+
+        slice.chars().count()
+    }
+
+    /// converts a character column of a line into the cell it is rendered at.
+    pub fn column_to_cell(&self, line: usize, column: usize, tab_width: u32) -> u32 {
+        match self.line(line) {
+            Some(slice) => slice
+                .chars()
+                .take(column)
+                .map(|c| char_width(c, tab_width))
+                .sum(),
+            None => 0,
+        }
+    }
+
+    /// the character columns of the word at (line, column) as [start, end).
+    /// If the column is not on a word then the run of similar characters is used.
+    pub fn word_bounds(&self, line: usize, column: usize) -> (usize, usize) {
+        let chars: Vec<char> = match self.line(line) {
+            Some(slice) => slice.chars().take(self.line_len(line)).collect(),
+            None => return (0, 0),
+        };
+
+        if chars.is_empty() {
+            return (0, 0);
+        }
+
+        let column = column.min(chars.len() - 1);
+        let class = |c: char| {
+            if c.is_alphanumeric() || c == '_' {
+                0
+            } else if c.is_whitespace() {
+                1
+            } else {
+                2
+            }
+        };
+
+        let target = class(chars[column]);
+        let mut start = column;
+        while start > 0 && class(chars[start - 1]) == target {
+            start -= 1;
+        }
+
+        let mut end = column;
+        while end < chars.len() && class(chars[end]) == target {
+            end += 1;
+        }
+
+        (start, end)
     }
 }
 
 impl Engine {
     pub fn new(config: Rc<config::Config>) -> Self {
         Self {
-            docs: Vec::new(),
-            document_map: HashMap::new(),
+            docs: SlotMap::new(),
+            buffers: BufferList::new(),
+            paths: HashMap::new(),
             config
         }
     }
 
+    /// replaces the settings used for documents opened from now on.
+    pub fn set_config(&mut self, config: Rc<config::Config>) {
+        self.config = config;
+    }
+
     /// Attempts to open a given file on a pane.
     /// Handles the pane/document association.
     /// pane: The ID of the pane that is opening this file
     /// path: Path of the file attempting to be open.
     pub fn open_document(&mut self, path: &str) -> Result<DocID> {
         let doc = self.open_file(path)?;
-        self.register_document(&doc)
+        self.register_document(doc)
+    }
+
+    /// takes ownership of the document and gives it its id.
+    pub fn register_document(&mut self, document: Document) -> Result<DocID> {
+        let key = self
+            .docs
+            .insert_with_key(|key| Document { id: DocID(key), ..document });
+        self.buffers.add(DocID(key));
+
+        if let Some(path) = self.docs.get(key).and_then(|document| document.path.as_ref()) {
+            self.paths.insert(path::PathBuf::from(path), DocID(key));
+        }
+
+        Ok(DocID(key))
+    }
+
+    pub fn buffers(&self) -> &BufferList {
+        &self.buffers
+    }
+
+    /// the open document of the file, so a file opened twice is not loaded twice.
+    pub fn find_document(&self, path: &str) -> Option<DocID> {
+        let path = fs::canonicalize(path).ok()?;
+        self.paths.get(&path).cloned()
     }
 
-    pub fn register_document(&mut self, document: &Document) -> Result<DocID> {
-        // This is a copy of the entire document. This isn't good.
-        let id = document.id();
-        self.docs.push(document.clone());
-        let index = self.docs.len() - 1;
+    /// the documents of the file, or of the files under it when it is a directory. The path
+    /// has to be canonical like the paths of the documents.
+    pub fn documents_under(&self, path: &path::Path) -> Vec<DocID> {
+        self.docs
+            .iter()
+            .filter(|(_, document)| match document.path.as_ref() {
+                Some(p) => path::Path::new(p).starts_with(path),
+                None => false,
+            })
+            .map(|(key, _)| DocID(key))
+            .collect()
+    }
 
-        self.document_map
-            .entry(id)
-            .and_modify(|e| *e = index)
-            .or_insert(index);
+    /// moves the documents of the file, or of the files under the directory, to where it was
+    /// renamed. Both paths have to be canonical.
+    pub fn rename_path(&mut self, from: &path::Path, to: &path::Path) {
+        for doc in self.documents_under(from) {
+            if let Some(document) = self.docs.get_mut(doc.0) {
+                let old = match document.path.as_ref() {
+                    Some(old) => path::PathBuf::from(old),
+                    None => continue,
+                };
+                let moved = match old.strip_prefix(from) {
+                    Ok(rest) if rest.as_os_str().is_empty() => to.to_path_buf(),
+                    Ok(rest) => to.join(rest),
+                    Err(_) => continue,
+                };
+                document.set_path(&moved);
 
-        Ok(document.id())
+                self.paths.remove(&old);
+                self.paths.insert(moved, doc);
+            }
+        }
     }
 
     /// Opens the file and retuns the index in docs.
     fn open_file(&mut self, path: &str) -> Result<Document> {
-        Ok(Document::from_path(path)?)
+        // a file that does not exist is opened as a new, empty document.
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(Error::FileError(e)),
+        };
+
+        // large files are mapped so they open instantly.
+        let mut document = if size >= self.config.large_file.threshold {
+            Document::mapped(path)?
+        } else {
+            Document::from_path(path)?
+        };
+        document.set_indent_from(&self.config.tabs);
+        Ok(document)
     }
 
+    /// drops the document, its id is rejected from then on.
     pub fn close_file(&mut self, doc: DocID) -> Result<()> {
-        unimplemented!();
+        let document = self.docs.remove(doc.0).ok_or(Error::InvalidDocID)?;
+        self.buffers.remove(doc);
+
+        if let Some(path) = document.path.as_ref() {
+            self.paths.remove(path::Path::new(path));
+        }
+        Ok(())
     }
 
     pub fn get_document(&self, doc: DocID) -> Option<&Document> {
-        match self.document_map.get(&doc) {
-            Some(e) => Some(&self.docs[*e]),
-            _ => None,
-        }
+        self.docs.get(doc.0)
     }
 
     pub fn get_mut_document(&mut self, doc: DocID) -> Option<&mut Document> {
-        match self.document_map.get(&doc) {
-            Some(e) => Some(&mut self.docs[*e]),
-            _ => None,
-        }
+        self.docs.get_mut(doc.0)
     }
 
     pub fn create_empty_document(&mut self) -> Result<DocID> {
-        let document = Document::empty(None)?;
-        self.register_document(&document)?;
-        Ok(document.id())
+        let mut document = Document::empty(None)?;
+        document.set_indent_from(&self.config.tabs);
+        self.register_document(document)
+    }
+
+    /// executes the operations on a document as a single step of its history, the edits at
+    /// every cursor are undone together. The first error is returned after every operation ran.
+    pub fn execute_group(&mut self, doc: DocID, ops: Vec<Operation>) -> Result<()> {
+        self.get_mut_document(doc).ok_or(Error::InvalidDocID)?.begin_group();
+
+        let mut result = Ok(());
+        for op in ops {
+            let executed = self.execute_on(op);
+            if result.is_ok() {
+                result = executed;
+            }
+        }
+
+        if let Some(document) = self.get_mut_document(doc) {
+            document.end_group();
+        }
+
+        result
+    }
+
+    /// takes back the last step of the document's history, returning where it was made.
+    pub fn undo(&mut self, doc: DocID) -> Result<Option<usize>> {
+        let document = self.get_mut_document(doc).ok_or(Error::InvalidDocID)?;
+        if document.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+
+        Ok(document.undo())
+    }
+
+    /// makes the last undone step of the document's history again, returning where it was made.
+    pub fn redo(&mut self, doc: DocID) -> Result<Option<usize>> {
+        let document = self.get_mut_document(doc).ok_or(Error::InvalidDocID)?;
+        if document.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+
+        Ok(document.redo())
     }
 
     /// Executes a given operation on document of pane.
@@ -289,25 +933,46 @@ impl Engine {
     /// op: The operation being executed. See Operation for more detail.
     pub fn execute_on(&mut self, op: Operation) -> Result<()> {
         let tab_size = self.config.as_ref().tabs.tab_width as u32;
-        let document = self.get_mut_document(op.doc).ok_or(Error::InvalidDocID)?;
 
         println!("Operation {:?}", op);
 
+        // closing drops the document, so it cannot be borrowed for the rest of the operations.
+        if let OperationKind::CloseFile = op.kind {
+            return self.close_file(op.doc);
+        }
+
+        let document = self.get_mut_document(op.doc).ok_or(Error::InvalidDocID)?;
+
+        let modifies = matches!(
+            op.kind,
+            OperationKind::Insert(..) | OperationKind::Delete(..) | OperationKind::Paste(..) | OperationKind::Replace(..)
+        );
+
+        if modifies && document.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+
         match op.kind {
             OperationKind::Insert(start_index, x, y, ch) => {
-                let index = document.cursor_index(start_index, x, y, tab_size);
+                let index = document.cursor_index(start_index, x, y, tab_size)?;
                 document.insert(index, ch)?
             }
             OperationKind::Delete(start_index, x, y) => {
-                let index = document.cursor_index(start_index, x, y, tab_size);
+                let index = document.cursor_index(start_index, x, y, tab_size)?;
                 document.delete(index)?
             }
             OperationKind::Paste(start_index, x, y, data) => {
-                let index = document.cursor_index(start_index, x, y, tab_size);
+                let index = document.cursor_index(start_index, x, y, tab_size)?;
                 document.paste(index, data.as_str())?
             }
+            OperationKind::Replace(index, remove, text) => {
+                document.materialize()?;
+                document.replace(index, remove, text.as_str())?
+            }
             OperationKind::WriteFile => document.write()?,
-            OperationKind::CloseFile => self.close_file(op.doc)?,
+            OperationKind::SetLineEnding(line_ending) => document.set_line_ending(line_ending),
+            OperationKind::SetEncoding(encoding) => document.set_encoding(encoding),
+            OperationKind::CloseFile => unreachable!(),
             OperationKind::Invalid => panic!("Attempting to execute invalid operatiion"),
             _ => unimplemented!(),
         }
@@ -315,3 +980,100 @@ impl Engine {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::thread;
+    use std::time::Duration;
+
+    /// a file for the engine to open, removed when it is dropped.
+    struct TempFile(path::PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, bytes: &[u8]) -> Self {
+            let path = env::temp_dir().join(format!("rem-engine-{}-{}", name, std::process::id()));
+            fs::write(&path, bytes).unwrap();
+            Self(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// an engine that maps files of at least threshold bytes.
+    fn engine(threshold: u64) -> Engine {
+        let mut config = config::Config::default();
+        config.large_file.threshold = threshold;
+        Engine::new(Rc::new(config))
+    }
+
+    #[test]
+    fn invalid_mapped_files_are_not_edited() {
+        let bytes = b"caf\xe9\nsecond line\n";
+        let file = TempFile::new("invalid", bytes);
+        let mut engine = engine(0);
+        let doc = engine.open_document(file.path()).unwrap();
+
+        // the file is checked in the background after it is indexed.
+        while !engine.get_document(doc).unwrap().mapped.as_ref().unwrap().is_checked() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let result = engine.execute_group(doc, vec![Operation::replace(doc, 0, 0, "x")]);
+        assert!(matches!(result, Err(Error::ReadOnly)));
+
+        let document = engine.get_mut_document(doc).unwrap();
+        assert!(matches!(document.materialize(), Err(Error::ReadOnly)));
+        assert!(document.is_large());
+        assert!(document.is_read_only());
+        assert_eq!(document.as_str(), String::from_utf8_lossy(bytes));
+        assert_eq!(fs::read(&file.0).unwrap(), bytes);
+    }
+
+    #[test]
+    fn mixed_line_endings_are_read_only_until_one_is_set() {
+        let file = TempFile::new("mixed", b"a\r\nb\nc\r\n");
+        let mut engine = engine(u64::MAX);
+        let doc = engine.open_document(file.path()).unwrap();
+
+        let result = engine.execute_on(Operation::replace(doc, 0, 0, "x"));
+        assert!(matches!(result, Err(Error::ReadOnly)));
+
+        engine.execute_on(Operation::set_line_ending(doc, LineEnding::Lf)).unwrap();
+        engine.execute_on(Operation::replace(doc, 0, 0, "x")).unwrap();
+        engine.execute_on(Operation::write_file(doc)).unwrap();
+        assert_eq!(fs::read(&file.0).unwrap(), b"xa\nb\nc\n");
+    }
+
+    #[test]
+    fn find_searches_across_chunks_and_wraps_around() {
+        let mut document = Document::empty(None).unwrap();
+
+        // the occurrence is moved over the chunk boundaries of the rope.
+        for offset in (0..3000).step_by(7) {
+            let text = format!("{}néedle{}", "x".repeat(offset), "y".repeat(2000));
+            document.content = Rope::from_str(&text);
+            assert_eq!(document.find("néedle", 0), Some(offset));
+        }
+
+        let text = format!("{}néedle\n{}néedle\n", "ab".repeat(3000), "çd".repeat(3000));
+        document.content = Rope::from_str(&text);
+        let first = 6000;
+        let second = first + 7 + 6000;
+
+        assert_eq!(document.find("néedle", 0), Some(first));
+        assert_eq!(document.find("néedle", first + 1), Some(second));
+        assert_eq!(document.find("néedle", second + 1), Some(first));
+        assert_eq!(document.find("needle\nçd", 0), None);
+        assert_eq!(document.find("dle\nçd", 0), Some(first + 3));
+    }
+}
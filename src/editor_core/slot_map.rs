@@ -0,0 +1,232 @@
+/// A key into a SlotMap. The generation is bumped every time a slot is
+/// reused so a key to a removed value never finds the value that replaced it.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct SlotKey {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Debug)]
+enum Entry<T> {
+    /// the slot holds a value.
+    Occupied(T),
+    /// the slot is empty and points to the next free slot.
+    Free(Option<u32>),
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    generation: u32,
+    entry: Entry<T>,
+}
+
+/// Storage with stable keys and constant time insertion, lookup and removal.
+/// Removed slots are kept in a free list and reused by later insertions.
+#[derive(Debug)]
+pub struct SlotMap<T> {
+    slots: Vec<Slot<T>>,
+    /// the most recently freed slot.
+    free: Option<u32>,
+    len: usize,
+}
+
+impl SlotKey {
+    /// a key that is never handed out by a SlotMap.
+    pub const fn null() -> Self {
+        Self {
+            index: u32::MAX,
+            generation: 0,
+        }
+    }
+}
+
+impl<T> SlotMap<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: None,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, value: T) -> SlotKey {
+        self.insert_with_key(|_| value)
+    }
+
+    /// inserts the value built by `f`, which is given the key the value will be stored under.
+    pub fn insert_with_key<F>(&mut self, f: F) -> SlotKey
+    where
+        F: FnOnce(SlotKey) -> T,
+    {
+        self.len += 1;
+
+        match self.free {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                self.free = match slot.entry {
+                    Entry::Free(next) => next,
+                    Entry::Occupied(_) => unreachable!("free list points at an occupied slot"),
+                };
+
+                let key = SlotKey {
+                    index,
+                    generation: slot.generation,
+                };
+                slot.entry = Entry::Occupied(f(key));
+                key
+            }
+            None => {
+                let key = SlotKey {
+                    index: self.slots.len() as u32,
+                    generation: 0,
+                };
+                self.slots.push(Slot {
+                    generation: 0,
+                    entry: Entry::Occupied(f(key)),
+                });
+                key
+            }
+        }
+    }
+
+    /// removes the value of the key. Keys of removed values are rejected from then on.
+    pub fn remove(&mut self, key: SlotKey) -> Option<T> {
+        let free = self.free;
+        let slot = self.slot_mut(key)?;
+
+        slot.generation = slot.generation.wrapping_add(1);
+        let entry = std::mem::replace(&mut slot.entry, Entry::Free(free));
+
+        self.free = Some(key.index);
+        self.len -= 1;
+
+        match entry {
+            Entry::Occupied(value) => Some(value),
+            Entry::Free(_) => unreachable!(),
+        }
+    }
+
+    pub fn get(&self, key: SlotKey) -> Option<&T> {
+        match self.slots.get(key.index as usize) {
+            Some(Slot {
+                generation,
+                entry: Entry::Occupied(ref value),
+            }) if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: SlotKey) -> Option<&mut T> {
+        match self.slot_mut(key) {
+            Some(Slot {
+                entry: Entry::Occupied(ref mut value),
+                ..
+            }) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, key: SlotKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// iterates over the keys and values of every occupied slot.
+    pub fn iter(&self) -> impl Iterator<Item = (SlotKey, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| match slot.entry {
+                Entry::Occupied(ref value) => Some((
+                    SlotKey {
+                        index: index as u32,
+                        generation: slot.generation,
+                    },
+                    value,
+                )),
+                Entry::Free(_) => None,
+            })
+    }
+
+    /// the slot of the key if the key is still valid.
+    fn slot_mut(&mut self, key: SlotKey) -> Option<&mut Slot<T>> {
+        match self.slots.get_mut(key.index as usize) {
+            Some(slot) if slot.generation == key.generation => match slot.entry {
+                Entry::Occupied(_) => Some(slot),
+                Entry::Free(_) => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<T> Default for SlotMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insert_and_get() {
+        let mut map = SlotMap::new();
+        let a = map.insert("a");
+        let b = map.insert("b");
+
+        assert_eq!(map.get(a), Some(&"a"));
+        assert_eq!(map.get(b), Some(&"b"));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn stale_keys_are_rejected() {
+        let mut map = SlotMap::new();
+        let a = map.insert("a");
+
+        assert_eq!(map.remove(a), Some("a"));
+        assert_eq!(map.get(a), None);
+        assert_eq!(map.remove(a), None);
+
+        // the slot is reused but the old key still does not match.
+        let b = map.insert("b");
+        assert_eq!(map.get(a), None);
+        assert_eq!(map.get(b), Some(&"b"));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn removal_keeps_other_keys_valid() {
+        let mut map = SlotMap::new();
+        let keys: Vec<SlotKey> = (0..10).map(|i| map.insert(i)).collect();
+
+        map.remove(keys[3]);
+        map.remove(keys[7]);
+
+        for (i, key) in keys.iter().enumerate() {
+            if i == 3 || i == 7 {
+                assert!(!map.contains(*key));
+            } else {
+                assert_eq!(map.get(*key), Some(&i));
+            }
+        }
+
+        assert_eq!(map.iter().count(), 8);
+    }
+
+    #[test]
+    fn insert_with_key_sees_its_key() {
+        let mut map = SlotMap::new();
+        let key = map.insert_with_key(|key| key);
+        assert_eq!(map.get(key), Some(&key));
+    }
+}
@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::window::EventsLoopProxy;
+
+/// how often the expanded directories are checked for changes on disk.
+const WATCH_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// the directories that were read and when each last changed, as the watcher thread checks them.
+type Watched = Vec<(PathBuf, Option<SystemTime>)>;
+
+/// A file or directory shown in the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// how many directories down from the root the entry is.
+    pub depth: usize,
+    pub is_dir: bool,
+    /// the children of a directory are only read once it is expanded.
+    pub expanded: bool,
+}
+
+impl Entry {
+    pub fn name(&self) -> String {
+        match self.path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => self.path.to_string_lossy().to_string(),
+        }
+    }
+}
+
+/// A change to the files asked for in the tree, made once it is confirmed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prompt {
+    /// the name of a new file in the directory, a name ending in '/' makes a directory.
+    Create(PathBuf, String),
+    /// the new name of the file.
+    Rename(PathBuf, String),
+    /// deleting the file or directory needs a 'y'.
+    Delete(PathBuf),
+}
+
+/// What a confirmed prompt did to a file that was already there, so the documents open on it
+/// can follow.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// the file or directory was moved from the first path to the second.
+    Renamed(PathBuf, PathBuf),
+    Deleted(PathBuf),
+}
+
+impl Prompt {
+    /// the line the prompt is shown as, below the entries.
+    pub fn line(&self) -> String {
+        match self {
+            Prompt::Create(_, name) => format!("New file: {}", name),
+            Prompt::Rename(_, name) => format!("Rename to: {}", name),
+            Prompt::Delete(path) => format!("Delete {}? (y/n)", file_name(path)),
+        }
+    }
+
+    /// the text typed at a create or rename prompt.
+    fn input_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Prompt::Create(_, name) | Prompt::Rename(_, name) => Some(name),
+            Prompt::Delete(_) => None,
+        }
+    }
+}
+
+/// The directories and files under the root of the project. Only the directories that were
+/// expanded are read, and they are read again when they change on disk.
+pub struct FileTree {
+    root: PathBuf,
+    /// the entries in the order they are shown.
+    entries: Vec<Entry>,
+    selected: usize,
+    /// the first entry in view.
+    start: usize,
+    /// when each directory that was read last changed, to notice changes on disk.
+    modified: HashMap<PathBuf, Option<SystemTime>>,
+    prompt: Option<Prompt>,
+    /// sends the directories to watch to the watcher thread, which stops when it is dropped.
+    watch: Option<Sender<Watched>>,
+    /// the directories the watcher thread saw change on disk.
+    changes: Option<Receiver<Vec<PathBuf>>>,
+}
+
+impl FileTree {
+    pub fn new(root: PathBuf) -> Self {
+        let mut tree = Self {
+            root,
+            entries: Vec::new(),
+            selected: 0,
+            start: 0,
+            modified: HashMap::new(),
+            prompt: None,
+            watch: None,
+            changes: None,
+        };
+
+        tree.reload();
+        tree
+    }
+
+    /// checks the directories of the tree on another thread, sending the ones that changed on
+    /// disk and waking the event loop.
+    pub fn watch(&mut self, proxy: EventsLoopProxy) {
+        let (sender, receiver) = mpsc::channel::<Watched>();
+        let (change_sender, changes) = mpsc::channel::<Vec<PathBuf>>();
+
+        let spawned = thread::Builder::new().name("file tree".to_string()).spawn(move || {
+            let mut dirs: Watched = Vec::new();
+
+            loop {
+                match receiver.recv_timeout(WATCH_INTERVAL) {
+                    Ok(watched) => dirs = watched,
+                    Err(RecvTimeoutError::Timeout) => {
+                        let mut changed = Vec::new();
+                        for (dir, time) in dirs.iter_mut() {
+                            let now = modified(dir);
+                            if now != *time {
+                                *time = now;
+                                changed.push(dir.clone());
+                            }
+                        }
+
+                        if changed.is_empty() {
+                            continue;
+                        }
+                        if change_sender.send(changed).is_err() || proxy.wakeup().is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        match spawned {
+            Ok(_) => {
+                self.watch = Some(sender);
+                self.changes = Some(changes);
+                self.update_watch();
+            }
+            Err(e) => println!("Failed to watch the file tree: {}", e),
+        }
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn selected_entry(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn prompt(&self) -> Option<&Prompt> {
+        self.prompt.as_ref()
+    }
+
+    /// the lines of the tree for a pane of `rows` rows, scrolled so the selected entry is in view.
+    /// The last row shows the prompt while there is one.
+    pub fn lines(&mut self, rows: usize) -> Vec<String> {
+        let rows = if self.prompt.is_some() { rows.saturating_sub(1) } else { rows }.max(1);
+
+        if self.selected < self.start {
+            self.start = self.selected;
+        } else if self.selected >= self.start + rows {
+            self.start = self.selected + 1 - rows;
+        }
+
+        let mut lines: Vec<String> = self
+            .entries
+            .iter()
+            .skip(self.start)
+            .take(rows)
+            .map(|entry| {
+                let marker = match (entry.is_dir, entry.expanded) {
+                    (true, true) => "- ",
+                    (true, false) => "+ ",
+                    (false, _) => "  ",
+                };
+                let slash = if entry.is_dir { "/" } else { "" };
+
+                format!("{}{}{}{}", "  ".repeat(entry.depth), marker, entry.name(), slash)
+            })
+            .collect();
+
+        if let Some(prompt) = self.prompt.as_ref() {
+            lines.resize(rows, String::new());
+            lines.push(prompt.line());
+        }
+
+        lines
+    }
+
+    /// the row of the pane the selected entry is on.
+    pub fn selected_row(&self) -> usize {
+        self.selected.saturating_sub(self.start)
+    }
+
+    /// moves the selection by a number of entries.
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+
+        let selected = self.selected as isize + delta;
+        self.selected = selected.max(0).min(self.entries.len() as isize - 1) as usize;
+    }
+
+    /// selects the entry on the row of the pane, returning false if there is none.
+    pub fn select_row(&mut self, row: usize) -> bool {
+        let index = self.start + row;
+        if index < self.entries.len() {
+            self.selected = index;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// expands a collapsed directory or collapses an expanded one.
+    pub fn toggle(&mut self) {
+        match self.selected_entry() {
+            Some(entry) if entry.is_dir && entry.expanded => self.collapse(),
+            Some(entry) if entry.is_dir => self.expand(),
+            _ => {}
+        }
+    }
+
+    /// reads the children of the selected directory into the tree.
+    pub fn expand(&mut self) {
+        let entry = match self.entries.get(self.selected) {
+            Some(entry) if entry.is_dir && !entry.expanded => entry.clone(),
+            _ => return,
+        };
+
+        let children = self.read_dir(&entry.path, entry.depth + 1);
+        self.entries[self.selected].expanded = true;
+
+        let index = self.selected + 1;
+        self.entries.splice(index..index, children);
+        self.update_watch();
+    }
+
+    /// hides the children of the selected directory, or selects the parent of a file or a
+    /// collapsed directory.
+    pub fn collapse(&mut self) {
+        let entry = match self.entries.get(self.selected) {
+            Some(entry) => entry.clone(),
+            None => return,
+        };
+
+        if entry.is_dir && entry.expanded {
+            let end = self.subtree_end(self.selected);
+
+            // the directories that are hidden are not watched anymore.
+            for hidden in self.entries.drain(self.selected + 1..end) {
+                self.modified.remove(&hidden.path);
+            }
+            self.modified.remove(&entry.path);
+            self.entries[self.selected].expanded = false;
+            self.update_watch();
+        } else if let Some(parent) = (0..self.selected).rev().find(|i| self.entries[*i].depth < entry.depth) {
+            self.selected = parent;
+        }
+    }
+
+    /// reads the tree again if the watcher saw a directory that is still shown change on disk,
+    /// returning true when it did. The expanded directories and the selected entry are kept.
+    pub fn refresh(&mut self) -> bool {
+        let changed = match self.changes.as_ref() {
+            Some(changes) => changes.try_iter().flatten().any(|dir| self.modified.contains_key(&dir)),
+            None => false,
+        };
+        if changed {
+            self.reload();
+        }
+
+        changed
+    }
+
+    /// reads every expanded directory again.
+    pub fn reload(&mut self) {
+        let expanded: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.expanded)
+            .map(|entry| entry.path.clone())
+            .collect();
+        let selected = self.selected_entry().map(|entry| entry.path.clone());
+
+        self.modified.clear();
+        let root = self.root.clone();
+        self.entries = self.read_dir(&root, 0);
+
+        // expanding a directory reads its children, which are expanded in turn.
+        let mut index = 0;
+        while index < self.entries.len() {
+            if self.entries[index].is_dir && expanded.contains(&self.entries[index].path) {
+                self.selected = index;
+                self.expand();
+            }
+            index += 1;
+        }
+
+        self.selected = selected
+            .and_then(|path| self.entries.iter().position(|entry| entry.path == path))
+            .unwrap_or(0)
+            .min(self.entries.len().saturating_sub(1));
+
+        self.update_watch();
+    }
+
+    /// asks for the name of a new file next to the selected entry, or in it if it is an
+    /// expanded directory.
+    pub fn prompt_create(&mut self) {
+        let dir = match self.selected_entry() {
+            Some(entry) if entry.is_dir && entry.expanded => entry.path.clone(),
+            Some(entry) => entry.path.parent().map_or(self.root.clone(), Path::to_path_buf),
+            None => self.root.clone(),
+        };
+
+        self.prompt = Some(Prompt::Create(dir, String::new()));
+    }
+
+    pub fn prompt_rename(&mut self) {
+        if let Some(entry) = self.selected_entry() {
+            self.prompt = Some(Prompt::Rename(entry.path.clone(), entry.name()));
+        }
+    }
+
+    pub fn prompt_delete(&mut self) {
+        if let Some(entry) = self.selected_entry() {
+            self.prompt = Some(Prompt::Delete(entry.path.clone()));
+        }
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        self.prompt = None;
+    }
+
+    /// types a character at the prompt, a 'y' confirms a delete and anything else cancels it.
+    pub fn prompt_input(&mut self, ch: char) -> io::Result<Option<Change>> {
+        let prompt = match self.prompt.as_mut() {
+            Some(prompt) => prompt,
+            None => return Ok(None),
+        };
+
+        if let Prompt::Delete(_) = prompt {
+            if ch == 'y' || ch == 'Y' {
+                return self.confirm_prompt();
+            }
+
+            self.prompt = None;
+            return Ok(None);
+        }
+
+        if let Some(input) = prompt.input_mut() {
+            input.push(ch);
+        }
+        Ok(None)
+    }
+
+    pub fn prompt_backspace(&mut self) {
+        if let Some(input) = self.prompt.as_mut().and_then(Prompt::input_mut) {
+            input.pop();
+        }
+    }
+
+    /// makes the change the prompt asked for and reads the tree again, returning what happened
+    /// to an existing file. The prompt stays open when the change fails.
+    pub fn confirm_prompt(&mut self) -> io::Result<Option<Change>> {
+        let prompt = match self.prompt.clone() {
+            Some(prompt) => prompt,
+            None => return Ok(None),
+        };
+
+        let (selected, change) = match prompt {
+            Prompt::Create(dir, name) => {
+                // a trailing '/' makes a directory.
+                let file = name.trim_end_matches('/');
+                if !is_valid_name(file) {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "the name is not valid"));
+                }
+                let path = dir.join(file);
+                if path.exists() {
+                    return Err(io::Error::new(io::ErrorKind::AlreadyExists, "the file already exists"));
+                }
+
+                if name.ends_with('/') {
+                    fs::create_dir(&path)?;
+                } else {
+                    fs::OpenOptions::new().write(true).create_new(true).open(&path)?;
+                }
+
+                self.expand_path(&dir);
+                (Some(path), None)
+            }
+            Prompt::Rename(from, name) => {
+                if !is_valid_name(&name) {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "the name is not valid"));
+                }
+                let to = from.with_file_name(&name);
+                if to.exists() && to != from {
+                    return Err(io::Error::new(io::ErrorKind::AlreadyExists, "the file already exists"));
+                }
+
+                fs::rename(&from, &to)?;
+                (Some(to.clone()), Some(Change::Renamed(from, to)))
+            }
+            Prompt::Delete(path) => {
+                if path.is_dir() {
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    fs::remove_file(&path)?;
+                }
+                (None, Some(Change::Deleted(path)))
+            }
+        };
+
+        self.prompt = None;
+        self.reload();
+
+        if let Some(index) = selected.and_then(|path| self.entries.iter().position(|entry| entry.path == path)) {
+            self.selected = index;
+        }
+
+        Ok(change)
+    }
+
+    /// marks the directory as expanded so the next reload shows what was made in it.
+    fn expand_path(&mut self, dir: &Path) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.path == dir) {
+            entry.expanded = true;
+        }
+    }
+
+    /// the index after the last entry below the directory at the index.
+    fn subtree_end(&self, index: usize) -> usize {
+        let depth = self.entries[index].depth;
+        (index + 1..self.entries.len())
+            .find(|i| self.entries[*i].depth <= depth)
+            .unwrap_or(self.entries.len())
+    }
+
+    /// the entries of the directory, directories first and then by name.
+    fn read_dir(&mut self, dir: &Path, depth: usize) -> Vec<Entry> {
+        self.modified.insert(dir.to_path_buf(), modified(dir));
+
+        let mut entries: Vec<Entry> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name() != ".git")
+                .map(|entry| Entry {
+                    is_dir: entry.file_type().map(|t| t.is_dir()).unwrap_or(false),
+                    path: entry.path(),
+                    depth,
+                    expanded: false,
+                })
+                .collect(),
+            Err(e) => {
+                println!("Unable to read {}: {}", dir.display(), e);
+                Vec::new()
+            }
+        };
+
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name().cmp(&b.name())));
+        entries
+    }
+
+    /// tells the watcher thread which directories are read and when they last changed.
+    fn update_watch(&self) {
+        if let Some(watch) = self.watch.as_ref() {
+            let _ = watch.send(self.modified.iter().map(|(dir, time)| (dir.clone(), *time)).collect());
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// a name given at a prompt has to name a file in the directory, it cannot reach out of it.
+fn is_valid_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => !name.contains('/') && !name.contains('\\'),
+        _ => false,
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or(String::new(), |name| name.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    /// a directory of files to build a tree of, removed when it is dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("rem-file-tree-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("src/render")).unwrap();
+            fs::write(dir.join("src/main.rs"), "").unwrap();
+            fs::write(dir.join("src/render/mod.rs"), "").unwrap();
+            fs::write(dir.join("Cargo.toml"), "").unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn names(tree: &FileTree) -> Vec<String> {
+        tree.entries().iter().map(|e| format!("{}{}", "  ".repeat(e.depth), e.name())).collect()
+    }
+
+    #[test]
+    fn directories_are_read_when_expanded() {
+        let dir = TempDir::new("expand");
+        let mut tree = FileTree::new(dir.0.clone());
+        assert_eq!(names(&tree), vec!["src", "Cargo.toml"]);
+
+        tree.expand();
+        assert_eq!(names(&tree), vec!["src", "  render", "  main.rs", "Cargo.toml"]);
+
+        // collapsing a file selects its directory, which collapses next.
+        tree.move_selection(2);
+        tree.collapse();
+        assert_eq!(tree.selected_entry().unwrap().name(), "src");
+        tree.collapse();
+        assert_eq!(names(&tree), vec!["src", "Cargo.toml"]);
+    }
+
+    #[test]
+    fn reloading_keeps_expanded_directories() {
+        let dir = TempDir::new("reload");
+        let mut tree = FileTree::new(dir.0.clone());
+        tree.expand();
+        tree.move_selection(2);
+
+        fs::write(dir.0.join("src/lib.rs"), "").unwrap();
+        tree.reload();
+
+        assert_eq!(names(&tree), vec!["src", "  render", "  lib.rs", "  main.rs", "Cargo.toml"]);
+        assert_eq!(tree.selected_entry().unwrap().name(), "main.rs");
+    }
+
+    #[test]
+    fn prompts_change_the_files() {
+        let dir = TempDir::new("prompt");
+        let mut tree = FileTree::new(dir.0.clone());
+
+        tree.move_selection(1);
+        tree.prompt_create();
+        "notes.txt".chars().for_each(|c| assert_eq!(tree.prompt_input(c).unwrap(), None));
+        assert_eq!(tree.confirm_prompt().unwrap(), None);
+        assert!(dir.0.join("notes.txt").exists());
+        assert_eq!(tree.selected_entry().unwrap().name(), "notes.txt");
+
+        tree.prompt_rename();
+        (0..3).for_each(|_| tree.prompt_backspace());
+        "md".chars().for_each(|c| assert_eq!(tree.prompt_input(c).unwrap(), None));
+        let renamed = Change::Renamed(dir.0.join("notes.txt"), dir.0.join("notes.md"));
+        assert_eq!(tree.confirm_prompt().unwrap(), Some(renamed));
+        assert!(dir.0.join("notes.md").exists());
+
+        // a name cannot reach out of the directory.
+        for name in &["../escape", "/tmp/escape", "a/b", ".."] {
+            tree.prompt_create();
+            name.chars().for_each(|c| assert_eq!(tree.prompt_input(c).unwrap(), None));
+            assert!(tree.confirm_prompt().is_err());
+            tree.cancel_prompt();
+        }
+        assert!(!dir.0.join("../escape").exists());
+
+        // anything but 'y' keeps the file.
+        tree.prompt_delete();
+        assert_eq!(tree.prompt_input('n').unwrap(), None);
+        assert!(tree.prompt().is_none());
+        assert!(dir.0.join("notes.md").exists());
+
+        tree.prompt_delete();
+        assert_eq!(tree.prompt_input('y').unwrap(), Some(Change::Deleted(dir.0.join("notes.md"))));
+        assert!(!dir.0.join("notes.md").exists());
+    }
+}
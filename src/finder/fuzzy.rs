@@ -0,0 +1,163 @@
+/// the score of every matched character.
+const SCORE_MATCH: i64 = 16;
+/// the penalty for the first skipped character of a gap and for each one after it.
+const GAP_START: i64 = -3;
+const GAP_EXTENSION: i64 = -1;
+/// bonuses for matching at the start of a word, a camel case hump or right after the last match.
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+/// the bonus of the first character of the query is worth more.
+const FIRST_CHAR_MULTIPLIER: i64 = 2;
+/// matches that start in the file name rather than the directories before it.
+const BONUS_FILE_NAME: i64 = 8;
+
+/// How well a candidate matched the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub score: i64,
+    /// the character indices of the candidate that matched the query.
+    pub positions: Vec<usize>,
+}
+
+/// scores the candidate if the query is a subsequence of it, like fzf does. The query only
+/// matches case sensitively when it has an upper case character.
+pub fn score(query: &str, candidate: &str) -> Option<Match> {
+    let query: Vec<char> = query.chars().collect();
+    if query.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    let case_sensitive = query.iter().any(|c| c.is_uppercase());
+    let matches = |a: char, b: char| {
+        if case_sensitive {
+            a == b
+        } else {
+            a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
+
+    let chars: Vec<char> = candidate.chars().collect();
+
+    // the first place the whole query matches ends.
+    let mut next = 0;
+    let mut end = None;
+    for (i, c) in chars.iter().enumerate() {
+        if matches(*c, query[next]) {
+            next += 1;
+            if next == query.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // going back from there finds the shortest window the query matches in.
+    let mut next = query.len() - 1;
+    let mut start = 0;
+    for i in (0..=end).rev() {
+        if matches(chars[i], query[next]) {
+            if next == 0 {
+                start = i;
+                break;
+            }
+            next -= 1;
+        }
+    }
+
+    let mut score = 0;
+    let mut positions = Vec::with_capacity(query.len());
+    let mut in_gap = false;
+    let mut last_matched = false;
+
+    for i in start..=end {
+        let next = positions.len();
+
+        if next < query.len() && matches(chars[i], query[next]) {
+            let mut bonus = bonus(&chars, i);
+            if next == 0 {
+                bonus *= FIRST_CHAR_MULTIPLIER;
+            }
+            if last_matched {
+                bonus = bonus.max(BONUS_CONSECUTIVE);
+            }
+
+            score += SCORE_MATCH + bonus;
+            positions.push(i);
+            in_gap = false;
+            last_matched = true;
+        } else {
+            score += if in_gap { GAP_EXTENSION } else { GAP_START };
+            in_gap = true;
+            last_matched = false;
+        }
+    }
+
+    let file_name = chars.iter().rposition(|c| *c == '/' || *c == '\\').map_or(0, |i| i + 1);
+    if start >= file_name {
+        score += BONUS_FILE_NAME;
+    }
+
+    Some(Match { score, positions })
+}
+
+/// the bonus of matching the character at the index, by what comes before it.
+fn bonus(chars: &[char], index: usize) -> i64 {
+    let c = chars[index];
+    if index == 0 {
+        return BONUS_BOUNDARY;
+    }
+
+    let prev = chars[index - 1];
+    if !prev.is_alphanumeric() && c.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if prev.is_lowercase() && c.is_uppercase() {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_subsequences_match() {
+        assert!(score("mnr", "src/main.rs").is_some());
+        assert!(score("rnm", "src/main.rs").is_none());
+        assert_eq!(score("", "src/main.rs").unwrap().score, 0);
+    }
+
+    #[test]
+    fn positions_are_the_shortest_window() {
+        let m = score("main", "src/main.rs").unwrap();
+        assert_eq!(m.positions, vec![4, 5, 6, 7]);
+
+        // the 'm' of "xm" is skipped for the one closer to the rest of the query.
+        let m = score("ma", "xm/ma").unwrap();
+        assert_eq!(m.positions, vec![3, 4]);
+    }
+
+    #[test]
+    fn word_starts_score_higher() {
+        let boundary = score("fb", "foo_bar").unwrap().score;
+        let middle = score("fb", "afxbx").unwrap().score;
+        assert!(boundary > middle);
+
+        let file_name = score("main", "src/main.rs").unwrap().score;
+        let directory = score("main", "main/lib.rs").unwrap().score;
+        assert!(file_name > directory);
+    }
+
+    #[test]
+    fn upper_case_queries_are_case_sensitive() {
+        assert!(score("readme", "README.md").is_some());
+        assert!(score("README", "readme.md").is_none());
+        assert!(score("Read", "README.md").is_none());
+    }
+}
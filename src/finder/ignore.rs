@@ -0,0 +1,137 @@
+/// A line of a .gitignore file.
+#[derive(Debug, Clone)]
+struct Pattern {
+    glob: Vec<char>,
+    /// a pattern starting with '!' includes what an earlier pattern ignored.
+    negated: bool,
+    /// a pattern ending in '/' only matches directories.
+    dir_only: bool,
+    /// a pattern with a '/' before its end matches the path from the directory of the file,
+    /// otherwise it matches the name of a file in any directory below it.
+    anchored: bool,
+}
+
+/// The patterns of a .gitignore file, matched against paths relative to the directory it is in.
+#[derive(Debug, Clone, Default)]
+pub struct Gitignore {
+    patterns: Vec<Pattern>,
+}
+
+impl Gitignore {
+    pub fn parse(text: &str) -> Self {
+        let patterns = text
+            .lines()
+            .map(|line| line.trim_end())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line.strip_prefix('\\').unwrap_or(line)),
+                };
+
+                let dir_only = line.ends_with('/');
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+
+                Pattern {
+                    glob: line.trim_start_matches('/').chars().collect(),
+                    negated,
+                    dir_only,
+                    anchored,
+                }
+            })
+            .filter(|pattern| !pattern.glob.is_empty())
+            .collect();
+
+        Self { patterns }
+    }
+
+    /// whether the path is ignored by the last pattern that matches it, None when none do.
+    /// The path is relative to the directory of the file and separated by '/'.
+    pub fn matches(&self, path: &str, is_dir: bool) -> Option<bool> {
+        let path: Vec<char> = path.chars().collect();
+        let name_start = path.iter().rposition(|c| *c == '/').map_or(0, |i| i + 1);
+
+        self.patterns
+            .iter()
+            .rev()
+            .filter(|pattern| is_dir || !pattern.dir_only)
+            .find(|pattern| {
+                if pattern.anchored {
+                    glob_match(&pattern.glob, &path)
+                } else {
+                    glob_match(&pattern.glob, &path[name_start..])
+                }
+            })
+            .map(|pattern| !pattern.negated)
+    }
+}
+
+/// matches the text against the glob, '*' and '?' do not match a '/' but "**" does.
+fn glob_match(glob: &[char], text: &[char]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some('*') if glob.get(1) == Some(&'*') => {
+            // "**/" also matches no directories at all.
+            let rest = &glob[2..];
+            if rest.first() == Some(&'/') && glob_match(&rest[1..], text) {
+                return true;
+            }
+
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some('*') => {
+            let rest = &glob[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => match text.first() {
+            Some(c) if *c != '/' => glob_match(&glob[1..], &text[1..]),
+            _ => false,
+        },
+        Some(c) => text.first() == Some(c) && glob_match(&glob[1..], &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_match_in_any_directory() {
+        let ignore = Gitignore::parse("# build output\ntarget/\n*.log\n");
+
+        assert_eq!(ignore.matches("target", true), Some(true));
+        assert_eq!(ignore.matches("sub/target", true), Some(true));
+        assert_eq!(ignore.matches("target", false), None);
+        assert_eq!(ignore.matches("logs/debug.log", false), Some(true));
+        assert_eq!(ignore.matches("src/main.rs", false), None);
+    }
+
+    #[test]
+    fn slashes_anchor_to_the_directory() {
+        let ignore = Gitignore::parse("/build\ndocs/*.html\nassets/**/*.png\n");
+
+        assert_eq!(ignore.matches("build", true), Some(true));
+        assert_eq!(ignore.matches("src/build", true), None);
+        assert_eq!(ignore.matches("docs/index.html", false), Some(true));
+        assert_eq!(ignore.matches("docs/api/index.html", false), None);
+        assert_eq!(ignore.matches("assets/icon.png", false), Some(true));
+        assert_eq!(ignore.matches("assets/a/b/icon.png", false), Some(true));
+    }
+
+    #[test]
+    fn later_patterns_win() {
+        let ignore = Gitignore::parse("*.txt\n!keep.txt\n");
+
+        assert_eq!(ignore.matches("notes.txt", false), Some(true));
+        assert_eq!(ignore.matches("keep.txt", false), Some(false));
+    }
+}
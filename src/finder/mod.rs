@@ -0,0 +1,252 @@
+pub mod fuzzy;
+pub mod ignore;
+pub mod walk;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use crate::pane::PaneID;
+use crate::window::EventsLoopProxy;
+
+/// the most files listed for a query.
+const MAX_RESULTS: usize = 256;
+/// how much of the highlighted file is read for its preview.
+const PREVIEW_BYTES: u64 = 64 * 1024;
+const PREVIEW_LINES: usize = 256;
+
+/// A file or item that matched the query.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// the index of the item in the list, or of the file in the walk.
+    pub index: usize,
+    /// the path relative to the root of the project, or the text of the item.
+    pub path: String,
+    /// the character indices of the path that matched the query.
+    pub positions: Vec<usize>,
+}
+
+/// What the finder picks from.
+enum Source {
+    /// the files under the directory.
+    Walk(PathBuf),
+    Items(Vec<String>),
+}
+
+/// Finds files of the project, or items of a list, by a fuzzy query. The project is walked and
+/// the files are scored on a background thread, which wakes the event loop when the results of
+/// a query are ready.
+pub struct Finder {
+    /// the directory of the files, items of a list have no root and no preview.
+    root: Option<PathBuf>,
+    query: String,
+    results: Vec<Candidate>,
+    selected: usize,
+    /// the first lines of the selected file.
+    preview: Vec<String>,
+    queries: Sender<String>,
+    replies: Receiver<(String, Vec<Candidate>)>,
+    /// the panes the list and the preview are drawn in, over the main window.
+    list_pane: PaneID,
+    preview_pane: PaneID,
+}
+
+impl Finder {
+    /// a finder of the files under the root.
+    pub fn open(root: PathBuf, proxy: EventsLoopProxy) -> Self {
+        Self::spawn(Source::Walk(root.clone()), Some(root), proxy)
+    }
+
+    /// a finder of the items, like the open buffers.
+    pub fn with_items(items: Vec<String>, proxy: EventsLoopProxy) -> Self {
+        Self::spawn(Source::Items(items), None, proxy)
+    }
+
+    fn spawn(source: Source, root: Option<PathBuf>, proxy: EventsLoopProxy) -> Self {
+        let (queries, receiver) = mpsc::channel();
+        let (sender, replies) = mpsc::channel();
+
+        let spawned = thread::Builder::new()
+            .name("finder".to_string())
+            .spawn(move || run(source, receiver, sender, proxy));
+
+        if let Err(e) = spawned {
+            println!("Failed to start the finder: {}", e);
+        }
+
+        let finder = Self {
+            root,
+            query: String::new(),
+            results: Vec::new(),
+            selected: 0,
+            preview: Vec::new(),
+            queries,
+            replies,
+            list_pane: PaneID::next(),
+            preview_pane: PaneID::next(),
+        };
+
+        // everything is listed before anything is typed.
+        finder.search();
+        finder
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn results(&self) -> &[Candidate] {
+        &self.results
+    }
+
+    /// the index of the selected result.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn preview(&self) -> &[String] {
+        &self.preview
+    }
+
+    pub fn list_pane(&self) -> PaneID {
+        self.list_pane
+    }
+
+    pub fn preview_pane(&self) -> PaneID {
+        self.preview_pane
+    }
+
+    /// the path of the selected file.
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        let root = self.root.as_ref()?;
+        self.results.get(self.selected).map(|candidate| root.join(&candidate.path))
+    }
+
+    /// the index of the selected item in the list.
+    pub fn selected_item(&self) -> Option<usize> {
+        self.results.get(self.selected).map(|candidate| candidate.index)
+    }
+
+    /// the selected file is previewed, items of a list are not.
+    pub fn has_preview(&self) -> bool {
+        self.root.is_some()
+    }
+
+    pub fn push(&mut self, ch: char) {
+        self.query.push(ch);
+        self.search();
+    }
+
+    pub fn pop(&mut self) {
+        if self.query.pop().is_some() {
+            self.search();
+        }
+    }
+
+    /// moves the selection up or down the results.
+    pub fn move_selection(&mut self, down: bool) {
+        if self.results.is_empty() {
+            return;
+        }
+
+        let selected = if down {
+            (self.selected + 1).min(self.results.len() - 1)
+        } else {
+            self.selected.saturating_sub(1)
+        };
+
+        if selected != self.selected {
+            self.selected = selected;
+            self.load_preview();
+        }
+    }
+
+    /// takes the results of the current query from the background thread, true when they changed.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+
+        for (query, results) in self.replies.try_iter() {
+            // the results of a query that was typed over are dropped.
+            if query == self.query {
+                self.results = results;
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.selected = 0;
+            self.load_preview();
+        }
+
+        changed
+    }
+
+    fn search(&self) {
+        let _ = self.queries.send(self.query.clone());
+    }
+
+    fn load_preview(&mut self) {
+        self.preview = match self.selected_path() {
+            Some(path) => read_preview(&path),
+            None => Vec::new(),
+        };
+    }
+}
+
+/// the first lines of the file, binary files are shown as their valid text.
+fn read_preview(path: &PathBuf) -> Vec<String> {
+    let mut bytes = Vec::new();
+    let read = File::open(path).and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut bytes));
+
+    match read {
+        Ok(_) => String::from_utf8_lossy(&bytes)
+            .lines()
+            .take(PREVIEW_LINES)
+            .map(|line| line.to_string())
+            .collect(),
+        Err(e) => vec![format!("Unable to read {}: {}", path.display(), e)],
+    }
+}
+
+/// walks the project once, or takes the items, then scores them for each query. Queries that were sent while
+/// one was scored are skipped for the newest one.
+fn run(source: Source, queries: Receiver<String>, replies: Sender<(String, Vec<Candidate>)>, proxy: EventsLoopProxy) {
+    let items = match source {
+        Source::Walk(root) => walk::walk(&root),
+        Source::Items(items) => items,
+    };
+
+    while let Ok(query) = queries.recv() {
+        let query = queries.try_iter().last().unwrap_or(query);
+
+        let mut matches: Vec<(i64, Candidate)> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, path)| {
+                fuzzy::score(&query, path).map(|m| {
+                    let candidate = Candidate {
+                        index,
+                        path: path.clone(),
+                        positions: m.positions,
+                    };
+                    (m.score, candidate)
+                })
+            })
+            .collect();
+
+        // shorter paths win a tie, everything is listed in its order before a query is typed.
+        if !query.is_empty() {
+            matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.path.len().cmp(&b.1.path.len())));
+        }
+        matches.truncate(MAX_RESULTS);
+
+        let results = matches.into_iter().map(|(_, candidate)| candidate).collect();
+
+        // the finder was closed or the window is gone.
+        if replies.send((query, results)).is_err() || proxy.wakeup().is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+
+use super::ignore::Gitignore;
+
+/// the most files collected from a project, so a huge directory cannot stall the finder.
+const MAX_FILES: usize = 100_000;
+
+/// the files under the root as '/' separated paths relative to it. Whatever the .gitignore
+/// files of the directories ignore is skipped, along with the .git directory.
+pub fn walk(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut ignores = Vec::new();
+
+    visit(root, "", &mut ignores, &mut files);
+
+    files
+}
+
+/// the .gitignore files that apply to a directory, with the directory each one is in.
+type Ignores = Vec<(String, Gitignore)>;
+
+fn visit(dir: &Path, relative: &str, ignores: &mut Ignores, files: &mut Vec<String>) {
+    let gitignore = fs::read_to_string(dir.join(".gitignore")).ok();
+    let pushed = gitignore.is_some();
+    if let Some(text) = gitignore {
+        ignores.push((relative.to_string(), Gitignore::parse(&text)));
+    }
+
+    let mut entries: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).collect(),
+        Err(_) => Vec::new(),
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        if files.len() >= MAX_FILES {
+            break;
+        }
+
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+
+        if name == ".git" {
+            continue;
+        }
+
+        // symbolic links are not followed into directories so the walk cannot loop.
+        let is_dir = match entry.file_type() {
+            Ok(file_type) => file_type.is_dir(),
+            Err(_) => continue,
+        };
+
+        let path = if relative.is_empty() {
+            name
+        } else {
+            format!("{}/{}", relative, name)
+        };
+
+        if is_ignored(ignores, &path, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            visit(&entry.path(), &path, ignores, files);
+        } else {
+            files.push(path);
+        }
+    }
+
+    if pushed {
+        ignores.pop();
+    }
+}
+
+/// the .gitignore files closer to the path override the ones above them.
+fn is_ignored(ignores: &Ignores, path: &str, is_dir: bool) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .filter_map(|(base, ignore)| {
+            let path = if base.is_empty() {
+                path
+            } else {
+                &path[base.len() + 1..]
+            };
+
+            ignore.matches(path, is_dir)
+        })
+        .next()
+        .unwrap_or(false)
+}
@@ -0,0 +1,402 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Error, FontDesc, Result, Style};
+
+/// Directories searched for font files when fontconfig does not know a family.
+/// The last is the directory of the fonts shipped with the editor.
+const FONT_DIRS: &[&str] = &[
+    "~/.fonts",
+    "~/.local/share/fonts",
+    "/usr/share/fonts",
+    "/usr/local/share/fonts",
+    "/Library/Fonts",
+    "/System/Library/Fonts",
+    "C:\\Windows\\Fonts",
+    "dev",
+];
+
+/// the most close matches reported when a family cannot be found.
+const MAX_CLOSE_MATCHES: usize = 5;
+
+/// A font file found by scanning the font directories.
+#[derive(Debug, Clone)]
+struct FontFile {
+    path: PathBuf,
+    /// the family as it is written in the file name.
+    family: String,
+    style: Style,
+}
+
+/// Finds the files of fonts, the font directories are only scanned the first time a
+/// family is not known to fontconfig.
+#[derive(Debug)]
+pub struct Discovery {
+    /// the font files in the font directories, None until they are scanned.
+    files: Option<Vec<FontFile>>,
+}
+
+impl Discovery {
+    pub fn new() -> Self {
+        Self { files: None }
+    }
+
+    /// finds the file of a font.
+    /// A font with a path uses it, otherwise the family is looked up with fontconfig and
+    /// then in the font directories. Only a face of the requested style is accepted.
+    /// The error has no close matches, they are found with `close_matches` when reported.
+    pub fn resolve(&mut self, desc: &FontDesc) -> Result<PathBuf> {
+        if let Some(ref path) = desc.path {
+            return Ok(path.clone());
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(path) = fc::find(&desc.name, desc.style) {
+                return Ok(path);
+            }
+        }
+
+        let key = normalize(&desc.name);
+        match self
+            .files()
+            .iter()
+            .find(|f| normalize(&f.family) == key && f.style == desc.style)
+        {
+            Some(file) => Ok(file.path.clone()),
+            None => Err(Error::FontNotFound {
+                family: desc.name.clone(),
+                style: desc.style,
+                close_matches: Vec::new(),
+            }),
+        }
+    }
+
+    /// the known families closest to a family that was not found, best first.
+    pub fn close_matches(&mut self, family: &str) -> Vec<String> {
+        #[allow(unused_mut)]
+        let mut families: Vec<String> = Vec::new();
+
+        // fontconfig's names are preferred, they are spelled the way they are meant to be written.
+        #[cfg(target_os = "linux")]
+        families.extend(fc::families());
+
+        families.extend(self.files().iter().map(|f| f.family.clone()));
+
+        close_matches(family, families)
+    }
+
+    fn files(&mut self) -> &[FontFile] {
+        self.files.get_or_insert_with(|| scan(FONT_DIRS))
+    }
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// all of the font files in the directories and their sub directories.
+fn scan(dirs: &[&str]) -> Vec<FontFile> {
+    let mut files = Vec::new();
+
+    for dir in dirs {
+        let dir = match dir.starts_with("~/") {
+            true => match std::env::var_os("HOME") {
+                Some(home) => Path::new(&home).join(&dir[2..]),
+                None => continue,
+            },
+            false => PathBuf::from(dir),
+        };
+
+        scan_dir(&dir, &mut files);
+    }
+
+    files
+}
+
+fn scan_dir(dir: &Path, files: &mut Vec<FontFile>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+
+        if path.is_dir() {
+            scan_dir(&path, files);
+            continue;
+        }
+
+        let is_font = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => ["ttf", "otf", "ttc"].contains(&ext.to_lowercase().as_str()),
+            None => false,
+        };
+
+        if let (true, Some(stem)) = (is_font, path.file_stem().and_then(|s| s.to_str())) {
+            let (family, style) = parse_file_name(stem);
+            files.push(FontFile {
+                family,
+                style,
+                path: path.clone(),
+            });
+        }
+    }
+}
+
+/// splits a font file name such as `DejaVuSansMono-BoldOblique` into its family and style.
+fn parse_file_name(stem: &str) -> (String, Style) {
+    let (family, suffix) = match stem.rfind(['-', '_']) {
+        Some(index) => (&stem[..index], stem[index + 1..].to_lowercase()),
+        None => (stem, String::new()),
+    };
+
+    let bold = suffix.contains("bold");
+    let italic = suffix.contains("italic") || suffix.contains("oblique");
+
+    if bold || italic || suffix == "regular" || suffix == "book" {
+        (family.to_string(), Style::new(bold, italic))
+    } else {
+        // the dash is part of the family name.
+        (stem.to_string(), Style::Normal)
+    }
+}
+
+/// a family name without case, spaces or punctuation so `Droid Sans Mono` matches `DroidSansMono`.
+fn normalize(family: &str) -> String {
+    family
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// the families closest to the requested family, best first.
+fn close_matches(family: &str, families: Vec<String>) -> Vec<String> {
+    let key = normalize(family);
+    let limit = std::cmp::max(3, key.len() / 3);
+
+    let mut seen = HashSet::new();
+    let mut scored: Vec<(usize, String)> = families
+        .into_iter()
+        .filter_map(|f| {
+            let other = normalize(&f);
+            if other.is_empty() || !seen.insert(other.clone()) {
+                return None;
+            }
+
+            // a family containing the other is always a close match, the closer in length the better.
+            if other.contains(&key) || key.contains(&other) {
+                return Some((other.len().max(key.len()) - other.len().min(key.len()), f));
+            }
+
+            match edit_distance(&key, &other) {
+                score if score <= limit => Some((score, f)),
+                _ => None,
+            }
+        })
+        .collect();
+
+    scored.sort();
+
+    scored
+        .into_iter()
+        .take(MAX_CLOSE_MATCHES)
+        .map(|(_, f)| f)
+        .collect()
+}
+
+/// the number of single character edits between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(target_os = "linux")]
+mod fc {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_char, c_int};
+    use std::path::PathBuf;
+    use std::ptr;
+
+    use fontconfig_sys as ffi;
+    use fontconfig_sys::constants::{
+        FC_SLANT_ITALIC, FC_SLANT_ROMAN, FC_WEIGHT_BOLD, FC_WEIGHT_DEMIBOLD, FC_WEIGHT_REGULAR,
+    };
+
+    use super::{normalize, Style};
+
+    const FAMILY: &[u8] = b"family\0";
+    const FILE: &[u8] = b"file\0";
+    const WEIGHT: &[u8] = b"weight\0";
+    const SLANT: &[u8] = b"slant\0";
+
+    fn object(name: &[u8]) -> *const c_char {
+        name.as_ptr() as *const c_char
+    }
+
+    /// the strings of a pattern's object, a font can have a family name in several languages.
+    unsafe fn strings(pattern: *mut ffi::FcPattern, name: &[u8]) -> Vec<String> {
+        let mut values = Vec::new();
+        let mut value: *mut ffi::FcChar8 = ptr::null_mut();
+
+        while ffi::FcPatternGetString(pattern, object(name), values.len() as c_int, &mut value)
+            == ffi::FcResultMatch
+        {
+            values.push(CStr::from_ptr(value as *const c_char).to_string_lossy().into_owned());
+        }
+
+        values
+    }
+
+    unsafe fn integer(pattern: *mut ffi::FcPattern, name: &[u8]) -> Option<c_int> {
+        let mut value = 0;
+        match ffi::FcPatternGetInteger(pattern, object(name), 0, &mut value) {
+            ffi::FcResultMatch => Some(value),
+            _ => None,
+        }
+    }
+
+    /// the file of the best match for the family and style.
+    /// Fontconfig substitutes a default font for unknown families so the match is only
+    /// accepted if it is the family and style that was asked for.
+    pub fn find(family: &str, style: Style) -> Option<PathBuf> {
+        let name = CString::new(family).ok()?;
+
+        unsafe {
+            let pattern = ffi::FcPatternCreate();
+            ffi::FcPatternAddString(pattern, object(FAMILY), name.as_ptr() as *const ffi::FcChar8);
+
+            let weight = if style.is_bold() { FC_WEIGHT_BOLD } else { FC_WEIGHT_REGULAR };
+            let slant = if style.is_italic() { FC_SLANT_ITALIC } else { FC_SLANT_ROMAN };
+            ffi::FcPatternAddInteger(pattern, object(WEIGHT), weight);
+            ffi::FcPatternAddInteger(pattern, object(SLANT), slant);
+
+            ffi::FcConfigSubstitute(ptr::null_mut(), pattern, ffi::FcMatchPattern);
+            ffi::FcDefaultSubstitute(pattern);
+
+            let mut result = ffi::FcResultNoMatch;
+            let matched = ffi::FcFontMatch(ptr::null_mut(), pattern, &mut result);
+            ffi::FcPatternDestroy(pattern);
+
+            if matched.is_null() {
+                return None;
+            }
+
+            let key = normalize(family);
+            let same_family = strings(matched, FAMILY).iter().any(|f| normalize(f) == key);
+
+            let bold = integer(matched, WEIGHT).is_some_and(|w| w >= FC_WEIGHT_DEMIBOLD);
+            let italic = integer(matched, SLANT).is_some_and(|s| s != FC_SLANT_ROMAN);
+
+            let file = strings(matched, FILE).into_iter().next();
+            ffi::FcPatternDestroy(matched);
+
+            match file {
+                Some(file) if same_family && Style::new(bold, italic) == style => Some(PathBuf::from(file)),
+                _ => None,
+            }
+        }
+    }
+
+    /// the families of every font fontconfig knows about.
+    pub fn families() -> Vec<String> {
+        let mut families = Vec::new();
+
+        unsafe {
+            let pattern = ffi::FcPatternCreate();
+            let objects = ffi::FcObjectSetCreate();
+            ffi::FcObjectSetAdd(objects, object(FAMILY));
+
+            let set = ffi::FcFontList(ptr::null_mut(), pattern, objects);
+
+            if !set.is_null() {
+                for i in 0..(*set).nfont as isize {
+                    families.extend(strings(*(*set).fonts.offset(i), FAMILY));
+                }
+                ffi::FcFontSetDestroy(set);
+            }
+
+            ffi::FcObjectSetDestroy(objects);
+            ffi::FcPatternDestroy(pattern);
+        }
+
+        families
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn file_names() {
+        assert_eq!(parse_file_name("DroidSansMono"), ("DroidSansMono".to_string(), Style::Normal));
+        assert_eq!(parse_file_name("Hack-Regular"), ("Hack".to_string(), Style::Normal));
+        assert_eq!(
+            parse_file_name("DejaVuSansMono-BoldOblique"),
+            ("DejaVuSansMono".to_string(), Style::BoldItalics)
+        );
+        assert_eq!(parse_file_name("Fira_Italic"), ("Fira".to_string(), Style::Italics));
+        assert_eq!(parse_file_name("Source-Code-Pro"), ("Source-Code-Pro".to_string(), Style::Normal));
+    }
+
+    #[test]
+    fn families_match_without_spaces_or_case() {
+        assert_eq!(normalize("Droid Sans Mono"), normalize("DroidSansMono"));
+    }
+
+    #[test]
+    fn close_matches_are_ranked() {
+        let families = vec![
+            "DejaVu Sans Mono".to_string(),
+            "Droid Sans".to_string(),
+            "Droid Sans Mono".to_string(),
+            "Liberation Serif".to_string(),
+        ];
+
+        let matches = close_matches("Droid Sans Mon", families);
+        assert_eq!(matches, vec!["Droid Sans Mono".to_string(), "Droid Sans".to_string()]);
+    }
+
+    #[test]
+    fn the_directories_are_scanned_once_and_only_when_needed() {
+        let mut discovery = Discovery::new();
+        let mut desc = FontDesc {
+            style: Style::Normal,
+            name: "No Such Family".to_string(),
+            path: Some(PathBuf::from("dev/DroidSansMono.ttf")),
+            fallback: Vec::new(),
+        };
+
+        assert_eq!(discovery.resolve(&desc).unwrap(), PathBuf::from("dev/DroidSansMono.ttf"));
+        assert!(discovery.files.is_none());
+
+        desc.path = None;
+        match discovery.resolve(&desc) {
+            Err(Error::FontNotFound { close_matches, .. }) => assert!(close_matches.is_empty()),
+            result => panic!("found {:?}", result),
+        }
+        assert!(discovery.files.is_some());
+    }
+}
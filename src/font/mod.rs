@@ -0,0 +1,889 @@
+use super::ft;
+use std::path::PathBuf;
+
+use std::cmp::Eq;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU16, Ordering::SeqCst};
+
+mod discovery;
+
+use self::discovery::Discovery;
+
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontSize {
+    pub pixel_size: f32,
+}
+
+impl Eq for FontSize {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(u8)]
+pub enum Style {
+    #[default]
+    Normal,
+    Italics,
+    Bold,
+    BoldItalics,
+}
+
+/// A line drawn across a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decoration {
+    Underline,
+    Strikethrough,
+}
+
+/// How the edges of glyphs are smoothed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Antialias {
+    /// coverage is the same for each color channel.
+    #[default]
+    Grayscale,
+    /// coverage per subpixel for displays with the subpixels ordered red, green, blue.
+    LcdRgb,
+    /// coverage per subpixel for displays with the subpixels ordered blue, green, red.
+    LcdBgr,
+    /// no smoothing, a pixel is either covered or not.
+    Mono,
+}
+
+/// How strongly outlines are fitted to the pixel grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Hinting {
+    None,
+    /// only vertically, keeping the shapes of the glyphs.
+    #[default]
+    Slight,
+    Full,
+}
+
+/// the shear of a synthetic italic, as a 16.16 fixed point number (about 12 degrees).
+const SLANT: ft::ffi::FT_Fixed = 0x3800;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    FTError(ft::Error),
+    /// the font file could not be read.
+    FileError(PathBuf, std::io::ErrorKind),
+    MissingFont,
+    /// no font of the family and style was found, the families with similar names are listed.
+    FontNotFound {
+        family: String,
+        style: Style,
+        close_matches: Vec<String>,
+    },
+    InvalidGlyph,
+    NoSizeMetrics,
+    /// the font could not be parsed for shaping.
+    InvalidFont,
+}
+
+// describes a font by its family and style, the font file is found with fontconfig
+// or by searching the font directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontDesc {
+    /// the style of the face.
+    pub style: Style,
+    /// the family name of the font.
+    pub name: String,
+    /// a font file to use instead of looking up the family.
+    pub path: Option<PathBuf>,
+    //     pub size: Size,
+    /// fonts searched in order for characters this font does not have.
+    pub fallback: Vec<FontDesc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontKey {
+    pub token: u16,
+}
+
+// global metrics of the fonts.
+#[derive(Debug, Clone)]
+pub struct FullMetrics {
+    ft_metrics: ft::ffi::FT_Size_Metrics,
+    cell_width: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Metrics {
+    pub average_advance: f32,
+    pub line_height: f32,
+    pub descent: f32,
+    /// the center of the underline relative to the baseline, negative is below it.
+    pub underline_position: f32,
+    pub underline_thickness: f32,
+}
+
+// desciption of a glyph and font.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphKey {
+    pub ch: u32,       // to support unicode
+    pub font: FontKey, // the uniquly identify this font.
+    pub size: FontSize,
+    pub style: Style,
+}
+
+/// a glyph by its index in a face, the glyphs produced by shaping are not tied to a single character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphIndexKey {
+    /// the face the glyph is from.
+    pub font: FontKey,
+    pub index: u32,
+    /// the part of the style the face does not provide.
+    pub style: Style,
+}
+
+/// A glyph positioned by shaping a run of text, positions are in physical pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct ShapedGlyph {
+    /// the glyph, the index is 0 when the face does not have the character.
+    pub key: GlyphIndexKey,
+    /// the byte offset in the run of the first character this glyph was shaped from.
+    pub cluster: usize,
+    pub x_advance: f32,
+    pub x_offset: f32,
+    pub y_offset: f32,
+}
+
+/// the number of cells a character takes up on the grid, wide characters (CJK, emoji) take two.
+pub fn char_cells(ch: u32) -> u32 {
+    match std::char::from_u32(ch).and_then(|c| c.width()) {
+        Some(2) => 2,
+        _ => 1,
+    }
+}
+
+impl Style {
+    pub fn new(bold: bool, italic: bool) -> Self {
+        match (bold, italic) {
+            (false, false) => Style::Normal,
+            (false, true) => Style::Italics,
+            (true, false) => Style::Bold,
+            (true, true) => Style::BoldItalics,
+        }
+    }
+
+    pub fn is_bold(self) -> bool {
+        self == Style::Bold || self == Style::BoldItalics
+    }
+
+    pub fn is_italic(self) -> bool {
+        self == Style::Italics || self == Style::BoldItalics
+    }
+
+    /// the part of this style that is not provided by a face of the given style.
+    fn without(self, face: Style) -> Self {
+        Style::new(
+            self.is_bold() && !face.is_bold(),
+            self.is_italic() && !face.is_italic(),
+        )
+    }
+}
+
+/// the resolution at which a point is a pixel, the resolution of a face is this scaled by the dpi factor.
+const BASE_DPI: f64 = 72.0;
+
+#[inline]
+fn convert_to_ft(size: &FontSize) -> isize {
+    (((1 << 6) as f32) * size.pixel_size) as isize
+}
+
+#[derive(Clone)]
+struct Face {
+    pub(crate) font: FontKey,
+    pub(crate) face: ft::Face,
+    /// the font file, kept for shaping.
+    data: Rc<Vec<u8>>,
+    antialias: Antialias,
+    hinting: Hinting,
+    /// the scale of the display the glyphs are rendered for.
+    dpi_factor: f64,
+}
+
+// rasterized glyph for a specified size
+#[derive(Debug, Clone)]
+pub struct RasterizedGlyph {
+    pub glyph: u32,
+
+    pub width: f32,
+    pub height: f32,
+
+    pub top: f32,
+    pub left: f32,
+
+    pub bearing_x: f32,
+    pub bearing_y: f32,
+
+    pub advance_x: f32,
+    pub advance_y: f32,
+
+    pub bitmap: Vec<u8>,
+}
+
+pub trait Rasterizer: std::marker::Sized {
+    fn new(dpi_factor: f32) -> Result<Self>;
+
+    /// changes the scale glyphs and metrics are rendered at, font sizes stay in logical pixels.
+    fn set_dpi_factor(&mut self, dpi_factor: f32);
+
+    /// changes how glyphs are antialiased and hinted, glyphs already rendered are not changed.
+    fn set_rendering(&mut self, antialias: Antialias, hinting: Hinting);
+
+    fn load_glyph(&mut self, glyph: GlyphKey, size: FontSize) -> Result<RasterizedGlyph>;
+
+    /// renders a glyph by its index, used for the glyphs of shaped text.
+    fn load_glyph_index(&mut self, glyph: GlyphIndexKey, size: FontSize) -> Result<RasterizedGlyph>;
+
+    /// shapes a run of text with the face of the style, applying ligatures and the rules of complex scripts.
+    fn shape(&mut self, font: FontKey, style: Style, text: &str, size: FontSize) -> Result<Vec<ShapedGlyph>>;
+
+    /// the glyph to draw for characters the font does not have.
+    fn load_missing_glyph(&mut self, font: FontKey, size: FontSize) -> Result<RasterizedGlyph>;
+
+    /// a solid bar the width of a cell for drawing the decoration.
+    fn load_decoration(
+        &mut self,
+        font: FontKey,
+        size: FontSize,
+        decoration: Decoration,
+    ) -> Result<RasterizedGlyph>;
+
+    fn get_metrics(&self, font: FontKey, size: FontSize) -> Result<Metrics>;
+
+    //     fn get_full_metrics(&self, font: FontKey, size: FontSize) -> Result<FullMetrics>;
+
+    fn get_font(&mut self, font: FontDesc) -> Result<FontKey>;
+
+    /// sets the face used for a style of a font, styles without a face are synthesized.
+    fn set_style_font(&mut self, font: FontKey, styled: FontDesc) -> Result<()>;
+}
+
+pub struct FreeTypeRasterizer {
+    library: ft::Library,
+    faces: HashMap<FontKey, Face>,
+    fonts: HashMap<PathBuf, FontKey>,
+    /// the fallback fonts of each requested font, in the order they are searched.
+    fallbacks: HashMap<FontKey, Vec<FontKey>>,
+    /// the faces of the bold and italic styles of a font.
+    styles: HashMap<(FontKey, Style), FontKey>,
+    /// finds the files of the fonts by their family.
+    discovery: Discovery,
+    dpi_factor: f64,
+    antialias: Antialias,
+    hinting: Hinting,
+}
+
+impl FontKey {
+    fn next() -> Self {
+        static TOKEN: AtomicU16 = AtomicU16::new(0);
+
+        FontKey {
+            token: TOKEN.fetch_add(1, SeqCst),
+        }
+    }
+}
+
+//impl Hash for FontKey {
+//    fn hash<H: Hasher>(&self, state: &mut H) {
+//        self.token.hash(state);
+//    }
+//}
+
+impl Hash for GlyphKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ch.hash(state);
+        self.font.hash(state);
+        self.style.hash(state);
+    }
+}
+
+impl Face {
+    fn new(library: &ft::Library, path: &PathBuf, font: FontKey, rasterizer: &FreeTypeRasterizer) -> Result<Self> {
+        let data = Rc::new(std::fs::read(path).map_err(|e| Error::FileError(path.clone(), e.kind()))?);
+
+        Ok(Self {
+            font,
+            face: library
+                .new_memory_face(data.clone(), 0)
+                .map_err(Error::FTError)?,
+            data,
+            antialias: rasterizer.antialias,
+            hinting: rasterizer.hinting,
+            dpi_factor: rasterizer.dpi_factor,
+        })
+    }
+
+    /// the flags glyphs are loaded with, full hinting targets the way the glyph is rendered.
+    fn load_flags(&self) -> ft::face::LoadFlag {
+        use ft::face::LoadFlag;
+
+        match (self.hinting, self.antialias) {
+            (Hinting::None, _) => LoadFlag::NO_HINTING,
+            (Hinting::Slight, _) => LoadFlag::TARGET_LIGHT,
+            (Hinting::Full, Antialias::Grayscale) => LoadFlag::TARGET_NORMAL,
+            (Hinting::Full, Antialias::LcdRgb) | (Hinting::Full, Antialias::LcdBgr) => LoadFlag::TARGET_LCD,
+            (Hinting::Full, Antialias::Mono) => LoadFlag::TARGET_MONO,
+        }
+    }
+
+    fn render_mode(&self) -> ft::RenderMode {
+        match self.antialias {
+            Antialias::Grayscale => ft::RenderMode::Normal,
+            Antialias::LcdRgb | Antialias::LcdBgr => ft::RenderMode::Lcd,
+            Antialias::Mono => ft::RenderMode::Mono,
+        }
+    }
+
+    /// sets the size of the face, glyphs are rendered in physical pixels.
+    fn set_size(&self, size: FontSize) -> Result<()> {
+        let dpi = (BASE_DPI * self.dpi_factor).round() as u32;
+        self.face
+            .set_char_size(convert_to_ft(&size), 0, dpi, dpi)
+            .map_err(Error::FTError)
+    }
+
+    fn char_index(&self, glyph: &GlyphKey) -> Result<u32> {
+        let index = self.face.get_char_index(glyph.ch as usize);
+        if index == 0 {
+            Err(Error::InvalidGlyph)
+        } else {
+            Ok(index)
+        }
+    }
+
+    fn render_glyph(&self, glyph: GlyphKey, size: FontSize, synthetic: Style) -> Result<ft::GlyphSlot> {
+        // gets the index of the character
+        let index = self.char_index(&glyph)?;
+
+        self.render_styled(index, size, synthetic)
+    }
+
+    /// the metrics of a glyph without rendering it.
+    fn glyph_metrics(&self, index: u32, size: FontSize) -> Result<ft::GlyphMetrics> {
+        self.set_size(size)?;
+
+        self.face
+            .load_glyph(index, self.load_flags())
+            .map_err(Error::FTError)?;
+
+        Ok(self.face.glyph().metrics())
+    }
+
+    /// the horizontal advance of a glyph without rendering it.
+    fn advance(&self, index: u32, size: FontSize) -> Result<f32> {
+        Ok((self.glyph_metrics(index, size)?.horiAdvance >> 6) as f32)
+    }
+
+    fn render_index(&self, index: u32, size: FontSize) -> Result<ft::GlyphSlot> {
+        self.render_styled(index, size, Style::Normal)
+    }
+
+    /// shapes the text with HarfBuzz's rules, the direction and script are guessed from the text.
+    fn shape(&self, text: &str, size: FontSize, synthetic: Style) -> Result<Vec<ShapedGlyph>> {
+        let face = rustybuzz::Face::from_slice(&self.data, 0).ok_or(Error::InvalidFont)?;
+
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+
+        let shaped = rustybuzz::shape(&face, &[], buffer);
+
+        // positions are in font units, the face is sized the same way as when rendering.
+        let scale = size.pixel_size * self.dpi_factor as f32 / face.units_per_em() as f32;
+
+        Ok(shaped
+            .glyph_infos()
+            .iter()
+            .zip(shaped.glyph_positions())
+            .map(|(info, pos)| ShapedGlyph {
+                key: GlyphIndexKey {
+                    font: self.font,
+                    index: info.glyph_id,
+                    style: synthetic,
+                },
+                cluster: info.cluster as usize,
+                x_advance: (pos.x_advance as f32 * scale).round(),
+                x_offset: (pos.x_offset as f32 * scale).round(),
+                y_offset: (pos.y_offset as f32 * scale).round(),
+            })
+            .collect())
+    }
+
+    /// renders the glyph, emboldening or slanting its outline for the synthetic style.
+    fn render_styled(&self, index: u32, size: FontSize, synthetic: Style) -> Result<ft::GlyphSlot> {
+        self.set_size(size)?;
+
+        // the transform applies to every glyph loaded after it so it is always set.
+        let mut matrix = ft::Matrix {
+            xx: 0x10000,
+            xy: if synthetic.is_italic() { SLANT } else { 0 },
+            yx: 0,
+            yy: 0x10000,
+        };
+        let mut delta = ft::Vector { x: 0, y: 0 };
+        self.face.set_transform(&mut matrix, &mut delta);
+
+        // loads the glyph into the slot
+        self.face
+            .load_glyph(index, self.load_flags())
+            .map_err(Error::FTError)?;
+
+        // gets the glyph
+        let glyph = self.face.glyph();
+
+        if synthetic.is_bold() {
+            // thicken the outline by a little more than a pixel at common sizes.
+            let strength = (convert_to_ft(&size) as f64 * self.dpi_factor / 24.0) as ft::ffi::FT_Pos;
+            unsafe {
+                let outline = &glyph.raw().outline as *const _ as *mut ft::ffi::FT_Outline;
+                ft::ffi::FT_Outline_Embolden(outline, strength);
+            }
+        }
+
+        // renders the glyph into a bitmap
+        glyph.render_glyph(self.render_mode()).map_err(Error::FTError)?;
+
+        // returns a copy
+        Ok(*glyph)
+    }
+}
+
+impl FreeTypeRasterizer {
+    /// expands the bitmap into rgb coverage, the subpixels of a bgr display are swapped into rgb order.
+    fn normalize_buffer(bitmap: &ft::Bitmap, antialias: Antialias) -> (f32, f32, Vec<u8>) {
+        let mut data = Vec::new();
+        let buf = bitmap.buffer();
+        match bitmap.pixel_mode().unwrap() {
+            ft::bitmap::PixelMode::Mono => {
+                data.reserve(buf.len() * 8 * 3);
+                fn expand_pixel(buf: &mut Vec<u8>, byte: u8, mut bit_count: u8) {
+                    let mut offset = 7;
+                    while bit_count != 0 {
+                        let value: u8 = ((byte >> offset) & 1) * 255;
+
+                        buf.push(value);
+                        buf.push(value);
+                        buf.push(value);
+
+                        offset -= 1;
+                        bit_count -= 1;
+                    }
+                }
+
+                for i in 0..(bitmap.rows() as usize) {
+                    let start = i * bitmap.pitch().unsigned_abs() as usize;
+                    let mut column = bitmap.width();
+                    let mut byte = 0;
+
+                    while column != 0 {
+                        let bit_count = std::cmp::min(8, column);
+                        expand_pixel(&mut data, buf[start + byte], bit_count as u8);
+
+                        column -= bit_count;
+                        byte += 1;
+                    }
+                }
+                (bitmap.width() as f32, bitmap.rows() as f32, data)
+            }
+            ft::bitmap::PixelMode::Gray => {
+                data.reserve(buf.len() * 3);
+                for i in 0..bitmap.rows() {
+                    let start = (i * bitmap.pitch()) as usize;
+                    let stop = start + bitmap.width() as usize;
+                    for byte in &buf[start..stop] {
+                        data.push(*byte);
+                        data.push(*byte);
+                        data.push(*byte);
+                    }
+                }
+                (bitmap.width() as f32, bitmap.rows() as f32, data)
+            }
+            ft::bitmap::PixelMode::Lcd => {
+                data.reserve(buf.len());
+                for i in 0..bitmap.rows() {
+                    let start = (i as usize) * bitmap.pitch() as usize;
+                    let stop = start + bitmap.width() as usize;
+
+                    for pixel in buf[start..stop].chunks(3) {
+                        match antialias {
+                            Antialias::LcdBgr => data.extend(pixel.iter().rev()),
+                            _ => data.extend_from_slice(pixel),
+                        }
+                    }
+                }
+                // the bitmap has three columns per pixel.
+                (bitmap.width() as f32 / 3.0, bitmap.rows() as f32, data)
+            }
+            ft::bitmap::PixelMode::LcdV => {
+                panic!("Not implemented");
+            }
+            _ => {
+                panic!("Not implemented");
+            }
+        }
+    }
+
+    fn find_font(&self, font: FontKey) -> Result<&Face> {
+        self.faces.get(&font).ok_or(Error::MissingFont)
+    }
+
+    /// the face used for a style of a font and the part of the style that must be synthesized.
+    fn styled_face(&self, font: FontKey, style: Style) -> Result<(&Face, Style)> {
+        // prefer the exact face, then a face that has part of the style.
+        let candidates = [style, Style::new(style.is_bold(), false), Style::new(false, style.is_italic())];
+
+        for candidate in candidates.iter().filter(|s| **s != Style::Normal) {
+            if let Some(key) = self.styles.get(&(font, *candidate)) {
+                return Ok((self.find_font(*key)?, style.without(*candidate)));
+            }
+        }
+
+        Ok((self.find_font(font)?, style))
+    }
+
+    fn get_rendered_glyph(&mut self, glyph: GlyphKey, size: FontSize) -> Result<RasterizedGlyph> {
+        let font = glyph.font;
+
+        let (face, synthetic) = self.styled_face(font, glyph.style)?;
+
+        match face.render_glyph(glyph.clone(), size, synthetic) {
+            Ok(bitmap) => Ok(Self::rasterized(glyph.ch, &bitmap, self.antialias)),
+            // the font does not have the character, try the fallbacks.
+            Err(Error::InvalidGlyph) => self.get_fallback_glyph(glyph, size),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// renders the character with the first fallback font that has it.
+    /// The glyph is scaled down if needed so it fits in its cells of the primary font's grid.
+    fn get_fallback_glyph(&self, glyph: GlyphKey, size: FontSize) -> Result<RasterizedGlyph> {
+        let chain = match self.fallbacks.get(&glyph.font) {
+            Some(chain) => chain,
+            None => return Err(Error::InvalidGlyph),
+        };
+
+        for key in chain {
+            let face = self.find_font(*key)?;
+
+            let index = match face.char_index(&glyph) {
+                Ok(index) => index,
+                Err(_) => continue,
+            };
+
+            let cell_width = self.get_full_metrics(glyph.font, size)?.cell_width;
+            let width = cell_width * char_cells(glyph.ch) as f32;
+
+            let advance = face.advance(index, size)?;
+            let size = if advance > width {
+                FontSize {
+                    pixel_size: size.pixel_size * width / advance,
+                }
+            } else {
+                size
+            };
+
+            let slot = face.render_styled(index, size, glyph.style)?;
+            let mut rglyph = Self::rasterized(glyph.ch, &slot, self.antialias);
+
+            // center the glyph in its cells and snap its advance to the grid.
+            let left = ((width - rglyph.width) / 2.0).max(0.0).floor();
+            rglyph.left = left;
+            rglyph.bearing_x = left;
+            rglyph.advance_x = width;
+
+            return Ok(rglyph);
+        }
+
+        Err(Error::InvalidGlyph)
+    }
+
+    fn rasterized(ch: u32, slot: &ft::GlyphSlot, antialias: Antialias) -> RasterizedGlyph {
+        let metrics = slot.metrics();
+
+        let (w, h, buffer) = Self::normalize_buffer(&slot.bitmap(), antialias);
+
+        RasterizedGlyph {
+            glyph: ch,
+            width: w,
+            height: h,
+            top: slot.bitmap_top() as f32,
+            left: slot.bitmap_left() as f32,
+            bearing_x: (metrics.horiBearingX >> 6) as f32,
+            bearing_y: (metrics.horiBearingY >> 6) as f32,
+            advance_x: (metrics.horiAdvance >> 6) as f32,
+            advance_y: 0f32,
+            bitmap: buffer,
+        }
+    }
+
+    /// a hollow box the size of a cell, used when the font does not provide
+    /// anything to draw for its missing glyph.
+    fn missing_box(metrics: &Metrics) -> RasterizedGlyph {
+        let width = metrics.average_advance.max(3.0) as usize;
+        let height = (metrics.line_height + metrics.descent).max(3.0) as usize;
+
+        let mut bitmap = vec![0u8; width * height * 3];
+        for y in 0..height {
+            for x in 0..width {
+                if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                    let i = (y * width + x) * 3;
+                    bitmap[i..i + 3].copy_from_slice(&[255, 255, 255]);
+                }
+            }
+        }
+
+        RasterizedGlyph {
+            glyph: 0,
+            width: width as f32,
+            height: height as f32,
+            top: height as f32,
+            left: 0.0,
+            bearing_x: 0.0,
+            bearing_y: height as f32,
+            advance_x: metrics.average_advance,
+            advance_y: 0f32,
+            bitmap,
+        }
+    }
+
+    /// a solid bar of the given width and thickness whose top is `top` pixels above the baseline.
+    fn bar(width: f32, thickness: f32, top: f32) -> RasterizedGlyph {
+        let width = width.max(1.0) as usize;
+        let height = thickness.round().max(1.0) as usize;
+
+        RasterizedGlyph {
+            glyph: 0,
+            width: width as f32,
+            height: height as f32,
+            top,
+            left: 0.0,
+            bearing_x: 0.0,
+            bearing_y: top,
+            advance_x: width as f32,
+            advance_y: 0f32,
+            bitmap: vec![255u8; width * height * 3],
+        }
+    }
+
+    fn get_full_metrics(&self, font: FontKey, size: FontSize) -> Result<FullMetrics> {
+        let face = self.find_font(font)?;
+
+        face.set_size(size)?;
+
+        let metrics = face.face.size_metrics().ok_or(Error::NoSizeMetrics)?;
+
+        let cell_width = match face.face.load_glyph('0' as u32, ft::face::LoadFlag::RENDER) {
+            Ok(_) => (face.face.glyph().metrics().horiAdvance / 64) as f32,
+            Err(_) => (metrics.max_advance / 64) as f32,
+        };
+
+        Ok(FullMetrics {
+            ft_metrics: metrics,
+            cell_width,
+        })
+    }
+}
+
+impl Rasterizer for FreeTypeRasterizer {
+    fn new(dpi_factor: f32) -> Result<Self> {
+        let library = ft::Library::init().map_err(Error::FTError)?;
+
+        Ok(Self {
+            library,
+            faces: HashMap::new(),
+            fonts: HashMap::new(),
+            fallbacks: HashMap::new(),
+            styles: HashMap::new(),
+            discovery: Discovery::new(),
+            dpi_factor: dpi_factor as f64,
+            antialias: Antialias::default(),
+            hinting: Hinting::default(),
+        })
+    }
+
+    fn set_dpi_factor(&mut self, dpi_factor: f32) {
+        self.dpi_factor = dpi_factor as f64;
+
+        for face in self.faces.values_mut() {
+            face.dpi_factor = self.dpi_factor;
+        }
+    }
+
+    fn set_rendering(&mut self, antialias: Antialias, hinting: Hinting) {
+        self.antialias = antialias;
+        self.hinting = hinting;
+
+        // the filter reduces the color fringes of subpixel rendering.
+        let filter = match antialias {
+            Antialias::LcdRgb | Antialias::LcdBgr => ft::LcdFilter::LcdFilterDefault,
+            _ => ft::LcdFilter::LcdFilterNone,
+        };
+        if let Err(e) = self.library.set_lcd_filter(filter) {
+            println!("Unable to set the lcd filter: {:?}", e);
+        }
+
+        for face in self.faces.values_mut() {
+            face.antialias = antialias;
+            face.hinting = hinting;
+        }
+    }
+
+    fn load_glyph(&mut self, glyph: GlyphKey, size: FontSize) -> Result<RasterizedGlyph> {
+        self.get_rendered_glyph(glyph, size)
+    }
+
+    fn load_glyph_index(&mut self, glyph: GlyphIndexKey, size: FontSize) -> Result<RasterizedGlyph> {
+        if glyph.index == 0 {
+            return Err(Error::InvalidGlyph);
+        }
+
+        let face = self.find_font(glyph.font)?;
+        let slot = face.render_styled(glyph.index, size, glyph.style)?;
+
+        Ok(Self::rasterized(glyph.index, &slot, self.antialias))
+    }
+
+    fn shape(&mut self, font: FontKey, style: Style, text: &str, size: FontSize) -> Result<Vec<ShapedGlyph>> {
+        let (face, synthetic) = self.styled_face(font, style)?;
+        face.shape(text, size, synthetic)
+    }
+
+    fn load_missing_glyph(&mut self, font: FontKey, size: FontSize) -> Result<RasterizedGlyph> {
+        let face = self.find_font(font)?;
+
+        // glyph 0 is the font's own missing glyph (.notdef), though it is often left blank.
+        let glyph = Self::rasterized(0, &face.render_index(0, size)?, self.antialias);
+        if glyph.width > 0.0 && glyph.height > 0.0 {
+            return Ok(glyph);
+        }
+
+        let metrics = self.get_metrics(font, size)?;
+        Ok(Self::missing_box(&metrics))
+    }
+
+    fn load_decoration(
+        &mut self,
+        font: FontKey,
+        size: FontSize,
+        decoration: Decoration,
+    ) -> Result<RasterizedGlyph> {
+        let metrics = self.get_metrics(font, size)?;
+        let thickness = metrics.underline_thickness.max(1.0);
+
+        let center = match decoration {
+            Decoration::Underline => metrics.underline_position,
+            // strike through the middle of the lower case letters.
+            Decoration::Strikethrough => {
+                let face = self.find_font(font)?;
+                let x_height = match face.face.get_char_index('x' as usize) {
+                    0 => size.pixel_size / 2.0,
+                    index => (face.glyph_metrics(index, size)?.horiBearingY >> 6) as f32,
+                };
+                x_height / 2.0
+            }
+        };
+
+        Ok(Self::bar(metrics.average_advance, thickness, center + thickness / 2.0))
+    }
+
+    fn get_metrics(&self, font: FontKey, size: FontSize) -> Result<Metrics> {
+        let _face = self.find_font(font)?;
+
+        let full = self.get_full_metrics(font, size)?;
+
+        let height = (full.ft_metrics.height / 64) as f32;
+        let descent = (full.ft_metrics.descender / 64) as f32;
+
+        // the underline is given in font units, scale it like the other size metrics.
+        let face = self.find_font(font)?;
+        let scale = |units: i16| ((units as i64 * full.ft_metrics.y_scale) >> 16) as f32 / 64.0;
+
+        Ok(Metrics {
+            average_advance: full.cell_width,
+            line_height: height,
+            descent,
+            underline_position: scale(face.face.underline_position()),
+            underline_thickness: scale(face.face.underline_thickness()),
+        })
+    }
+
+    fn get_font(&mut self, font: FontDesc) -> Result<FontKey> {
+        let path = match self.discovery.resolve(&font) {
+            // the families like it are only looked for when the font the text is drawn with is missing.
+            Err(Error::FontNotFound { family, style, .. }) => {
+                let close_matches = self.discovery.close_matches(&family);
+                return Err(Error::FontNotFound {
+                    family,
+                    style,
+                    close_matches,
+                });
+            }
+            result => result?,
+        };
+        let key = self.load_face(&path)?;
+
+        // use the bold and italic faces of a family when it has them.
+        if font.path.is_none() {
+            for style in [Style::Bold, Style::Italics, Style::BoldItalics].iter() {
+                let styled = FontDesc {
+                    style: *style,
+                    ..font.clone()
+                };
+
+                if let Ok(path) = self.discovery.resolve(&styled) {
+                    let face = self.load_face(&path)?;
+                    self.styles.insert((key, *style), face);
+                }
+            }
+        }
+
+        let mut chain = Vec::new();
+        for fallback in &font.fallback {
+            match self.discovery.resolve(fallback).and_then(|path| self.load_face(&path)) {
+                Ok(fallback) if fallback != key => chain.push(fallback),
+                Ok(_) => {}
+                // a missing fallback only means fewer characters can be drawn.
+                Err(e) => println!("Unable to load fallback font {}: {:?}", fallback.name, e),
+            }
+        }
+        self.fallbacks.insert(key, chain);
+
+        Ok(key)
+    }
+
+    fn set_style_font(&mut self, font: FontKey, styled: FontDesc) -> Result<()> {
+        if styled.style == Style::Normal {
+            return Ok(());
+        }
+
+        let path = self.discovery.resolve(&styled)?;
+        let key = self.load_face(&path)?;
+        self.styles.insert((font, styled.style), key);
+
+        Ok(())
+    }
+}
+
+impl FreeTypeRasterizer {
+    fn load_face(&mut self, path: &PathBuf) -> Result<FontKey> {
+        match self.fonts.get(path) {
+            Some(key) => Ok(*key),
+            None => {
+                // build the face if it hasnt been seen yet.
+                let key = FontKey::next();
+                let face = Face::new(&self.library, path, key, self)?;
+                self.faces.insert(key, face);
+                self.fonts.insert(path.clone(), key);
+
+                Ok(key)
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! The editor as a library, so the benchmarks can drive the engine the binary uses.
+
+extern crate freetype as ft;
+extern crate gl;
+extern crate glutin;
+extern crate image;
+extern crate nalgebra_glm as glm;
+extern crate ropey;
+
+pub mod font;
+#[macro_use]
+pub mod render;
+
+pub mod config;
+pub mod editor;
+pub mod editor_core;
+pub mod size;
+pub mod window;
+pub mod timer;
+pub mod main_window;
+pub mod mouse;
+pub mod blink;
+pub mod file_tree;
+pub mod finder;
+pub mod view;
+pub mod color;
+pub mod command;
+pub mod pane;
+pub mod session;
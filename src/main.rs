@@ -1,129 +1,61 @@
-extern crate freetype as ft;
-extern crate gl;
-extern crate glutin;
-extern crate image;
-extern crate nalgebra_glm as glm;
-extern crate ropey;
-
-mod font;
-#[macro_use]
-mod render;
-
-mod config;
-mod editor;
-mod editor_core;
-mod size;
-mod window;
-mod timer;
-mod main_window;
-mod view;
-mod color;
-
-// mod pane;
-
-use editor::App;
-use font::Rasterizer;
-use gl::types::*;
+use rem::config;
+use rem::editor::{self, App};
+use rem::session::{self, Session};
 use std::path::PathBuf;
-use std::ptr;
 use std::result::Result;
-use std::str;
-
-static INDEX_DATA: [u32; 6] = [0, 1, 2, 0, 2, 3];
-static RECT_INDEX_DATA: [u32; 4] = [0, 1, 2, 3];
-
-macro_rules! check {
-    () => {{
-        if cfg!(debug_assertions) {
-            let err = unsafe { gl::GetError() };
-            // println!("Error {:?}", err);
-            if err != gl::NO_ERROR {
-                let err_str = match err {
-                    gl::INVALID_ENUM => "GL_INVALID_ENUM",
-                    gl::INVALID_VALUE => "GL_INVALID_VALUE",
-                    gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
-                    gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
-                    gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
-                    gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
-                    gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
-                    _ => "unknown error",
-                };
-                println!("{}:{} error {}", file!(), line!(), err_str);
-                panic!();
-            }
-        }
-    }};
-}
-
-extern "system" fn callback(
-    source: GLenum,
-    gltype: GLenum,
-    id: GLuint,
-    severity: GLenum,
-    length: GLsizei,
-    message: *const GLchar,
-    userParam: *mut std::ffi::c_void,
-) {
-    let sor = match source {
-        gl::DEBUG_SOURCE_API => "API",
-        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "WINDOW SYSTEM",
-        gl::DEBUG_SOURCE_SHADER_COMPILER => "SHADER COMPILER",
-        gl::DEBUG_SOURCE_THIRD_PARTY => "THIRD PARTY",
-        gl::DEBUG_SOURCE_APPLICATION => "APPLICATION",
-        gl::DEBUG_SOURCE_OTHER => "UNKNOWN",
-        _ => "UNKNOWN",
-    };
 
-    let ty = match gltype {
-        gl::DEBUG_TYPE_ERROR => "ERROR",
-        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "DEPRECATED BEHAVIOR",
-        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "UDEFINED BEHAVIOR",
-        gl::DEBUG_TYPE_PORTABILITY => "PORTABILITY",
-        gl::DEBUG_TYPE_PERFORMANCE => "PERFORMANCE",
-        gl::DEBUG_TYPE_OTHER => "OTHER",
-        gl::DEBUG_TYPE_MARKER => "MARKER",
-        _ => "UNKNOWN",
-    };
-
-    let ser = match severity {
-        gl::DEBUG_SEVERITY_HIGH => "HIGH",
-        gl::DEBUG_SEVERITY_MEDIUM => "MEDIUM",
-        gl::DEBUG_SEVERITY_LOW => "LOW",
-        gl::DEBUG_SEVERITY_NOTIFICATION => return,
-        _ => "UNKNOWN",
-    };
+/// the session file given with `-S [file]`, Some(None) when -S is given without a file.
+fn session_arg() -> Option<Option<PathBuf>> {
+    let mut args = std::env::args().skip(1).peekable();
+
+    while let Some(arg) = args.next() {
+        if arg == "-S" {
+            let file = match args.peek() {
+                Some(next) if !next.starts_with('-') => args.next().map(PathBuf::from),
+                _ => None,
+            };
+            return Some(file);
+        }
+    }
 
-    let message = unsafe { std::slice::from_raw_parts(message as *const u8, length as usize) };
-    println!(
-        "{}: {} of {} severity, raised from {}: {}",
-        id,
-        ty,
-        ser,
-        sor,
-        str::from_utf8(message).unwrap()
-    );
+    None
 }
 
 fn main() -> Result<(), editor::Error> {
     let config = config::Config::default();
 
+    // a session is kept for each project directory unless a file is given.
+    let project_session = std::env::current_dir()
+        .ok()
+        .and_then(|dir| session::project_session_path(&dir));
+    let (session_path, restore, save) = match session_arg() {
+        Some(Some(file)) => (Some(file), true, true),
+        Some(None) => (project_session, true, true),
+        None => (project_session, config.session.restore, config.session.save),
+    };
+
     let mut app = App::new(config)?;
-    check!();
 
-    unsafe {
-        gl::DebugMessageCallback(callback, ptr::null());
+    if let Some(path) = session_path.as_ref().filter(|path| restore && path.exists()) {
+        match Session::load(path) {
+            Ok(session) => app.restore_session(&session)?,
+            Err(e) => println!("Unable to read the session {}: {:?}", path.display(), e),
+        }
     }
 
-    let mut running = true;
-    let mut _iter = 0;
-
-    while app.process_input() {
+    // the panes are drawn on the render thread, this only sends it what changed.
+    loop {
         app.render_panes()?;
 
-        app.render_window();
+        if !app.process_input() {
+            break;
+        }
+    }
 
-        app.swap_buffers();
-        //iter += 1;
+    if let Some(path) = session_path.as_ref().filter(|_| save) {
+        if let Err(e) = app.save_session(path) {
+            println!("Unable to save the session {}: {:?}", path.display(), e);
+        }
     }
 
     // app.clean();
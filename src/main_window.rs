@@ -1,6 +1,11 @@
-use crate::view;
+use std::collections::HashMap;
 
-use view::{CellSize, ViewSize, LineCache, Cells, View};
+use crate::editor::Result;
+use crate::pane::{self, Pane, PaneID, PaneKind};
+use crate::render::PaneState;
+use crate::pane::{CellSize, Cells, Layout};
+use crate::size::Size;
+use crate::window::Window;
 
 
 #[derive(Debug, Clone)]
@@ -10,10 +15,13 @@ pub struct TabBar;
 /// I.E where the directory tree will be rendered, tab bar, message bar,
 ///     and handling of pane splits.
 #[derive(Debug)]
-struct MainWindow {
-    /// all of the file views of this editor.
-    views: Vec<View>,
+pub struct MainWindow {
+    /// the root of the split tree, every edit pane and the file tree are in it.
+    pane: Pane,
+    /// the cursor, scroll position and damage of each edit pane.
+    pane_states: HashMap<PaneID, PaneState>,
     /// this is only shown when there are
+    #[allow(dead_code)] // the tab bar is not drawn yet.
     tab_bar: Option<TabBar>,
     /// a reference to all of the edit panes.
     // panes: Vec<pane::Pane>,
@@ -23,10 +31,12 @@ struct MainWindow {
     cell_size: CellSize,
     /// The window dimensions in cells.
     cells: Size<u32>,
+    /// the shape of the cursors for the mode of the editor.
+    cursor_mode: pane::CursorMode,
 }
 
 impl MainWindow {
-    fn new(window: Window, cell_size: CellSize) -> Result<Self> {
+    pub fn new(window: Window, cell_size: CellSize) -> Result<Self> {
         // this the physical size of the window.
         let (width, height): (f64, f64) = window.get_physical_size().into();
         println!("Main Window Size: ({}, {})", width, height);
@@ -34,6 +44,7 @@ impl MainWindow {
         let cells = Cells::compute_cells(width as f32, height as f32, cell_size);
 
         let loc = pane::Loc::new(0f32, 0f32);
+        let pane = Pane::new(PaneKind::Edit, pane::Size::new(width as f32, height as f32), cells, loc);
 
         let mut main_window = Self {
             pane,
@@ -42,12 +53,13 @@ impl MainWindow {
             window,
             cell_size,
             cells,
+            cursor_mode: pane::CursorMode::Box,
         };
 
         // @TODO: Abstract this out to a method
         
         let id = main_window.pane.id();
-        let size = main_window.pane.size().clone();
+        let size = *main_window.pane.size();
 
         main_window.create_pane_state(size, id)?;
 
@@ -55,18 +67,145 @@ impl MainWindow {
 
         Ok(main_window)
     }
+
+    /// makes the given pane the only active pane.
+    pub fn focus_pane(&mut self, id: PaneID) {
+        if let Some(active) = self.find_active_pane_id() {
+            if active == id {
+                return;
+            }
+
+            self.set_pane_deactive(active);
+            if let Some(state) = self.get_pane_state_mut(active) {
+                state.damage.add_all();
+            }
+        }
+
+        self.set_pane_active(id);
+        if let Some(state) = self.get_pane_state_mut(id) {
+            state.damage.add_all();
+        }
+    }
+
+    pub fn get_pane(&self, id: PaneID) -> Option<&Pane> {
+        Self::find_pane_by_id(self.pane(), id)
+    }
+
+    /// the edit pane under the given point in physical pixels.
+    pub fn pane_at(&self, x: f32, y: f32) -> Option<PaneID> {
+        self.pane.pane_at(x, y)
+    }
+
+    /// the split border under the given point in physical pixels.
+    pub fn border_at(&self, x: f32, y: f32, tolerance: f32) -> Option<pane::Border> {
+        self.pane.border_at(x, y, tolerance)
+    }
+
+    /// moves a split border to the given point and reallocates the affected panes.
+    pub fn drag_border(&mut self, border: &pane::Border, x: f32, y: f32) -> Result<()> {
+        if self.pane.drag_border(border, x, y) {
+            self.update_pane_states()?;
+        }
+        Ok(())
+    }
+
+    /// changes the size of a cell, the panes keep their size but fit a different number of cells.
+    pub fn set_cell_size(&mut self, cell_size: CellSize) {
+        let (width, height): (f64, f64) = self.window.get_physical_size().into();
+
+        self.cell_size = cell_size;
+        self.cells = Cells::compute_cells(width as f32, height as f32, cell_size);
+        self.pane.set_cell_size(cell_size);
+
+        for state in self.pane_states.values_mut() {
+            state.damage.add_all();
+        }
+    }
+
+    /// lays out the panes to fill the window, after its physical size changed.
+    pub fn fit_to_window(&mut self) -> Result<()> {
+        let (width, height): (f64, f64) = self.window.get_physical_size().into();
+        let size = pane::Size::new(width as f32, height as f32);
+
+        self.cells = Cells::compute_cells(size.x, size.y, self.cell_size);
+        self.pane.on_resize(size, self.cells, self.cell_size);
+
+        self.update_pane_states()
+    }
+
+    /// brings every pane state in line with the size of its pane.
+    pub fn update_pane_states(&mut self) -> Result<()> {
+        for (id, size, _) in self.pane.edit_panes() {
+            if let Some(state) = self.pane_states.get_mut(&id) {
+                state.resize(size);
+            }
+        }
+        Ok(())
+    }
+
+    /// shows the file tree left of the panes, taking `weight` of the width, returning its pane.
+    pub fn show_tree(&mut self, weight: f32) -> Result<PaneID> {
+        let id = match self.pane.tree_pane() {
+            Some(tree) => tree.id(),
+            None => self.pane.insert_tree(weight),
+        };
+
+        self.fit_to_window()?;
+        Ok(id)
+    }
+
+    /// gives the width of the file tree back to the panes.
+    pub fn hide_tree(&mut self) -> Result<()> {
+        if let Some(id) = self.pane.tree_pane().map(|tree| tree.id()) {
+            self.pane.remove_pane(id);
+            self.fit_to_window()?;
+        }
+        Ok(())
+    }
+
+    pub fn tree_pane(&self) -> Option<&Pane> {
+        self.pane.tree_pane()
+    }
+
+    /// replaces the split tree with another one, like a restored session, and lays it out to
+    /// fill the window. The first edit pane is made active.
+    pub fn set_pane(&mut self, pane: Pane) -> Result<()> {
+        self.pane = pane;
+        self.pane_states.clear();
+
+        self.fit_to_window()?;
+
+        let panes = self.pane.edit_panes();
+        for (id, size, _) in panes.iter() {
+            self.create_pane_state(*size, *id)?;
+        }
+
+        if let Some((id, _, _)) = panes.first() {
+            self.set_pane_active(*id);
+        }
+
+        Ok(())
+    }
+
+    /// changes the shape of every cursor, after the editor changed modes.
+    pub fn set_cursor_mode(&mut self, mode: pane::CursorMode) {
+        if self.cursor_mode == mode {
+            return;
+        }
+
+        self.cursor_mode = mode;
+        for state in self.pane_states.values_mut() {
+            state.cursor.set_mode(mode);
+            state.damage.add_row(state.cursor.pos().y);
+        }
+    }
     
-    /*
     pub fn pane(&self) -> &Pane {
         &self.pane
     }
 
     pub fn create_pane_state(&mut self, sz: Size<f32>, id: PaneID) -> Result<()> {
-        self.pane_states.insert(
-            id,
-            PaneState::new(sz, id)
-                .map_err(|e| Error::RenderError(render::Error::FrameBufferError(e)))?,
-        );
+        self.pane_states.insert(id, PaneState::new(sz, id, self.cursor_mode));
         Ok(())
     }
 
@@ -88,7 +227,7 @@ impl MainWindow {
                 pane
             }
             else {
-                panic!(format!("Unable to find active pane. Invalid ID: {:?}", id));
+                panic!("Unable to find active pane. Invalid ID: {:?}", id);
             }
         }
         else {
@@ -102,7 +241,7 @@ impl MainWindow {
                 pane
             }
             else {
-                panic!(format!("Unable to find active pane. Invalid ID: {:?}", id));
+                panic!("Unable to find active pane. Invalid ID: {:?}", id);
             }
         }
         else {
@@ -110,9 +249,9 @@ impl MainWindow {
         }
     }
 
-    fn find_pane_by_id(pane: &pane::Pane, id: PaneID) -> Option<&pane::Pane> {
+    pub fn find_pane_by_id(pane: &pane::Pane, id: PaneID) -> Option<&pane::Pane> {
         match *pane.kind() {
-            PaneKind::Edit => {
+            PaneKind::Edit | PaneKind::Tree => {
                 if pane.id() == id {
                     return Some(pane);
                 }
@@ -142,48 +281,31 @@ impl MainWindow {
     }
 
     fn find_pane_by_id_mut(pane: &mut pane::Pane, id: PaneID) -> Option<&mut pane::Pane> {
-        // *pane.kind();
-        let pane_id = pane.id();
-        match  pane.kind_mut() {
-            PaneKind::Edit => {
-                if pane_id == id {
-                    return Some(pane);
-                }
-                else {
-                    return None;
-                    // panic!(format!("Unable to find pane of given id: {:?}", id));
-                }
-            },
-            PaneKind::Vert(ref mut layout) => {
-                for pane in layout.iter_mut() {
-                    let p = Self::find_pane_by_id_mut(pane, id);
-                    if p.is_some() {
-                        return p;
-                    }
-                }
-            },
-            PaneKind::Hor(ref mut layout) => {
-                for pane in layout.iter_mut() {
-                    let p = Self::find_pane_by_id_mut(pane, id);
-                    if p.is_some() {
-                        return p;
-                    }
-                }
-            },
+        // the id is checked before the children are borrowed.
+        if pane.id() == id {
+            return match *pane.kind() {
+                PaneKind::Edit | PaneKind::Tree => Some(pane),
+                _ => None,
+            };
+        }
+
+        match pane.kind_mut() {
+            PaneKind::Edit | PaneKind::Tree => None,
+            PaneKind::Vert(ref mut layout) => layout.iter_mut().find_map(|pane| Self::find_pane_by_id_mut(pane, id)),
+            PaneKind::Hor(ref mut layout) => layout.iter_mut().find_map(|pane| Self::find_pane_by_id_mut(pane, id)),
         }
-        None
     }
     
     pub fn find_active_pane_id(&self) -> Option<PaneID> {
         // attempting to be idiomatic
         let temp : Vec<(&PaneID, &PaneState)> = self.pane_states
                         .iter()
-                        .filter(|(k, v)| v.active)
+                        .filter(|(_, v)| v.active)
                         .collect();
         
-        assert!(temp.len() == 1, format!("Unexpected number of active panes {}", temp.len()));
+        assert!(temp.len() == 1, "Unexpected number of active panes {}", temp.len());
 
-        Some(temp[0].0.clone())
+        Some(*temp[0].0)
     }
 
     pub fn get_pane_state(&self, id: PaneID) -> Option<&PaneState> {
@@ -205,5 +327,4 @@ impl MainWindow {
             state.active = false;
         }
     }
-    */ 
 }
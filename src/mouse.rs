@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+use crate::pane::{Border, PaneID};
+use crate::size::Size;
+
+/// Clicks closer together than this are counted as a double or triple click.
+const MULTI_CLICK_TIME: Duration = Duration::from_millis(400);
+
+/// How close (in pixels) the pointer has to be to a split border to grab it.
+pub const BORDER_TOLERANCE: f32 = 3.0;
+
+/// What the left mouse button is currently dragging.
+#[derive(Debug, Clone, Copy)]
+pub enum Drag {
+    /// extending the selection of a pane.
+    Select(PaneID),
+    /// resizing the split at the given border.
+    Border(Border),
+}
+
+/// State of the mouse between events.
+#[derive(Debug)]
+pub struct MouseState {
+    /// the last known position of the pointer in physical pixels.
+    pub pos: Size<f32>,
+    /// what the left button is dragging, if it is held.
+    pub drag: Option<Drag>,
+    /// time and cell of the last left click.
+    last_click: Option<(Instant, Size<u32>)>,
+    /// 1 for a single click, 2 for a double click, 3 for a triple click.
+    click_count: u32,
+}
+
+impl MouseState {
+    pub fn new() -> Self {
+        Self {
+            pos: Size::new(0.0, 0.0),
+            drag: None,
+            last_click: None,
+            click_count: 0,
+        }
+    }
+
+    /// registers a left click on the given cell and returns the click count.
+    /// Clicking a fourth time starts over from a single click.
+    pub fn click(&mut self, cell: Size<u32>) -> u32 {
+        let now = Instant::now();
+
+        self.click_count = match self.last_click {
+            Some((time, last))
+                if now.duration_since(time) <= MULTI_CLICK_TIME
+                    && last.x == cell.x
+                    && last.y == cell.y =>
+            {
+                self.click_count % 3 + 1
+            }
+            _ => 1,
+        };
+
+        self.last_click = Some((now, cell));
+        self.click_count
+    }
+
+    pub fn release(&mut self) {
+        self.drag = None;
+    }
+}
+
+impl Default for MouseState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
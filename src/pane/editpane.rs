@@ -1,6 +1,6 @@
-use super::{CellSize, Loc, PaneID, Position, Size};
+use super::{PaneID, Position};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CursorMode {
     /// Full boxy cursor
     Box,
@@ -42,9 +42,63 @@ impl Cursor {
         &self.pos
     }
 
+    pub fn mode(&self) -> CursorMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: CursorMode) {
+        self.mode = mode;
+    }
+
     pub fn advance(&mut self, dist: u32) {
         self.pos.x += dist;
     }
+
+    pub fn set_pos(&mut self, pos: Position) {
+        self.pos = pos;
+    }
+}
+
+/// How a selection grows when it is extended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// character by character (single click and drag)
+    Char,
+    /// whole words (double click)
+    Word,
+    /// whole lines (triple click)
+    Line,
+}
+
+/// A range of selected text within a pane.
+/// Positions are in document coordinates: x is a char column and y is a line.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    /// where the selection was started, this does not move while extending.
+    pub anchor: Position,
+    /// the end of the selection that follows the cursor.
+    pub head: Position,
+    /// granularity of the selection
+    pub mode: SelectionMode,
+}
+
+impl Selection {
+    pub fn new(anchor: Position, head: Position, mode: SelectionMode) -> Self {
+        Self { anchor, head, mode }
+    }
+
+    /// the selection as (start, end) where start comes first in the document.
+    pub fn ordered(&self) -> (Position, Position) {
+        if (self.anchor.y, self.anchor.x) <= (self.head.y, self.head.x) {
+            (self.anchor, self.head)
+        } else {
+            (self.head, self.anchor)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor.x == self.head.x && self.anchor.y == self.head.y
+    }
 }
 
 /*
@@ -1,12 +1,14 @@
 use core::slice::{IterMut, Iter};
-use super::size::Size;
-use super::{Pane, PaneID};
+use super::Pane;
 
 pub enum Orientation {
     Vert,
     Hori,
 }
 
+/// the smallest share of a layout a single child can be dragged down to.
+pub const MIN_WEIGHT: f32 = 0.05;
+
 pub trait Layout {
     /// Creates a new layout
     fn new() -> Self;
@@ -22,9 +24,27 @@ pub trait Layout {
 	/// the number of direct children of this pane.
 	fn num_children(&self) -> usize;
 	
-	fn iter(&self) -> Iter<Pane>;
+	fn iter(&self) -> Iter<'_, Pane>;
 	
 	fn iter_mut(&mut self) -> IterMut<'_, Pane>;
+
+	/// the share of the layout each child takes up. These always sum to 1.
+	fn weights(&self) -> &[f32];
+
+	/// moves the border between child `index` and `index + 1` by `delta`
+	/// (a fraction of the layout). Neither child is shrunk below MIN_WEIGHT.
+	fn shift_border(&mut self, index: usize, delta: f32);
+
+	/// inserts a child before child `index` with `weight` of the layout, the others are
+	/// scaled down to make room for it.
+	fn insert_child(&mut self, index: usize, pane: Pane, weight: f32);
+
+	/// takes out child `index`, the others are scaled up to fill its share.
+	fn remove_child(&mut self, index: usize) -> Pane;
+
+	/// gives the children the shares of the layout, scaled to sum to 1. Weights that do not
+	/// match the children share the layout evenly instead.
+	fn set_weights(&mut self, weights: &[f32]);
 }
 
 #[derive(Debug, Clone)]
@@ -32,6 +52,7 @@ pub struct VerticalLayout {
     // The pane this layout belongs to.
 //    id: PaneID,
     elements: Vec<Pane>,
+    weights: Vec<f32>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,6 +60,48 @@ pub struct HorizontalLayout {
     // The pane this layout belongs to.
 //    id: PaneID,
     elements: Vec<Pane>,
+    weights: Vec<f32>,
+}
+
+/// gives every child an equal share of the layout.
+fn even_weights(weights: &mut Vec<f32>, num: usize) {
+    weights.clear();
+    weights.resize(num, 1.0 / num as f32);
+}
+
+fn insert_weight(weights: &mut Vec<f32>, index: usize, weight: f32) {
+    let weight = weight.clamp(MIN_WEIGHT, 1.0 - MIN_WEIGHT);
+    weights.iter_mut().for_each(|w| *w *= 1.0 - weight);
+    weights.insert(index, weight);
+}
+
+fn remove_weight(weights: &mut Vec<f32>, index: usize) {
+    let removed = weights.remove(index);
+    if removed < 1.0 {
+        weights.iter_mut().for_each(|w| *w /= 1.0 - removed);
+    }
+}
+
+fn scale_weights(weights: &mut Vec<f32>, shares: &[f32], num: usize) {
+    if shares.len() != num || shares.iter().any(|w| w.is_nan() || *w <= 0.0) {
+        even_weights(weights, num);
+    } else {
+        let total: f32 = shares.iter().sum();
+        weights.clear();
+        weights.extend(shares.iter().map(|w| w / total));
+    }
+}
+
+fn shift_weights(weights: &mut [f32], index: usize, delta: f32) {
+    if index + 1 >= weights.len() {
+        return;
+    }
+
+    let total = weights[index] + weights[index + 1];
+    let left = (weights[index] + delta).max(MIN_WEIGHT).min(total - MIN_WEIGHT);
+
+    weights[index] = left;
+    weights[index + 1] = total - left;
 }
 
 impl Layout for VerticalLayout {
@@ -46,6 +109,7 @@ impl Layout for VerticalLayout {
     fn new() -> Self {
         Self {
             elements: Vec::new(),
+            weights: Vec::new(),
         }
     }
 
@@ -61,6 +125,7 @@ impl Layout for VerticalLayout {
 
 	fn add_child(&mut self, pane: Pane) {
 		self.elements.push(pane);
+		even_weights(&mut self.weights, self.elements.len());
 	}
 	
 	fn num_children(&self) -> usize {
@@ -74,6 +139,28 @@ impl Layout for VerticalLayout {
 	fn iter(&self) -> Iter<'_, Pane> {
 		self.elements.iter()
 	}
+
+	fn weights(&self) -> &[f32] {
+		self.weights.as_slice()
+	}
+
+	fn shift_border(&mut self, index: usize, delta: f32) {
+		shift_weights(&mut self.weights, index, delta);
+	}
+
+	fn insert_child(&mut self, index: usize, pane: Pane, weight: f32) {
+		self.elements.insert(index, pane);
+		insert_weight(&mut self.weights, index, weight);
+	}
+
+	fn remove_child(&mut self, index: usize) -> Pane {
+		remove_weight(&mut self.weights, index);
+		self.elements.remove(index)
+	}
+
+	fn set_weights(&mut self, weights: &[f32]) {
+		scale_weights(&mut self.weights, weights, self.elements.len());
+	}
 }
 
 impl Layout for HorizontalLayout {
@@ -81,6 +168,7 @@ impl Layout for HorizontalLayout {
     fn new() -> Self {
         Self {
             elements: Vec::new(),
+            weights: Vec::new(),
         }
     }
 
@@ -96,6 +184,7 @@ impl Layout for HorizontalLayout {
 
 	fn add_child(&mut self, pane: Pane) {
 		self.elements.push(pane);
+		even_weights(&mut self.weights, self.elements.len());
 	}
 	
 	fn num_children(&self) -> usize {
@@ -109,4 +198,26 @@ impl Layout for HorizontalLayout {
 	fn iter(&self) -> Iter<'_, Pane> {
 		self.elements.iter()
 	}
+
+	fn weights(&self) -> &[f32] {
+		self.weights.as_slice()
+	}
+
+	fn shift_border(&mut self, index: usize, delta: f32) {
+		shift_weights(&mut self.weights, index, delta);
+	}
+
+	fn insert_child(&mut self, index: usize, pane: Pane, weight: f32) {
+		self.elements.insert(index, pane);
+		insert_weight(&mut self.weights, index, weight);
+	}
+
+	fn remove_child(&mut self, index: usize) -> Pane {
+		remove_weight(&mut self.weights, index);
+		self.elements.remove(index)
+	}
+
+	fn set_weights(&mut self, weights: &[f32]) {
+		scale_weights(&mut self.weights, weights, self.elements.len());
+	}
 }
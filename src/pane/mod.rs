@@ -1,8 +1,8 @@
 pub mod editpane;
 pub mod layout;
 
-pub use editpane::{Cursor, CursorMode};
-pub use layout::{HorizontalLayout, VerticalLayout, Layout, Orientation};
+pub use editpane::{Cursor, CursorMode, Selection, SelectionMode};
+pub use layout::{HorizontalLayout, VerticalLayout, Layout};
 
 use crate::size;
 use std::sync::atomic::{AtomicU32, Ordering::SeqCst};
@@ -28,6 +28,24 @@ pub type Loc = glm::Vec2;
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct PaneID(u32);
 
+/// The border between two neighbouring children of a layout pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Border {
+    /// the layout pane that owns the border.
+    pub layout: PaneID,
+    /// the border sits after this child.
+    pub index: usize,
+}
+
+/// The line drawn between two neighbouring children of a layout pane, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Separator {
+    /// where the line starts, the top of a vertical line or the left of a horizontal one.
+    pub loc: Loc,
+    pub length: f32,
+    pub vertical: bool,
+}
+
 impl Cells {
     pub fn compute_cells(width: f32, height: f32, cell_size: CellSize) -> Self {
         let cells_x = width / cell_size.x;
@@ -38,12 +56,10 @@ impl Cells {
 }
 
 impl PaneID {
-    fn next() -> Self {
+    pub(crate) fn next() -> Self {
         static TOKEN: AtomicU32 = AtomicU32::new(0);
 
-        Self {
-            0: TOKEN.fetch_add(1, SeqCst),
-        }
+        Self(TOKEN.fetch_add(1, SeqCst))
     }
 }
 
@@ -56,6 +72,8 @@ pub enum PaneKind {
     Hor(HorizontalLayout),
     /// of the data needed for rending a pane is stored else where.
     Edit,
+    /// the directory tree of the project, its entries are kept by the editor.
+    Tree,
 }
 
 #[derive(Debug, Clone)]
@@ -69,7 +87,7 @@ pub struct Pane {
     /// Size of the pane in number of cells.
     // The number of the parents cell are to be used to render this pane.
     cells: Cells,
-    /// The location of the bottom left of the pane.
+    /// The location of the top left of the pane.
     loc: Loc,
 }
 
@@ -129,9 +147,9 @@ impl Pane {
 		
 		let vertical_layout = layout::VerticalLayout::new();
 		
-		let mut parent_pane = Self::new(PaneKind::Vert(vertical_layout), size.clone(), cells.clone(), loc.clone());
+		let mut parent_pane = Self::new(PaneKind::Vert(vertical_layout), *size, *cells, *loc);
 		
-		let mut right_pane = Self::new(PaneKind::Edit, size.clone(), cells.clone(), loc.clone());
+		let right_pane = Self::new(PaneKind::Edit, *size, *cells, *loc);
 		let new_id = right_pane.id();
 		
 		parent_pane.add_child_pane(self.clone());
@@ -145,48 +163,257 @@ impl Pane {
 	}
 	
 	fn resize_children(&mut self) {
-		let psize = self.size().clone();
-		let pcells = self.cells().clone();
-		let mut new_loc = self.loc().clone();
+		let psize = *self.size();
+		let pcells = *self.cells();
+		let mut new_loc = *self.loc();
 		
 		match self.kind {
 			PaneKind::Vert(ref mut layout) => {
-//				layout as dyn Layout
-				let num = layout.num_children();
-				let new_size = Size::new(psize.x, psize.y / num as f32);
-				let new_cells = Cells::new(pcells.x, pcells.y / num as u32);
+				// children are placed side by side, so they split the width.
+				let weights = layout.weights().to_vec();
 				
-				for mut pane in layout.iter_mut() {
-					pane.size = new_size;
-					pane.cells = new_cells;
+				for (pane, weight) in layout.iter_mut().zip(weights) {
+					pane.size = Size::new(psize.x * weight, psize.y);
+					pane.cells = Cells::new((pcells.x as f32 * weight) as u32, pcells.y);
 					pane.loc = new_loc;
 					
-					new_loc.x += new_size.x;
+					new_loc.x += pane.size.x;
 					
 					pane.resize_children();
 				}
 			},
 			PaneKind::Hor(ref mut layout) => {
-//				layout as dyn Layout
-				let num = layout.num_children();
-				
-				let new_size = Size::new(psize.x / num as f32, psize.y);
-				let new_cells = Cells::new(pcells.x / num as u32, pcells.y);
+				// children are stacked on top of each other, so they split the height.
+				let weights = layout.weights().to_vec();
 				
-				for mut pane in layout.iter_mut() {
-					pane.size = new_size;
-					pane.cells = new_cells;
+				for (pane, weight) in layout.iter_mut().zip(weights) {
+					pane.size = Size::new(psize.x, psize.y * weight);
+					pane.cells = Cells::new(pcells.x, (pcells.y as f32 * weight) as u32);
 					pane.loc = new_loc;
 					
-					new_loc.y += new_size.y;
+					new_loc.y += pane.size.y;
 					
 					pane.resize_children();
 				}
 			},
-			_ => return,
+			_ => {},
 		};	
 	}
 	
+	/// is the point (in physical pixels, origin top left) within this pane.
+	pub fn contains(&self, x: f32, y: f32) -> bool {
+		x >= self.loc.x && x < self.loc.x + self.size.x &&
+		y >= self.loc.y && y < self.loc.y + self.size.y
+	}
+	
+	/// finds the edit pane under the given point.
+	pub fn pane_at(&self, x: f32, y: f32) -> Option<PaneID> {
+		if !self.contains(x, y) {
+			return None;
+		}
+		
+		match self.kind {
+			PaneKind::Edit => Some(self.id),
+			PaneKind::Tree => None,
+			PaneKind::Vert(ref layout) => layout.iter().filter_map(|p| p.pane_at(x, y)).next(),
+			PaneKind::Hor(ref layout) => layout.iter().filter_map(|p| p.pane_at(x, y)).next(),
+		}
+	}
+	
+	/// finds the split border within `tolerance` pixels of the given point.
+	pub fn border_at(&self, x: f32, y: f32, tolerance: f32) -> Option<Border> {
+		if !self.contains(x, y) {
+			return None;
+		}
+		
+		match self.kind {
+			PaneKind::Edit | PaneKind::Tree => None,
+			PaneKind::Vert(ref layout) => {
+				let children: Vec<&Pane> = layout.iter().collect();
+				for (index, pane) in children.iter().enumerate() {
+					let edge = pane.loc.x + pane.size.x;
+					if index + 1 < children.len() && (x - edge).abs() <= tolerance {
+						return Some(Border { layout: self.id, index });
+					}
+				}
+				children.iter().filter_map(|p| p.border_at(x, y, tolerance)).next()
+			},
+			PaneKind::Hor(ref layout) => {
+				let children: Vec<&Pane> = layout.iter().collect();
+				for (index, pane) in children.iter().enumerate() {
+					let edge = pane.loc.y + pane.size.y;
+					if index + 1 < children.len() && (y - edge).abs() <= tolerance {
+						return Some(Border { layout: self.id, index });
+					}
+				}
+				children.iter().filter_map(|p| p.border_at(x, y, tolerance)).next()
+			},
+		}
+	}
+	
+	/// moves the given border so it sits under the point and re-lays out the affected children.
+	/// Returns false if the border no longer exists in this tree.
+	pub fn drag_border(&mut self, border: &Border, x: f32, y: f32) -> bool {
+		let id = self.id;
+		let loc = self.loc;
+		let size = self.size;
+		
+		let found = match self.kind {
+			PaneKind::Edit | PaneKind::Tree => false,
+			PaneKind::Vert(ref mut layout) if id == border.layout => {
+				let start: f32 = layout.weights()[..border.index].iter().sum();
+				let current = start + layout.weights()[border.index];
+				layout.shift_border(border.index, (x - loc.x) / size.x - current);
+				true
+			},
+			PaneKind::Hor(ref mut layout) if id == border.layout => {
+				let start: f32 = layout.weights()[..border.index].iter().sum();
+				let current = start + layout.weights()[border.index];
+				layout.shift_border(border.index, (y - loc.y) / size.y - current);
+				true
+			},
+			PaneKind::Vert(ref mut layout) => layout.iter_mut().any(|p| p.drag_border(border, x, y)),
+			PaneKind::Hor(ref mut layout) => layout.iter_mut().any(|p| p.drag_border(border, x, y)),
+		};
+		
+		if found && id == border.layout {
+			self.resize_children();
+		}
+		
+		found
+	}
+	
+	/// collects the ids, sizes and locations of every edit pane in this tree.
+	pub fn edit_panes(&self) -> Vec<(PaneID, Size, Loc)> {
+		let mut panes = Vec::new();
+		self.collect_edit_panes(&mut panes);
+		panes
+	}
+	
+	/// collects the lines between the children of every layout pane in this tree.
+	pub fn separators(&self) -> Vec<Separator> {
+		let mut separators = Vec::new();
+		self.collect_separators(&mut separators);
+		separators
+	}
+	
+	fn collect_separators(&self, separators: &mut Vec<Separator>) {
+		let children: Vec<&Pane> = match self.kind {
+			PaneKind::Edit | PaneKind::Tree => return,
+			PaneKind::Vert(ref layout) => layout.iter().collect(),
+			PaneKind::Hor(ref layout) => layout.iter().collect(),
+		};
+		let vertical = matches!(self.kind, PaneKind::Vert(_));
+		
+		for pane in children.iter().take(children.len().saturating_sub(1)) {
+			separators.push(if vertical {
+				Separator { loc: glm::vec2(pane.loc.x + pane.size.x, self.loc.y), length: self.size.y, vertical }
+			} else {
+				Separator { loc: glm::vec2(self.loc.x, pane.loc.y + pane.size.y), length: self.size.x, vertical }
+			});
+		}
+		
+		children.iter().for_each(|p| p.collect_separators(separators));
+	}
+	
+	fn collect_edit_panes(&self, panes: &mut Vec<(PaneID, Size, Loc)>) {
+		match self.kind {
+			PaneKind::Edit => panes.push((self.id, self.size, self.loc)),
+			PaneKind::Tree => {},
+			PaneKind::Vert(ref layout) => layout.iter().for_each(|p| p.collect_edit_panes(panes)),
+			PaneKind::Hor(ref layout) => layout.iter().for_each(|p| p.collect_edit_panes(panes)),
+		}
+	}
+	
+	/// the tree pane in this tree of panes, if it is shown.
+	pub fn tree_pane(&self) -> Option<&Pane> {
+		match self.kind {
+			PaneKind::Tree => Some(self),
+			PaneKind::Edit => None,
+			PaneKind::Vert(ref layout) => layout.iter().filter_map(|p| p.tree_pane()).next(),
+			PaneKind::Hor(ref layout) => layout.iter().filter_map(|p| p.tree_pane()).next(),
+		}
+	}
+	
+	/// puts a tree pane left of everything in this pane, taking `weight` of the width.
+	pub fn insert_tree(&mut self, weight: f32) -> PaneID {
+		let tree = Self::new(PaneKind::Tree, self.size, self.cells, self.loc);
+		let id = tree.id();
+		
+		let mut parent = Self::new(PaneKind::Vert(layout::VerticalLayout::new()), self.size, self.cells, self.loc);
+		parent.add_child_pane(self.clone());
+		
+		if let PaneKind::Vert(ref mut layout) = parent.kind {
+			layout.insert_child(0, tree, weight);
+		}
+		
+		parent.resize_children();
+		*self = parent;
+		
+		id
+	}
+	
+	/// a layout of the panes, side by side when it is vertical, sharing it by the weights.
+	/// It is sized when it is fit to the window.
+	pub fn split(vertical: bool, children: Vec<Pane>, weights: &[f32]) -> Self {
+		let kind = match vertical {
+			true => PaneKind::Vert(layout::VerticalLayout::new()),
+			false => PaneKind::Hor(layout::HorizontalLayout::new()),
+		};
+		
+		let mut pane = Self::new(kind, Size::new(0.0, 0.0), Cells::new(0, 0), glm::vec2(0.0, 0.0));
+		children.into_iter().for_each(|child| pane.add_child_pane(child));
+		
+		match pane.kind {
+			PaneKind::Vert(ref mut layout) => layout.set_weights(weights),
+			PaneKind::Hor(ref mut layout) => layout.set_weights(weights),
+			_ => {},
+		}
+		
+		pane
+	}
+	
+	/// takes the pane out of the tree, a layout left with a single child is replaced by it.
+	/// Returns false if the pane is not in this tree.
+	pub fn remove_pane(&mut self, id: PaneID) -> bool {
+		let removed = match self.kind {
+			PaneKind::Edit | PaneKind::Tree => return false,
+			PaneKind::Vert(ref mut layout) => Self::remove_child_pane(layout, id),
+			PaneKind::Hor(ref mut layout) => Self::remove_child_pane(layout, id),
+		};
+		
+		if !removed {
+			return false;
+		}
+		
+		let only_child = match self.kind {
+			PaneKind::Vert(ref layout) if layout.num_children() == 1 => layout.iter().next().cloned(),
+			PaneKind::Hor(ref layout) if layout.num_children() == 1 => layout.iter().next().cloned(),
+			_ => None,
+		};
+		
+		// the child takes the place of the layout.
+		if let Some(mut child) = only_child {
+			child.size = self.size;
+			child.cells = self.cells;
+			child.loc = self.loc;
+			*self = child;
+		}
+		
+		self.resize_children();
+		true
+	}
+	
+	fn remove_child_pane<L: Layout>(layout: &mut L, id: PaneID) -> bool {
+		match layout.iter().position(|p| p.id == id) {
+			Some(index) => {
+				layout.remove_child(index);
+				true
+			},
+			None => layout.iter_mut().any(|p| p.remove_pane(id)),
+		}
+	}
+	
 	fn add_child_pane(&mut self, pane: Pane) {
 		match self.kind {
 			PaneKind::Vert(ref mut layout) => {
@@ -218,5 +445,83 @@ impl Pane {
         self.update_children(cell_size);
     }
 
-    pub fn update_children(&mut self, cell_size: CellSize) {}
+    /// re-lays out the children and recomputes the cells of every pane.
+    pub fn update_children(&mut self, cell_size: CellSize) {
+        self.resize_children();
+        self.set_cell_size(cell_size);
+    }
+
+    /// recomputes the number of cells that fit in this pane and its children.
+    pub fn set_cell_size(&mut self, cell_size: CellSize) {
+        self.cells = Cells::compute_cells(self.size.x, self.size.y, cell_size);
+
+        match self.kind {
+            PaneKind::Vert(ref mut layout) => layout.iter_mut().for_each(|p| p.set_cell_size(cell_size)),
+            PaneKind::Hor(ref mut layout) => layout.iter_mut().for_each(|p| p.set_cell_size(cell_size)),
+            PaneKind::Edit | PaneKind::Tree => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resizing_relays_out_the_split_tree() {
+        let cell_size = CellSize::new(10.0, 20.0);
+        let mut root = Pane::new(PaneKind::Edit, Size::new(200.0, 100.0), Cells::new(20, 5), glm::vec2(0.0, 0.0));
+        let left = root.id();
+        let right = root.vertical_split();
+
+        root.on_resize(Size::new(400.0, 60.0), Cells::new(40, 3), cell_size);
+
+        let panes = root.edit_panes();
+        assert_eq!(panes.len(), 2);
+        assert_eq!(panes[0].0, left);
+        assert_eq!(panes[1].0, right);
+        assert_eq!((panes[0].1.x, panes[0].1.y), (200.0, 60.0));
+        assert_eq!((panes[1].2.x, panes[1].2.y), (200.0, 0.0));
+
+        let separators = root.separators();
+        assert_eq!(separators.len(), 1);
+        assert_eq!((separators[0].loc.x, separators[0].length), (200.0, 60.0));
+        assert!(separators[0].vertical);
+    }
+
+    #[test]
+    fn the_tree_is_inserted_and_removed() {
+        let mut root = Pane::new(PaneKind::Edit, Size::new(200.0, 100.0), Cells::new(20, 5), glm::vec2(0.0, 0.0));
+        let edit = root.id();
+        let right = root.vertical_split();
+
+        let tree = root.insert_tree(0.25);
+        let pane = root.tree_pane().unwrap();
+        assert_eq!(pane.id(), tree);
+        assert_eq!((pane.size().x, pane.loc().x), (50.0, 0.0));
+
+        // the edit panes share what is left of the width.
+        let panes = root.edit_panes();
+        assert_eq!((panes[0].0, panes[0].2.x, panes[0].1.x), (edit, 50.0, 75.0));
+        assert_eq!((panes[1].0, panes[1].2.x), (right, 125.0));
+
+        assert!(root.remove_pane(tree));
+        assert!(root.tree_pane().is_none());
+
+        let panes = root.edit_panes();
+        assert_eq!((panes[0].2.x, panes[0].1.x), (0.0, 100.0));
+        assert_eq!(panes[1].2.x, 100.0);
+    }
+
+    #[test]
+    fn splits_are_laid_out_by_their_weights() {
+        let top = Pane::new(PaneKind::Edit, Size::new(0.0, 0.0), Cells::new(0, 0), glm::vec2(0.0, 0.0));
+        let bottom = Pane::new(PaneKind::Edit, Size::new(0.0, 0.0), Cells::new(0, 0), glm::vec2(0.0, 0.0));
+        let mut root = Pane::split(false, vec![top, bottom], &[1.0, 3.0]);
+
+        root.on_resize(Size::new(200.0, 100.0), Cells::new(20, 5), CellSize::new(10.0, 20.0));
+
+        let panes = root.edit_panes();
+        assert_eq!((panes[0].1.y, panes[1].1.y, panes[1].2.y), (25.0, 75.0, 25.0));
+    }
 }
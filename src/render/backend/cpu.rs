@@ -0,0 +1,282 @@
+use image::{Rgba, RgbaImage};
+
+use std::collections::HashMap;
+
+use crate::pane::CellSize;
+
+use super::super::{InstanceData, Result};
+use super::{Backend, Size};
+
+/// An rgb image of glyphs.
+struct Texture {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+/// Draws into images in memory with the same results as the shaders, so what the editor
+/// draws can be checked without a gpu.
+pub struct CpuBackend {
+    textures: HashMap<u32, Texture>,
+    targets: HashMap<u32, RgbaImage>,
+    window: RgbaImage,
+    /// the target being drawn into, the window when None.
+    bound: Option<u32>,
+    /// the id given to the next texture or target.
+    next_id: u32,
+    cell_size: CellSize,
+    gamma: f32,
+}
+
+impl CpuBackend {
+    pub fn new() -> Self {
+        Self {
+            textures: HashMap::new(),
+            targets: HashMap::new(),
+            window: RgbaImage::new(0, 0),
+            bound: None,
+            next_id: 1,
+            cell_size: CellSize::new(0.0, 0.0),
+            gamma: 1.0,
+        }
+    }
+
+    /// the image a pane was drawn into.
+    pub fn target_image(&self, target: u32) -> Option<&RgbaImage> {
+        self.targets.get(&target)
+    }
+
+    pub fn window_image(&self) -> &RgbaImage {
+        &self.window
+    }
+
+    /// the rgb pixels of a texture.
+    pub fn texture_data(&self, texture: u32) -> Option<&[u8]> {
+        self.textures.get(&texture).map(|t| t.data.as_slice())
+    }
+
+    fn next_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn image(&mut self) -> &mut RgbaImage {
+        match self.bound {
+            Some(target) if self.targets.contains_key(&target) => self.targets.get_mut(&target).unwrap(),
+            _ => &mut self.window,
+        }
+    }
+
+    /// the top left pixel of a cell.
+    fn cell_origin(&self, instance: &InstanceData) -> (f32, f32) {
+        (instance.x * self.cell_size.x, instance.y * self.cell_size.y)
+    }
+}
+
+impl Default for CpuBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// blends a channel of text over the background it is drawn on the way the text shader does.
+/// The coverage is adjusted so the result is the blend of the colors in linear space.
+fn blend(fg: f32, bg: f32, coverage: f32, gamma: f32, dst: f32) -> f32 {
+    let linear = bg.powf(gamma) * (1.0 - coverage) + fg.powf(gamma) * coverage;
+    let target = linear.powf(1.0 / gamma);
+
+    let delta = fg - bg;
+    let alpha = if delta.abs() > 0.001 {
+        ((target - bg) / delta).clamp(0.0, 1.0)
+    } else {
+        coverage
+    };
+
+    fg * alpha + dst * (1.0 - alpha)
+}
+
+impl Backend for CpuBackend {
+    fn prepare(&mut self, cell_size: CellSize, gamma: f32) {
+        self.cell_size = cell_size;
+        self.gamma = gamma;
+    }
+
+    fn create_texture(&mut self, size: Size) -> Result<u32> {
+        let id = self.next_id();
+        let (width, height) = (size.x as u32, size.y as u32);
+
+        self.textures.insert(
+            id,
+            Texture {
+                width,
+                height,
+                data: vec![0; (width * height * 3) as usize],
+            },
+        );
+
+        Ok(id)
+    }
+
+    fn update_texture(&mut self, texture: u32, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        let texture = match self.textures.get_mut(&texture) {
+            Some(texture) => texture,
+            None => return,
+        };
+
+        for row in 0..height.min(texture.height.saturating_sub(y)) {
+            let columns = width.min(texture.width.saturating_sub(x)) as usize;
+            let src = (row * width * 3) as usize;
+            let dst = (((y + row) * texture.width + x) * 3) as usize;
+
+            texture.data[dst..dst + columns * 3].copy_from_slice(&data[src..src + columns * 3]);
+        }
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        self.textures.remove(&texture);
+    }
+
+    fn create_target(&mut self, size: Size) -> Result<u32> {
+        let id = self.next_id();
+        self.targets.insert(id, RgbaImage::new(size.x as u32, size.y as u32));
+        Ok(id)
+    }
+
+    fn delete_target(&mut self, target: u32) {
+        self.targets.remove(&target);
+    }
+
+    fn target_size(&self, target: u32) -> Option<Size> {
+        self.targets
+            .get(&target)
+            .map(|image| Size::new(image.width() as f32, image.height() as f32))
+    }
+
+    fn bind_target(&mut self, target: u32) {
+        self.bound = Some(target);
+    }
+
+    fn bind_window(&mut self, size: Size) {
+        let (width, height) = (size.x as u32, size.y as u32);
+        if self.window.width() != width || self.window.height() != height {
+            self.window = RgbaImage::new(width, height);
+        }
+
+        self.bound = None;
+    }
+
+    fn clear(&mut self, color: [f32; 4]) {
+        let pixel = Rgba([to_byte(color[0]), to_byte(color[1]), to_byte(color[2]), to_byte(color[3])]);
+
+        for p in self.image().pixels_mut() {
+            *p = pixel;
+        }
+    }
+
+    fn clear_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        let pixel = Rgba([to_byte(color[0]), to_byte(color[1]), to_byte(color[2]), to_byte(color[3])]);
+
+        let image = self.image();
+        let right = ((x + width).round() as u32).min(image.width());
+        let bottom = ((y + height).round() as u32).min(image.height());
+
+        for py in y.round().max(0.0) as u32..bottom {
+            for px in x.round().max(0.0) as u32..right {
+                image.put_pixel(px, py, pixel);
+            }
+        }
+    }
+
+    fn draw_backgrounds(&mut self, instances: &[InstanceData]) {
+        let cell_size = self.cell_size;
+
+        for instance in instances.iter().filter(|i| i.ba != 0.0) {
+            let (x, y) = self.cell_origin(instance);
+            let pixel = Rgba([to_byte(instance.br), to_byte(instance.bg), to_byte(instance.bb), to_byte(instance.ba)]);
+
+            let image = self.image();
+            let (width, height) = (image.width() as f32, image.height() as f32);
+
+            // the pixels whose centers are in the cell.
+            let (left, right) = (x.round().max(0.0), (x + cell_size.x).round().min(width));
+            let (top, bottom) = (y.round().max(0.0), (y + cell_size.y).round().min(height));
+
+            for py in top as u32..bottom.max(top) as u32 {
+                for px in left as u32..right.max(left) as u32 {
+                    image.put_pixel(px, py, pixel);
+                }
+            }
+        }
+    }
+
+    fn draw_glyphs(&mut self, texture: u32, instances: &[InstanceData]) {
+        let cell_size = self.cell_size;
+        let gamma = self.gamma;
+
+        // the texture is taken out so the bound image can be drawn into while reading it.
+        let atlas = match self.textures.remove(&texture) {
+            Some(atlas) => atlas,
+            None => return,
+        };
+
+        for instance in instances {
+            let (x, y) = self.cell_origin(instance);
+            let left = (x + instance.offset_x).round() as i64;
+            let top = (y + cell_size.y - instance.offset_y).round() as i64;
+
+            let tex_x = (instance.uv_x * atlas.width as f32).round() as u32;
+            let tex_y = (instance.uv_y * atlas.height as f32).round() as u32;
+
+            let fg = [instance.tr, instance.tg, instance.tb];
+            let bg = [instance.br, instance.bg, instance.bb];
+
+            let image = self.image();
+            let (width, height) = (image.width() as i64, image.height() as i64);
+
+            for row in 0..instance.height as u32 {
+                for column in 0..instance.width as u32 {
+                    let (px, py) = (left + column as i64, top + row as i64);
+                    if px < 0 || py < 0 || px >= width || py >= height {
+                        continue;
+                    }
+
+                    let (tx, ty) = (tex_x + column, tex_y + row);
+                    if tx >= atlas.width || ty >= atlas.height {
+                        continue;
+                    }
+
+                    let texel = ((ty * atlas.width + tx) * 3) as usize;
+                    let pixel = image.get_pixel_mut(px as u32, py as u32);
+
+                    for channel in 0..3 {
+                        let coverage = atlas.data[texel + channel] as f32 / 255.0;
+                        let dst = pixel[channel] as f32 / 255.0;
+                        pixel[channel] = to_byte(blend(fg[channel], bg[channel], coverage, gamma, dst));
+                    }
+                }
+            }
+        }
+
+        self.textures.insert(texture, atlas);
+    }
+
+    fn present(&mut self, target: u32, x: f32, y: f32) {
+        let image = match self.targets.get(&target) {
+            Some(image) => image,
+            None => return,
+        };
+
+        let (x, y) = (x.round() as u32, y.round() as u32);
+
+        for (px, py, pixel) in image.enumerate_pixels() {
+            if x + px < self.window.width() && y + py < self.window.height() {
+                self.window.put_pixel(x + px, y + py, *pixel);
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::pane::CellSize;
+use crate::size;
+
+use super::{InstanceData, Result};
+
+// draws into an image so frames can be checked without a window.
+#[cfg(test)]
+mod cpu;
+mod opengl;
+
+#[cfg(test)]
+pub use self::cpu::CpuBackend;
+pub use self::opengl::GlBackend;
+
+pub type Size = size::Size<f32>;
+
+/// The drawing the renderer needs, done with OpenGL for the window and on the cpu where
+/// there is no gpu, such as for the screenshot tests.
+///
+/// Instances are positioned on a grid of cells with the top left of the bound image as the origin.
+pub trait Backend {
+    /// sets the state used by every draw, after the window or the font changed.
+    fn prepare(&mut self, cell_size: CellSize, gamma: f32);
+
+    /// creates an rgb texture glyphs are copied into, returning its id.
+    fn create_texture(&mut self, size: Size) -> Result<u32>;
+
+    /// copies rgb pixels into a region of the texture.
+    fn update_texture(&mut self, texture: u32, x: u32, y: u32, width: u32, height: u32, data: &[u8]);
+
+    fn delete_texture(&mut self, texture: u32);
+
+    /// creates an image a pane is drawn into, returning its id.
+    fn create_target(&mut self, size: Size) -> Result<u32>;
+
+    fn delete_target(&mut self, target: u32);
+
+    fn target_size(&self, target: u32) -> Option<Size>;
+
+    /// draws into the target until something else is bound.
+    fn bind_target(&mut self, target: u32);
+
+    /// draws into the window, which has the given size in physical pixels.
+    fn bind_window(&mut self, size: Size);
+
+    fn clear(&mut self, color: [f32; 4]);
+
+    /// clears a rectangle of the bound image, in pixels from its top left.
+    fn clear_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]);
+
+    /// fills the cells of the instances with their background colors.
+    fn draw_backgrounds(&mut self, instances: &[InstanceData]);
+
+    /// draws the glyphs of the instances, all of them are in the texture.
+    fn draw_glyphs(&mut self, texture: u32, instances: &[InstanceData]);
+
+    /// copies a target onto the window with its top left corner at (x, y).
+    fn present(&mut self, target: u32, x: f32, y: f32);
+}
@@ -0,0 +1,408 @@
+use gl;
+use gl::types::*;
+use nalgebra_glm as glm;
+
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+
+use crate::glCheck;
+use crate::pane::CellSize;
+
+use super::super::framebuffer::FrameBuffer;
+use super::super::render::BATCH_SIZE;
+use super::super::shader::{RectShader, TextShader};
+use super::super::{Error, InstanceData, Result};
+use super::{Backend, Size};
+
+/// Draws with OpenGL, the textures and targets are OpenGL textures and frame buffers.
+pub struct GlBackend {
+    // vertex buffer object
+    vbo: u32,
+    // index buffer object
+    ibo: u32,
+    // vertex attribute object
+    vao: u32,
+    // the shader for rendering text
+    text_shader: TextShader,
+    // the shader for rendering text
+    #[allow(dead_code)]
+    rect_shader: RectShader,
+    // the frame buffers panes are drawn into
+    targets: HashMap<u32, FrameBuffer>,
+    // the size of the window
+    window_size: Size,
+    // the size of the bound target or window
+    bound_size: Size,
+}
+
+impl GlBackend {
+    pub fn new() -> Result<Self> {
+        let mut vao = 0;
+
+        let mut bufs = [0, 0];
+        // bufs[0] is vbo
+        // bufs[1] is ibo
+
+        let index_data = [0, 1, 2, 0, 2, 3];
+
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            // generate both with a single call
+            gl::GenBuffers(2, bufs.as_mut_ptr());
+
+            gl::BindVertexArray(vao);
+
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, bufs[1]);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                (mem::size_of::<u32>() * index_data.len()) as isize,
+                index_data.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, bufs[0]);
+
+            let size = mem::size_of::<InstanceData>() as usize;
+
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (size * BATCH_SIZE) as isize,
+                ptr::null(),
+                gl::STATIC_DRAW,
+            );
+
+            let float_size = mem::size_of::<f32>();
+
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, size as i32, ptr::null());
+            gl::VertexAttribDivisor(0, 1);
+            glCheck!();
+
+            let mut stride = 2;
+
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(
+                1,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                size as i32,
+                (stride * float_size) as *const _,
+            );
+            gl::VertexAttribDivisor(1, 1);
+            glCheck!();
+
+            stride += 4;
+
+            // color attribute
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(
+                2,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                size as i32,
+                (stride * float_size) as *const _,
+            );
+            gl::VertexAttribDivisor(2, 1);
+            glCheck!();
+
+            stride += 4;
+
+            gl::EnableVertexAttribArray(3);
+            gl::VertexAttribPointer(
+                3,
+                3,
+                gl::FLOAT,
+                gl::FALSE,
+                size as i32,
+                (stride * float_size) as *const _,
+            );
+            gl::VertexAttribDivisor(3, 1);
+            glCheck!();
+
+            stride += 3;
+
+            gl::EnableVertexAttribArray(4);
+            gl::VertexAttribPointer(
+                4,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                size as i32,
+                (stride * float_size) as *const _,
+            );
+            gl::VertexAttribDivisor(4, 1);
+
+            glCheck!();
+        }
+
+        let text_shader = TextShader::new()?;
+        let rect_shader = RectShader::new()?;
+
+        Ok(Self {
+            vao,
+            vbo: bufs[0],
+            ibo: bufs[1],
+            text_shader,
+            rect_shader,
+            targets: HashMap::new(),
+            window_size: Size::new(0.0, 0.0),
+            bound_size: Size::new(0.0, 0.0),
+        })
+    }
+
+    /// makes the projection and view port cover an image of the size.
+    fn set_view(&self, size: Size) {
+        let ortho = glm::ortho(0f32, size.x, size.y, 0f32, -1f32, 1f32);
+
+        unsafe {
+            gl::Viewport(0, 0, size.x as i32, size.y as i32);
+        }
+
+        self.text_shader.activate();
+        self.text_shader.set_perspective(ortho);
+        self.text_shader.deactivate();
+    }
+
+    fn draw_instances(&self, instances: &[InstanceData]) {
+        for chunk in instances.chunks(BATCH_SIZE) {
+            unsafe {
+                gl::BindVertexArray(self.vao);
+                glCheck!();
+
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ibo);
+                glCheck!();
+
+                gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+                glCheck!();
+
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    (mem::size_of::<InstanceData>() * BATCH_SIZE) as isize,
+                    ptr::null(),
+                    gl::STREAM_DRAW,
+                );
+                gl::BufferSubData(
+                    gl::ARRAY_BUFFER,
+                    0,
+                    mem::size_of_val(chunk) as isize,
+                    chunk.as_ptr() as *const _,
+                );
+                glCheck!();
+
+                gl::DrawElementsInstanced(
+                    gl::TRIANGLES,
+                    6,
+                    gl::UNSIGNED_INT,
+                    ptr::null(),
+                    chunk.len() as i32,
+                );
+                glCheck!();
+
+                gl::BindVertexArray(0);
+            }
+        }
+    }
+}
+
+impl Backend for GlBackend {
+    fn prepare(&mut self, cell_size: CellSize, gamma: f32) {
+        self.text_shader.activate();
+        self.text_shader.set_cell_size(cell_size);
+        self.text_shader.set_gamma(gamma);
+        self.text_shader.deactivate();
+
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC1_COLOR, gl::ONE_MINUS_SRC1_COLOR);
+            gl::Enable(gl::MULTISAMPLE);
+            gl::DepthMask(gl::FALSE);
+        }
+    }
+
+    fn create_texture(&mut self, size: Size) -> Result<u32> {
+        let mut texture = 0;
+
+        unsafe {
+            gl::GenTextures(1, &mut texture);
+
+            if texture == 0 {
+                return Err(Error::AtlasError(
+                    format!("Failed to allocate texture of size {:?}", size).to_owned(),
+                ));
+            }
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as GLint);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+
+            // allocate an empty texture
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB as i32,
+                size.x as i32,
+                size.y as i32,
+                0,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Ok(texture)
+    }
+
+    fn update_texture(&mut self, texture: u32, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x as i32,
+                y as i32,
+                width as i32,
+                height as i32,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const _,
+            );
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        unsafe {
+            gl::DeleteTextures(1, &texture);
+        }
+    }
+
+    fn create_target(&mut self, size: Size) -> Result<u32> {
+        let frame = FrameBuffer::with_size(size).map_err(Error::FrameBufferError)?;
+        let id = frame.fbo;
+
+        self.targets.insert(id, frame);
+        Ok(id)
+    }
+
+    fn delete_target(&mut self, target: u32) {
+        if let Some(frame) = self.targets.remove(&target) {
+            frame.clear();
+        }
+    }
+
+    fn target_size(&self, target: u32) -> Option<Size> {
+        self.targets.get(&target).map(|frame| frame.size())
+    }
+
+    fn bind_target(&mut self, target: u32) {
+        if let Some(frame) = self.targets.get(&target) {
+            frame.bind_write();
+            self.set_view(frame.size());
+            self.bound_size = frame.size();
+        }
+    }
+
+    fn bind_window(&mut self, size: Size) {
+        unsafe {
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+        }
+
+        self.window_size = size;
+        self.bound_size = size;
+        self.set_view(size);
+    }
+
+    fn clear(&mut self, color: [f32; 4]) {
+        unsafe {
+            gl::ClearColor(color[0], color[1], color[2], color[3]);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+    }
+
+    fn clear_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        // the scissor's origin is the bottom left.
+        let bottom = self.bound_size.y - y - height;
+
+        unsafe {
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Scissor(x as i32, bottom as i32, width as i32, height as i32);
+
+            gl::ClearColor(color[0], color[1], color[2], color[3]);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            gl::Disable(gl::SCISSOR_TEST);
+        }
+    }
+
+    fn draw_backgrounds(&mut self, instances: &[InstanceData]) {
+        self.text_shader.activate();
+        self.text_shader.set_background_pass(1);
+
+        self.draw_instances(instances);
+
+        self.text_shader.deactivate();
+    }
+
+    fn draw_glyphs(&mut self, texture: u32, instances: &[InstanceData]) {
+        self.text_shader.activate();
+        self.text_shader.set_background_pass(0);
+        self.text_shader.set_font_atlas_texture(texture as i32);
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+        }
+
+        self.draw_instances(instances);
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        self.text_shader.deactivate();
+    }
+
+    fn present(&mut self, target: u32, x: f32, y: f32) {
+        let frame = match self.targets.get(&target) {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let size = frame.size();
+        // the window's origin is its bottom left.
+        let bottom = self.window_size.y - y - size.y;
+
+        frame.bind_read();
+
+        unsafe {
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+
+            gl::BlitFramebuffer(
+                0,
+                0,
+                size.x as i32,
+                size.y as i32,
+                x as i32,
+                bottom as i32,
+                (x + size.x) as i32,
+                (bottom + size.y) as i32,
+                gl::COLOR_BUFFER_BIT,
+                gl::NEAREST,
+            );
+
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+    }
+}
@@ -1,10 +1,12 @@
-use gl::types::*;
-
 use std::collections::HashMap;
-use std::mem;
-use std::ptr;
-
-use super::font::{self, FontDesc, FontKey, FontSize, GlyphKey, RasterizedGlyph, Rasterizer};
+use std::rc::Rc;
+
+use super::font::{
+    self, Decoration, FontKey, FontSize, GlyphIndexKey, GlyphKey, RasterizedGlyph, Rasterizer, Style,
+};
+use super::backend::Backend;
+use super::packer::Skyline;
+use super::shaping::{self, ShapeCache, ShapedLine};
 use super::Error;
 use super::Glyph;
 use super::Result;
@@ -13,185 +15,81 @@ use crate::config;
 use crate::size;
 
 pub trait GlyphLoader {
-    /// load a glyph
+    /// load a glyph, AtlasFull when there is no room left in any atlas.
     fn load_glyph(&mut self, glyph: &RasterizedGlyph) -> Result<super::Glyph>;
 
+    /// empties an atlas and its texture so its space is reused, the glyphs in it can no longer be drawn.
+    fn evict(&mut self, atlas: u32);
+
+    /// adds an atlas past the limit, for when every atlas is used by the current frame.
+    fn grow(&mut self) -> Result<()>;
+
     /// clear
     fn clear(&mut self);
 }
 
 pub type Size = size::Size<f32>;
 
-/////////////////////////////////////////////////////////
-///
-///
-///
-///             current x
-///             v
-/// +---------------------------------------------------+
-/// |                                                   |
-/// |                                                   |
-/// |                                                   |
-/// |                                                   |
-/// |                                                   |
-/// |---------------------------------------------------|
-/// |           |                                       |
-/// |     f     |                                       |
-/// |           |                                       |
-/// |---------------------------------------------------| < Base Line
-/// |           |       |       |         |        |    |
-/// |     a     |   b   |   c   |    d    |   e    |    | This line is full
-/// |           |       |       |         |        |    | the next character
-/// +---------------------------------------------------+ would not fit
-///
-///
-///
-/////////////////////////////////////////////////////////
+/// the space left between glyphs so filtering does not blend in the neighbouring glyphs.
+const PADDING: u32 = 1;
+
+/// A texture the rasterized glyphs are packed into.
 #[derive(Debug, Clone)]
 pub struct Atlas {
-    // the current x within the atlas
-    x: f32,
-    // the current baseline of the atlas
-    base_line: f32,
+    // the space used by the glyphs in the atlas
+    packer: Skyline,
     // the size of the underline texture
     pub(crate) size: Size,
-    // the height of the larget subtexture in the current row.
-    max_height: f32,
     // the gl texture handle
     pub(crate) texture_id: u32,
-    // the altas id
-    id: u32,
 }
 
 impl Atlas {
-    // the gl texture is not allocated until a subtexture is added.
-    pub fn new(size: Size) -> Result<Self> {
-        let mut atlas = Self {
-            x: 0.0,
-            base_line: 0.0,
+    pub fn new(size: Size, backend: &mut dyn Backend) -> Result<Self> {
+        Ok(Self {
+            packer: Skyline::new(size.x as u32, size.y as u32),
             size,
-            max_height: 0.0,
-            texture_id: 0,
-            id: 0, // I dont know how to set this.
-        };
-
-        atlas.allocate_texture()?;
-
-        Ok(atlas)
-    }
-
-    pub fn bind(&self) {
-        unsafe {
-            gl::ActiveTexture(gl::TEXTURE0 + self.texture_id);
-            gl::BindTexture(gl::TEXTURE_2D, self.texture_id);
-        }
-    }
-
-    pub fn unbind(&self) {
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-        }
-    }
-
-    // allocates the gl texture
-    fn allocate_texture(&mut self) -> Result<()> {
-        unsafe {
-            gl::GenTextures(1, &mut self.texture_id);
-
-            if self.texture_id == 0 {
-                return Err(Error::AtlasError(
-                    format!("Failed to allocate texture of size {:?}", self.size).to_owned(),
-                ));
-            }
-
-            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-            gl::BindTexture(gl::TEXTURE_2D, self.texture_id);
-
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_S,
-                gl::CLAMP_TO_BORDER as GLint,
-            );
-
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_T,
-                gl::CLAMP_TO_BORDER as GLint,
-            );
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-
-            // allocate an empty texture
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGB as i32,
-                self.size.x as i32,
-                self.size.y as i32,
-                0,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
-                std::ptr::null(),
-            );
-
-            // glCheck!();
-
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-        }
-        Ok(())
+            texture_id: backend.create_texture(size)?,
+        })
     }
 
-    pub fn insert(&mut self, glyph: &RasterizedGlyph) -> Result<Glyph> {
-        // move to next row if needed
-        //println!("{:?}", self.size);
-        //println!("\tW: {}, H: {}", glyph.width, glyph.height);
-        //println!("\tX: {}, y: {}", self.x, self.base_line);
-        if !self.has_space(glyph) {
-            self.advance()?;
-        }
-
-        // check if the glyph will fit vertically fix in the altas.
-        if self.base_line + glyph.height > self.size.height() as f32 {
-            return Err(Error::AtlasFull);
-        }
-
-        // the glyph can be added.
-
-        // if the new glyph is vertically largest glyph in the row then set
-        // it to the new heightest.
-        if glyph.height > self.max_height {
-            self.max_height = glyph.height;
+    pub fn insert(&mut self, glyph: &RasterizedGlyph, backend: &mut dyn Backend) -> Result<Glyph> {
+        // glyphs such as spaces have nothing to draw so they do not take any room.
+        if glyph.width == 0.0 || glyph.height == 0.0 {
+            return Ok(Glyph {
+                ch: glyph.glyph,
+                atlas: self.texture_id,
+                width: 0.0,
+                height: 0.0,
+                uv_x: 0.0,
+                uv_y: 0.0,
+                uv_dx: 0.0,
+                uv_dy: 0.0,
+                top: glyph.top,
+                left: glyph.left,
+                advance_x: glyph.advance_x,
+                advance_y: glyph.advance_y,
+                bearing_x: glyph.bearing_x,
+                bearing_y: glyph.bearing_y,
+            });
         }
 
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.texture_id);
-            // glCheck!();
-
-            //println!("{:?}", glyph);
-            //println!("{:?}", self.size);
-
-            gl::TexSubImage2D(
-                gl::TEXTURE_2D,
-                0,
-                self.x as i32,
-                self.base_line as i32,
-                glyph.width as i32,
-                glyph.height as i32,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
-                mem::transmute(&glyph.bitmap[0]),
-            );
-            // glCheck!();
-
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-        }
-
-        let old_x = self.x;
+        let (x, y) = match self
+            .packer
+            .pack(glyph.width as u32 + PADDING, glyph.height as u32 + PADDING)
+        {
+            Some((x, y)) => (x as f32, y as f32),
+            None => return Err(Error::AtlasFull),
+        };
 
-        // move the x cursor forward.
-        self.x += glyph.width;
+        backend.update_texture(
+            self.texture_id,
+            x as u32,
+            y as u32,
+            glyph.width as u32,
+            glyph.height as u32,
+            &glyph.bitmap,
+        );
 
         // build the glyph
         Ok(Glyph {
@@ -199,10 +97,10 @@ impl Atlas {
             atlas: self.texture_id,
             width: glyph.width,
             height: glyph.height,
-            uv_x: old_x / self.size.width() as f32,
-            uv_y: self.base_line / self.size.y as f32,
-            uv_dx: glyph.width / self.size.x as f32,
-            uv_dy: glyph.height / self.size.y as f32,
+            uv_x: x / self.size.width(),
+            uv_y: y / self.size.y,
+            uv_dx: glyph.width / self.size.x,
+            uv_dy: glyph.height / self.size.y,
             top: glyph.top,
             left: glyph.left,
             advance_x: glyph.advance_x,
@@ -212,29 +110,15 @@ impl Atlas {
         })
     }
 
-    // checks whether there is room on the current row for the new glyph
-    fn has_space(&self, glyph: &RasterizedGlyph) -> bool {
-        self.x + glyph.width < self.size.x as f32
+    // forgets every glyph in the atlas and clears the part of the texture they were drawn in.
+    fn reset(&mut self, backend: &mut dyn Backend) {
+        let (width, height) = (self.size.x as u32, self.packer.used_height());
+        backend.update_texture(self.texture_id, 0, 0, width, height, &vec![0; (width * height * 3) as usize]);
+        self.packer.clear();
     }
 
-    // advance to the next row of the atlas
-    // Errors if there is no more room
-    fn advance(&mut self) -> Result<()> {
-        println!("Advance!");
-        if self.base_line + self.max_height < self.size.y as f32 {
-            self.x = 0.0;
-            self.base_line += self.max_height;
-            self.max_height = 0f32;
-            Ok(())
-        } else {
-            Err(Error::AtlasError("last line of atlas".to_owned()))
-        }
-    }
-
-    fn clear(&mut self) {
-        unsafe {
-            gl::DeleteTextures(1, &self.texture_id);
-        }
+    fn clear(&mut self, backend: &mut dyn Backend) {
+        backend.delete_texture(self.texture_id);
     }
 }
 
@@ -253,13 +137,44 @@ pub struct GlyphCache<T> {
     font_size: FontSize,
     metrics: font::Metrics,
     proto: CacheMissProto,
+    /// the glyph drawn for characters the font does not have.
+    missing: Option<Glyph>,
+    /// the bars drawn for underlined and struck through text.
+    decorations: HashMap<Decoration, Glyph>,
+    /// the glyphs of shaped text, by their index in the face.
+    indexed: HashMap<GlyphIndexKey, Glyph>,
+    /// the shaped lines of text.
+    lines: ShapeCache,
+    /// the frame each glyph in an atlas was last used in, rarely used glyphs are evicted when the atlases are full.
+    glyph_use: HashMap<Slot, u64>,
+    /// the current frame.
+    frame: u64,
+    /// glyphs kept through an eviction are being loaded again, they do not evict other glyphs.
+    evicting: bool,
+}
+
+/// where a glyph is in the atlases, characters drawn as the missing glyph share its place.
+type Slot = (u32, u32, u32);
+
+fn slot(glyph: &Glyph) -> Slot {
+    (glyph.atlas, glyph.uv_x.to_bits(), glyph.uv_y.to_bits())
+}
+
+/// a glyph by the map of the cache it is kept in.
+#[derive(Debug, Clone)]
+enum Cached {
+    Char(GlyphKey),
+    Indexed(GlyphIndexKey),
+    Decoration(Decoration),
+    Missing,
 }
 
 // What happens when a character and size is requested that doesn't exist.
 #[derive(Debug, Clone)]
 pub enum CacheMissProto {
     ErrorOnMiss,
-    //    RasterizeChar,
+    /// rasterize the character the first time it is requested.
+    RasterizeChar,
     //    Custom(Fn(GlyphKey) -> Glyph)
 }
 //
@@ -277,16 +192,25 @@ where
     pub fn new(
         mut rasterizer: T,
         font: config::Font,
-        dpi: f32,
         proto: CacheMissProto,
     ) -> Result<Self> {
         let font_size = font.size;
+        let styles = font.styles;
+
+        rasterizer.set_rendering(font.antialias, font.hinting);
+
         let font = rasterizer
             .get_font(font.font)
-            .map_err(|e| Error::FontError(e))?;
+            .map_err(Error::FontError)?;
+
+        for styled in styles {
+            rasterizer
+                .set_style_font(font, styled)
+                .map_err(Error::FontError)?;
+        }
         let metrics = rasterizer
             .get_metrics(font, font_size)
-            .map_err(|e| Error::FontError(e))?;
+            .map_err(Error::FontError)?;
 
         Ok(Self {
             glyphs: HashMap::new(),
@@ -295,6 +219,13 @@ where
             font_size,
             metrics,
             proto,
+            missing: None,
+            decorations: HashMap::new(),
+            indexed: HashMap::new(),
+            lines: ShapeCache::new(),
+            glyph_use: HashMap::new(),
+            frame: 0,
+            evicting: false,
         })
     }
 
@@ -303,15 +234,33 @@ where
     }
 
     pub fn get(&self, ch: u32) -> Result<&Glyph> {
+        self.get_styled(ch, Style::Normal)
+    }
+
+    pub fn get_styled(&self, ch: u32, style: Style) -> Result<&Glyph> {
         let glyph = GlyphKey {
             ch,
             font: self.font,
             size: self.font_size,
+            style,
         };
 
         self.request(&glyph)
     }
 
+    /// a glyph of shaped text.
+    pub fn get_indexed(&self, glyph: GlyphIndexKey) -> Result<&Glyph> {
+        match self.indexed.get(&glyph) {
+            Some(g) => Ok(g),
+            None => Err(Error::CacheMissGlyph(glyph)),
+        }
+    }
+
+    /// the bar of a decoration, if it has been loaded.
+    pub fn get_decoration(&self, decoration: Decoration) -> Option<&Glyph> {
+        self.decorations.get(&decoration)
+    }
+
     pub fn request(&self, glyph: &GlyphKey) -> Result<&Glyph> {
         match self.glyphs.get(glyph) {
             Some(g) => Ok(g),
@@ -319,42 +268,309 @@ where
         }
     }
 
-    pub fn load_glyph<F>(&mut self, glyph: GlyphKey, loader: &mut F)
+    /// gets the glyph of a character, handling a miss according to the cache miss protocol.
+    /// With RasterizeChar a character the font does not have is drawn as the missing glyph.
+    pub fn get_or_load<F>(&mut self, ch: u32, style: Style, loader: &mut F) -> Result<Glyph>
     where
         F: GlyphLoader,
     {
-        let rasterizer = &mut self.rasterizer;
+        let glyph = GlyphKey {
+            ch,
+            font: self.font,
+            size: self.font_size,
+            style,
+        };
 
-        self.glyphs.entry(glyph.clone()).or_insert_with(|| {
-            let size = glyph.size.clone();
-            let rglyph = rasterizer.load_glyph(glyph, size).unwrap();
+        if let Some(g) = self.glyphs.get(&glyph).cloned() {
+            self.touch(&g);
+            return Ok(g);
+        }
 
-            loader.load_glyph(&rglyph).unwrap()
-        });
+        match self.proto {
+            CacheMissProto::ErrorOnMiss => Err(Error::CacheMissChar(glyph)),
+            CacheMissProto::RasterizeChar => self.load_glyph(glyph, loader),
+        }
     }
 
-    pub fn load_glyphs<F>(&mut self, loader: &mut F)
+    /// rasterizes the glyph and adds it to the cache.
+    pub fn load_glyph<F>(&mut self, glyph: GlyphKey, loader: &mut F) -> Result<Glyph>
     where
         F: GlyphLoader,
     {
-        for c in 33..=126 {
-            let g = font::GlyphKey {
-                ch: c as u32,
-                font: self.font,
-                size: self.font_size,
+        if let Some(g) = self.glyphs.get(&glyph).cloned() {
+            self.touch(&g);
+            return Ok(g);
+        }
+
+        let size = glyph.size;
+        let loaded = match self.rasterizer.load_glyph(glyph.clone(), size) {
+            Ok(rglyph) => self.insert(&rglyph, loader)?,
+            // the font does not have this character.
+            Err(font::Error::InvalidGlyph) => self.missing_glyph(loader)?,
+            Err(e) => return Err(Error::FontError(e)),
+        };
+
+        // missing characters are cached as well so they are only looked up once.
+        self.glyphs.insert(glyph, loaded);
+
+        Ok(loaded)
+    }
+
+    /// gets a glyph of shaped text, rasterizing it the first time it is requested.
+    pub fn get_or_load_indexed<F>(&mut self, glyph: GlyphIndexKey, loader: &mut F) -> Result<Glyph>
+    where
+        F: GlyphLoader,
+    {
+        if let Some(g) = self.indexed.get(&glyph).cloned() {
+            self.touch(&g);
+            return Ok(g);
+        }
+
+        if let CacheMissProto::ErrorOnMiss = self.proto {
+            return Err(Error::CacheMissGlyph(glyph));
+        }
+
+        let loaded = match self.rasterizer.load_glyph_index(glyph, self.font_size) {
+            Ok(rglyph) => self.insert(&rglyph, loader)?,
+            Err(font::Error::InvalidGlyph) => self.missing_glyph(loader)?,
+            Err(e) => return Err(Error::FontError(e)),
+        };
+
+        self.indexed.insert(glyph, loaded);
+
+        Ok(loaded)
+    }
+
+    /// shapes a line of text onto the cell grid, lines are only shaped the first time they are seen.
+    pub fn shape_line(&mut self, line: &str, style: Style, tab_width: u32) -> Result<Rc<ShapedLine>> {
+        if let Some(shaped) = self.lines.get(line, style) {
+            return Ok(shaped);
+        }
+
+        let mut glyphs = Vec::new();
+        for (offset, run) in shaping::runs(line) {
+            let shaped = self
+                .rasterizer
+                .shape(self.font, style, run, self.font_size)
+                .map_err(Error::FontError)?;
+
+            glyphs.extend(shaped.into_iter().map(|mut g| {
+                g.cluster += offset;
+                g
+            }));
+        }
+
+        Ok(self.lines.insert(line, style, shaping::place(line, &glyphs, tab_width)))
+    }
+
+    /// the glyph used for characters the font does not have. (A box, sometimes called tofu)
+    pub fn missing_glyph<F>(&mut self, loader: &mut F) -> Result<Glyph>
+    where
+        F: GlyphLoader,
+    {
+        if let Some(glyph) = self.missing {
+            self.touch(&glyph);
+            return Ok(glyph);
+        }
+
+        let rglyph = self
+            .rasterizer
+            .load_missing_glyph(self.font, self.font_size)
+            .map_err(Error::FontError)?;
+
+        let glyph = self.insert(&rglyph, loader)?;
+        self.missing = Some(glyph);
+
+        Ok(glyph)
+    }
+
+    /// rasterizes the bar of a decoration the first time it is requested.
+    pub fn load_decoration<F>(&mut self, decoration: Decoration, loader: &mut F) -> Result<Glyph>
+    where
+        F: GlyphLoader,
+    {
+        if let Some(glyph) = self.decorations.get(&decoration).cloned() {
+            self.touch(&glyph);
+            return Ok(glyph);
+        }
+
+        let rglyph = self
+            .rasterizer
+            .load_decoration(self.font, self.font_size, decoration)
+            .map_err(Error::FontError)?;
+
+        let glyph = self.insert(&rglyph, loader)?;
+        self.decorations.insert(decoration, glyph);
+
+        Ok(glyph)
+    }
+
+    /// starts a new frame, glyphs that have not been used since an earlier frame can be evicted.
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    fn touch(&mut self, glyph: &Glyph) {
+        // glyphs with nothing to draw are not in the atlas, so they do not keep it in use.
+        if glyph.width == 0.0 || glyph.height == 0.0 {
+            return;
+        }
+        self.glyph_use.insert(slot(glyph), self.frame);
+    }
+
+    /// adds the glyph to an atlas. When every atlas is full the rarely used glyphs of the least
+    /// recently used atlas are evicted. A frame that uses more glyphs than fit in the limit adds
+    /// atlases past it instead of dropping glyphs it draws, those atlases are kept afterwards.
+    fn insert<F>(&mut self, rglyph: &RasterizedGlyph, loader: &mut F) -> Result<Glyph>
+    where
+        F: GlyphLoader,
+    {
+        let glyph = loop {
+            match loader.load_glyph(rglyph) {
+                Err(Error::AtlasFull) if !self.evicting => match self.least_recently_used() {
+                    Some(atlas) => self.evict(atlas, loader),
+                    None => {
+                        loader.grow()?;
+                        break loader.load_glyph(rglyph)?;
+                    }
+                },
+                result => break result?,
+            }
+        };
+
+        self.touch(&glyph);
+        Ok(glyph)
+    }
+
+    /// the atlas whose most recently used glyph is the oldest, atlases used by the current frame are not evicted.
+    fn least_recently_used(&self) -> Option<u32> {
+        let mut atlases: HashMap<u32, u64> = HashMap::new();
+        for (&(atlas, _, _), &frame) in &self.glyph_use {
+            let newest = atlases.entry(atlas).or_insert(frame);
+            *newest = (*newest).max(frame);
+        }
+
+        atlases
+            .into_iter()
+            .filter(|(_, frame)| *frame < self.frame)
+            .min_by_key(|(_, frame)| *frame)
+            .map(|(atlas, _)| atlas)
+    }
+
+    /// evicts the rarely used glyphs of the atlas. It is emptied and the more recently used half of
+    /// its glyphs are loaded again, the others are rasterized again when they are next used.
+    /// None of them are used by the current frame so moving them does not change what is drawn.
+    fn evict<F>(&mut self, atlas: u32, loader: &mut F)
+    where
+        F: GlyphLoader,
+    {
+        let mut slots: Vec<(Slot, u64)> = self
+            .glyph_use
+            .iter()
+            .filter(|(slot, _)| slot.0 == atlas)
+            .map(|(slot, frame)| (*slot, *frame))
+            .collect();
+        slots.sort_by_key(|(_, frame)| std::cmp::Reverse(*frame));
+        slots.truncate(slots.len() / 2);
+        let recent: HashMap<Slot, u64> = slots.into_iter().collect();
+
+        let used = |g: &Glyph| {
+            if g.width == 0.0 || g.height == 0.0 {
+                return None;
+            }
+            recent.get(&slot(g)).copied()
+        };
+        let mut reload = Vec::new();
+        reload.extend(self.missing.iter().filter_map(|g| used(g).map(|f| (Cached::Missing, f))));
+        reload.extend(self.glyphs.iter().filter_map(|(k, g)| used(g).map(|f| (Cached::Char(k.clone()), f))));
+        reload.extend(self.indexed.iter().filter_map(|(k, g)| used(g).map(|f| (Cached::Indexed(*k), f))));
+        reload.extend(self.decorations.iter().filter_map(|(k, g)| used(g).map(|f| (Cached::Decoration(*k), f))));
+
+        loader.evict(atlas);
+
+        let keep = |g: &Glyph| g.atlas != atlas || g.width == 0.0 || g.height == 0.0;
+        self.glyphs.retain(|_, g| keep(g));
+        self.indexed.retain(|_, g| keep(g));
+        self.decorations.retain(|_, g| keep(g));
+        if self.missing.is_some_and(|g| !keep(&g)) {
+            self.missing = None;
+        }
+        self.glyph_use.retain(|slot, _| slot.0 != atlas);
+
+        // a glyph that no longer fits is dropped like the rarely used ones.
+        self.evicting = true;
+        for (glyph, frame) in reload {
+            let loaded = match glyph {
+                Cached::Char(key) => self.load_glyph(key, loader),
+                Cached::Indexed(key) => self.get_or_load_indexed(key, loader),
+                Cached::Decoration(decoration) => self.load_decoration(decoration, loader),
+                Cached::Missing => self.missing_glyph(loader),
             };
-            self.load_glyph(g, loader);
+
+            // the glyphs keep the frame they were last used in, they are not used by this one.
+            if let Ok(glyph) = loaded {
+                self.glyph_use.insert(slot(&glyph), frame);
+            }
         }
+        self.evicting = false;
+    }
 
-        for c in 161..=256 {
+    /// preloads the printable ascii and latin-1 characters.
+    pub fn load_glyphs<F>(&mut self, loader: &mut F) -> Result<()>
+    where
+        F: GlyphLoader,
+    {
+        for c in (33..=126).chain(161..=255) {
             let g = font::GlyphKey {
                 ch: c as u32,
                 font: self.font,
                 size: self.font_size,
+                style: Style::Normal,
             };
-
-            self.load_glyph(g, loader);
+            self.load_glyph(g, loader)?;
         }
+
+        Ok(())
+    }
+
+    /// changes the size of the font. Every glyph is dropped and the atlases are cleared
+    /// through the loader, then the common characters are loaded again at the new size.
+    pub fn set_font_size<F>(&mut self, font_size: FontSize, loader: &mut F) -> Result<()>
+    where
+        F: GlyphLoader,
+    {
+        self.font_size = font_size;
+        self.rebuild(loader)
+    }
+
+    /// changes the scale of the display the glyphs are drawn on, rebuilding every glyph.
+    pub fn set_dpi_factor<F>(&mut self, dpi_factor: f32, loader: &mut F) -> Result<()>
+    where
+        F: GlyphLoader,
+    {
+        self.rasterizer.set_dpi_factor(dpi_factor);
+        self.rebuild(loader)
+    }
+
+    fn rebuild<F>(&mut self, loader: &mut F) -> Result<()>
+    where
+        F: GlyphLoader,
+    {
+        self.metrics = self
+            .rasterizer
+            .get_metrics(self.font, self.font_size)
+            .map_err(Error::FontError)?;
+
+        self.glyphs.clear();
+        self.missing = None;
+        self.decorations.clear();
+        self.indexed.clear();
+        self.glyph_use.clear();
+        // glyph positions are in pixels so the lines are shaped again at the new size.
+        self.lines.clear();
+        loader.clear();
+
+        self.load_glyphs(loader)
     }
 
     pub fn font(&self) -> FontKey {
@@ -368,48 +584,195 @@ where
 
 pub struct LoadApi<'a> {
     atlas: &'a mut Vec<Atlas>,
-    last: usize,
+    /// draws the glyphs into the atlas textures.
+    backend: &'a mut dyn Backend,
+    /// the size of new atlases.
+    size: Size,
+    /// the most atlases made before glyphs have to be evicted.
+    limit: usize,
 }
 
 impl<'a> LoadApi<'a> {
-    pub fn new(atlas: &'a mut Vec<Atlas>) -> Self {
-        Self { atlas, last: 0 }
+    pub fn new(atlas: &'a mut Vec<Atlas>, backend: &'a mut dyn Backend, size: Size, limit: usize) -> Self {
+        Self {
+            atlas,
+            backend,
+            size,
+            limit,
+        }
     }
 }
 
-fn load_glyph(
-    atlas: &mut Vec<Atlas>,
-    last: &mut usize,
-    glyph: &font::RasterizedGlyph,
-) -> Result<super::Glyph> {
-    match atlas[*last].insert(glyph) {
-        Ok(glyph) => Ok(glyph),
-        Err(Error::AtlasFull) => {
-            let size = atlas[*last].size;
-            *last += 1;
-            if *last == atlas.len() {
-                let t = Atlas::new(size)?;
-                atlas.push(t);
-                load_glyph(atlas, last, glyph)
-            } else {
-                panic!("LoadApi failed to manage last atlas");
+impl<'a> GlyphLoader for LoadApi<'a> {
+    fn load_glyph(&mut self, glyph: &font::RasterizedGlyph) -> Result<super::Glyph> {
+        // any atlas with room is used so space freed by evicting an atlas is filled again.
+        for atlas in self.atlas.iter_mut() {
+            match atlas.insert(glyph, self.backend) {
+                Err(Error::AtlasFull) => continue,
+                result => return result,
             }
         }
-        Err(e) => Err(e),
+
+        if self.atlas.len() >= self.limit {
+            return Err(Error::AtlasFull);
+        }
+
+        self.grow()?;
+        match self.atlas.last_mut() {
+            Some(atlas) => atlas.insert(glyph, self.backend),
+            None => Err(Error::AtlasFull),
+        }
     }
-}
 
-impl<'a> GlyphLoader for LoadApi<'a> {
-    fn load_glyph(&mut self, glyph: &font::RasterizedGlyph) -> Result<super::Glyph> {
-        load_glyph(self.atlas, &mut self.last, glyph)
+    fn evict(&mut self, atlas: u32) {
+        if let Some(atlas) = self.atlas.iter_mut().find(|a| a.texture_id == atlas) {
+            atlas.reset(self.backend);
+        }
+    }
+
+    fn grow(&mut self) -> Result<()> {
+        self.atlas.push(Atlas::new(self.size, self.backend)?);
+        Ok(())
     }
 
     fn clear(&mut self) {
         for atlas in self.atlas.iter_mut() {
-            atlas.clear();
+            atlas.clear(self.backend);
         }
 
         self.atlas.clear();
-        self.last = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::backend::CpuBackend;
+    use font::{FontDesc, Metrics, ShapedGlyph};
+
+    /// the size of the glyphs, with the padding four of them fill an atlas.
+    const GLYPH: f32 = 7.0;
+
+    /// draws every character as a solid square.
+    struct Squares;
+
+    impl Squares {
+        fn square(glyph: u32) -> RasterizedGlyph {
+            RasterizedGlyph {
+                glyph,
+                width: GLYPH,
+                height: GLYPH,
+                top: GLYPH,
+                left: 0.0,
+                bearing_x: 0.0,
+                bearing_y: 0.0,
+                advance_x: GLYPH,
+                advance_y: 0.0,
+                bitmap: vec![255; (GLYPH * GLYPH * 3.0) as usize],
+            }
+        }
+    }
+
+    impl Rasterizer for Squares {
+        fn new(_: f32) -> font::Result<Self> {
+            Ok(Squares)
+        }
+
+        fn set_dpi_factor(&mut self, _: f32) {}
+
+        fn set_rendering(&mut self, _: font::Antialias, _: font::Hinting) {}
+
+        fn load_glyph(&mut self, glyph: GlyphKey, _: FontSize) -> font::Result<RasterizedGlyph> {
+            Ok(Self::square(glyph.ch))
+        }
+
+        fn load_glyph_index(&mut self, glyph: GlyphIndexKey, _: FontSize) -> font::Result<RasterizedGlyph> {
+            Ok(Self::square(glyph.index))
+        }
+
+        fn shape(&mut self, _: FontKey, _: Style, _: &str, _: FontSize) -> font::Result<Vec<ShapedGlyph>> {
+            Ok(Vec::new())
+        }
+
+        fn load_missing_glyph(&mut self, _: FontKey, _: FontSize) -> font::Result<RasterizedGlyph> {
+            Ok(Self::square(0))
+        }
+
+        fn load_decoration(&mut self, _: FontKey, _: FontSize, _: Decoration) -> font::Result<RasterizedGlyph> {
+            Ok(Self::square(0))
+        }
+
+        fn get_metrics(&self, _: FontKey, _: FontSize) -> font::Result<Metrics> {
+            Ok(Metrics {
+                average_advance: GLYPH,
+                line_height: GLYPH,
+                descent: 0.0,
+                underline_position: 0.0,
+                underline_thickness: 1.0,
+            })
+        }
+
+        fn get_font(&mut self, _: FontDesc) -> font::Result<FontKey> {
+            Ok(FontKey { token: 0 })
+        }
+
+        fn set_style_font(&mut self, _: FontKey, _: FontDesc) -> font::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn cache() -> GlyphCache<Squares> {
+        GlyphCache::new(Squares, config::Config::default().font, CacheMissProto::RasterizeChar).unwrap()
+    }
+
+    fn load(cache: &mut GlyphCache<Squares>, loader: &mut LoadApi, text: &str) {
+        for ch in text.chars() {
+            cache.get_or_load(ch as u32, Style::Normal, loader).unwrap();
+        }
+    }
+
+    #[test]
+    fn rarely_used_glyphs_are_evicted_when_the_atlases_are_full() {
+        let mut backend = CpuBackend::new();
+        let mut atlases = Vec::new();
+        let mut cache = cache();
+
+        {
+            let mut loader = LoadApi::new(&mut atlases, &mut backend, Size::new(16.0, 16.0), 1);
+            load(&mut cache, &mut loader, "abcd");
+            cache.next_frame();
+            load(&mut cache, &mut loader, "cd");
+            cache.next_frame();
+            load(&mut cache, &mut loader, "e");
+        }
+
+        assert_eq!(atlases.len(), 1);
+        for ch in "cde".chars() {
+            assert!(cache.get(ch as u32).is_ok(), "{} was evicted", ch);
+        }
+        for ch in "ab".chars() {
+            assert!(cache.get(ch as u32).is_err(), "{} was kept", ch);
+        }
+
+        // the corner the fourth glyph was drawn in is cleared, three glyphs are left.
+        let texture = backend.texture_data(atlases[0].texture_id).unwrap();
+        assert_eq!(texture[(8 * 16 + 8) * 3], 0);
+    }
+
+    #[test]
+    fn a_frame_needing_more_glyphs_than_fit_goes_past_the_limit() {
+        let mut backend = CpuBackend::new();
+        let mut atlases = Vec::new();
+        let mut cache = cache();
+
+        {
+            let mut loader = LoadApi::new(&mut atlases, &mut backend, Size::new(16.0, 16.0), 1);
+            load(&mut cache, &mut loader, "abcde");
+        }
+
+        assert_eq!(atlases.len(), 2);
+        for ch in "abcde".chars() {
+            assert!(cache.get(ch as u32).is_ok(), "{} was evicted", ch);
+        }
     }
 }
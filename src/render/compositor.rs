@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use crate::pane::{PaneID, Separator};
+use crate::size;
+
+use super::backend::Backend;
+use super::font::Rasterizer;
+use super::{Damage, FrameSnapshot, GlyphCache, PaneFrame, PaneSnapshot, Renderer, Result};
+
+/// the width of the lines between panes in physical pixels.
+const SEPARATOR_WIDTH: f32 = 2.0;
+const SEPARATOR_COLOR: [f32; 4] = [80f32 / 255f32, 80f32 / 255f32, 80f32 / 255f32, 1.0];
+/// the width of the border around the panes of the overlay.
+const BORDER_WIDTH: f32 = 2.0;
+const BORDER_COLOR: [f32; 4] = [140f32 / 255f32, 140f32 / 255f32, 140f32 / 255f32, 1.0];
+
+/// A rectangle of the window on whole pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    /// rounds the edges of the rectangle to whole pixels. The edges are rounded rather than the
+    /// size so panes that share an edge still meet without a gap or an overlap.
+    pub fn snapped(x: f32, y: f32, width: f32, height: f32) -> Self {
+        let (left, top) = (x.round(), y.round());
+        let (right, bottom) = ((x + width).round(), (y + height).round());
+
+        Self {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+
+    /// the rectangle the separator is drawn in, centered on the line.
+    pub fn separator(separator: &Separator) -> Self {
+        let offset = SEPARATOR_WIDTH / 2.0;
+
+        if separator.vertical {
+            Self::snapped(separator.loc.x - offset, separator.loc.y, SEPARATOR_WIDTH, separator.length)
+        } else {
+            Self::snapped(separator.loc.x, separator.loc.y - offset, separator.length, SEPARATOR_WIDTH)
+        }
+    }
+
+    pub fn size(&self) -> size::Size<f32> {
+        size::Size::new(self.width, self.height)
+    }
+
+    /// the four sides of a border of the width around the outside of the rectangle.
+    pub fn border(&self, width: f32) -> [Rect; 4] {
+        let (left, top) = (self.x - width, self.y - width);
+        let outer_width = self.width + width * 2.0;
+
+        [
+            Rect::snapped(left, top, outer_width, width),
+            Rect::snapped(left, self.y + self.height, outer_width, width),
+            Rect::snapped(left, self.y, width, self.height),
+            Rect::snapped(self.x + self.width, self.y, width, self.height),
+        ]
+    }
+}
+
+/// Keeps the frame of every pane and puts them together on the window. A frame is drawn into
+/// only where its pane is damaged, reallocated when its pane is resized and freed when its
+/// pane is closed.
+pub struct Compositor {
+    frames: HashMap<PaneID, PaneFrame>,
+    /// the size of the window the last frame was drawn at.
+    window_size: Option<size::Size<f32>>,
+}
+
+impl Compositor {
+    pub fn new() -> Self {
+        Self {
+            frames: HashMap::new(),
+            window_size: None,
+        }
+    }
+
+    pub fn frame(&self, id: PaneID) -> Option<&PaneFrame> {
+        self.frames.get(&id)
+    }
+
+    /// the window changed size since the last frame was drawn.
+    pub fn resized(&self, window_size: size::Size<f32>) -> bool {
+        match self.window_size {
+            Some(size) => size.x != window_size.x || size.y != window_size.y,
+            None => true,
+        }
+    }
+
+    /// draws the damaged panes into their frames and puts the frames and the separators
+    /// between them on the window, with the overlay on top.
+    pub fn draw<B: Backend, R: Rasterizer>(
+        &mut self,
+        renderer: &mut Renderer<B>,
+        cache: &mut GlyphCache<R>,
+        snapshot: &FrameSnapshot,
+    ) -> Result<()> {
+        self.release_closed(renderer, snapshot);
+
+        let rects = self.draw_panes(renderer, cache, &snapshot.panes)?;
+        let overlay = self.draw_panes(renderer, cache, &snapshot.overlay)?;
+
+        renderer.begin_window(snapshot.window_size);
+        self.present(renderer, &rects);
+
+        for separator in snapshot.separators.iter() {
+            let rect = Rect::separator(separator);
+            renderer.fill_rect(rect.x, rect.y, rect.width, rect.height, SEPARATOR_COLOR);
+        }
+
+        for (_, rect) in overlay.iter() {
+            for side in rect.border(BORDER_WIDTH).iter() {
+                renderer.fill_rect(side.x, side.y, side.width, side.height, BORDER_COLOR);
+            }
+        }
+        self.present(renderer, &overlay);
+
+        self.window_size = Some(snapshot.window_size);
+
+        Ok(())
+    }
+
+    /// draws the damaged panes into their frames, returning where the frames go on the window.
+    fn draw_panes<B: Backend, R: Rasterizer>(
+        &mut self,
+        renderer: &mut Renderer<B>,
+        cache: &mut GlyphCache<R>,
+        panes: &[PaneSnapshot],
+    ) -> Result<Vec<(PaneID, Rect)>> {
+        let mut rects = Vec::with_capacity(panes.len());
+
+        for pane in panes.iter() {
+            let rect = Rect::snapped(pane.x, pane.y, pane.size.x, pane.size.y);
+
+            // a pane the window was shrunk past has nothing to show.
+            if rect.width < 1.0 || rect.height < 1.0 {
+                self.release_pane(renderer, pane.id);
+                continue;
+            }
+
+            rects.push((pane.id, rect));
+
+            let frame = self.frames.entry(pane.id).or_insert_with(|| PaneFrame::new(rect.size()));
+            frame.size = rect.size();
+
+            // a new frame has nothing in it yet.
+            let allocated = renderer.begin_pane(frame)?;
+            let damage = if allocated { Damage::full() } else { pane.damage.clone() };
+
+            if !damage.is_empty() {
+                renderer.draw_pane(cache, &pane.content(), &damage)?;
+            }
+        }
+
+        Ok(rects)
+    }
+
+    fn present<B: Backend>(&self, renderer: &mut Renderer<B>, rects: &[(PaneID, Rect)]) {
+        for (id, rect) in rects {
+            if let Some(frame) = self.frames.get(id) {
+                renderer.present_pane(frame, rect.x, rect.y);
+            }
+        }
+    }
+
+    /// frees the frames of every pane.
+    pub fn release<B: Backend>(&mut self, renderer: &mut Renderer<B>) {
+        for (_, mut frame) in self.frames.drain() {
+            renderer.release_pane(&mut frame);
+        }
+    }
+
+    /// frees the frames of the panes that are not in the snapshot.
+    fn release_closed<B: Backend>(&mut self, renderer: &mut Renderer<B>, snapshot: &FrameSnapshot) {
+        let closed: Vec<PaneID> = self
+            .frames
+            .keys()
+            .filter(|id| !snapshot.panes.iter().chain(snapshot.overlay.iter()).any(|pane| pane.id == **id))
+            .cloned()
+            .collect();
+
+        for id in closed {
+            self.release_pane(renderer, id);
+        }
+    }
+
+    fn release_pane<B: Backend>(&mut self, renderer: &mut Renderer<B>, id: PaneID) {
+        if let Some(mut frame) = self.frames.remove(&id) {
+            renderer.release_pane(&mut frame);
+        }
+    }
+}
+
+impl Default for Compositor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn snapped_neighbours_meet() {
+        let left = Rect::snapped(0.0, 0.0, 100.4, 50.0);
+        let right = Rect::snapped(100.4, 0.0, 100.4, 50.0);
+
+        assert_eq!(left.x + left.width, right.x);
+        assert_eq!(right.x + right.width, 201.0);
+    }
+
+    #[test]
+    fn separators_are_centered_on_the_border() {
+        let separator = Separator {
+            loc: glm::vec2(100.0, 0.0),
+            length: 50.0,
+            vertical: true,
+        };
+
+        let rect = Rect::separator(&separator);
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (99.0, 0.0, 2.0, 50.0));
+    }
+
+    #[test]
+    fn borders_surround_the_rectangle() {
+        let rect = Rect::snapped(10.0, 10.0, 20.0, 20.0);
+        let sides: Vec<_> = rect.border(2.0).iter().map(|r| (r.x, r.y, r.width, r.height)).collect();
+
+        assert_eq!(
+            sides,
+            vec![(8.0, 8.0, 24.0, 2.0), (8.0, 30.0, 24.0, 2.0), (8.0, 10.0, 2.0, 20.0), (30.0, 10.0, 2.0, 20.0)]
+        );
+    }
+}
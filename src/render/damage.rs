@@ -0,0 +1,109 @@
+use std::collections::BTreeSet;
+use std::mem;
+use std::ops::Range;
+
+/// The rows of a pane that changed since it was last drawn.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Damage {
+    /// every row changed, such as after scrolling or resizing.
+    full: bool,
+    rows: BTreeSet<u32>,
+}
+
+impl Damage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// damage covering the entire pane.
+    pub fn full() -> Self {
+        Self {
+            full: true,
+            rows: BTreeSet::new(),
+        }
+    }
+
+    pub fn add_row(&mut self, row: u32) {
+        if !self.full {
+            self.rows.insert(row);
+        }
+    }
+
+    pub fn add_rows(&mut self, rows: Range<u32>) {
+        for row in rows {
+            self.add_row(row);
+        }
+    }
+
+    pub fn add_all(&mut self) {
+        self.full = true;
+        self.rows.clear();
+    }
+
+    /// adds the rows damaged by the other damage.
+    pub fn extend(&mut self, other: &Damage) {
+        if other.full {
+            self.add_all();
+        } else {
+            for row in other.rows.iter() {
+                self.add_row(*row);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.full && self.rows.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    pub fn contains(&self, row: u32) -> bool {
+        self.full || self.rows.contains(&row)
+    }
+
+    /// the damaged rows of a partially damaged pane.
+    pub fn rows(&self) -> impl Iterator<Item = u32> + '_ {
+        self.rows.iter().cloned()
+    }
+
+    /// returns the damage, leaving nothing damaged.
+    pub fn take(&mut self) -> Damage {
+        mem::replace(self, Damage::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rows_are_tracked_until_taken() {
+        let mut damage = Damage::new();
+        assert!(damage.is_empty());
+
+        damage.add_row(3);
+        damage.add_rows(5..7);
+
+        assert!(damage.contains(3) && damage.contains(6));
+        assert!(!damage.contains(4) && !damage.contains(7));
+
+        let taken = damage.take();
+        assert_eq!(taken.rows().collect::<Vec<u32>>(), vec![3, 5, 6]);
+        assert!(damage.is_empty());
+    }
+
+    #[test]
+    fn full_damage_covers_every_row() {
+        let mut damage = Damage::new();
+        damage.add_row(1);
+        damage.extend(&Damage::full());
+
+        assert!(damage.is_full());
+        assert!(damage.contains(100));
+
+        damage.add_row(2);
+        assert_eq!(damage.rows().count(), 0);
+    }
+}
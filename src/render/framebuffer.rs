@@ -1,5 +1,4 @@
 use gl;
-use gl::types::*;
 
 use crate::size;
 
@@ -61,6 +60,10 @@ impl FrameBuffer {
         })
     }
 
+    pub fn size(&self) -> FrameSize {
+        self.size
+    }
+
     pub fn clear(&self) {
         unsafe {
             gl::DeleteFramebuffers(1, &self.fbo);
@@ -0,0 +1,336 @@
+//! Screenshot tests of the editor drawn with the cpu backend, compared against the images
+//! in tests/golden. Missing images are written by the test, set REM_BLESS_GOLDEN to
+//! rewrite all of them after an intended change to how the editor looks.
+
+use image::RgbaImage;
+
+use std::env;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::editor::App;
+use crate::font::{FontDesc, FreeTypeRasterizer, Style};
+use crate::pane::{CellSize, Cells, CursorMode, PaneID, Position, Separator};
+use crate::size::Size;
+
+use super::backend::CpuBackend;
+use super::{Compositor, CursorStyle, Damage, FrameSnapshot, GlyphCache, PaneContent, PaneFrame, PaneSnapshot, Renderer, TextStyle};
+
+/// how far a channel can be from the golden image, the rasterizer can differ slightly between versions.
+const CHANNEL_TOLERANCE: i32 = 8;
+/// the fraction of pixels that can be outside of the tolerance.
+const PIXEL_TOLERANCE: f32 = 0.01;
+
+const SOURCE: [&str; 3] = ["fn main() {", "\tprintln!(\"hello -> world\");", "}"];
+
+struct Harness {
+    renderer: Renderer<CpuBackend>,
+    cache: GlyphCache<FreeTypeRasterizer>,
+    cell_size: CellSize,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let mut config = Config::default();
+        config.font.font = FontDesc {
+            style: Style::Normal,
+            name: "Droid Sans Mono".to_string(),
+            path: Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("dev/DroidSansMono.ttf")),
+            fallback: Vec::new(),
+        };
+
+        let mut renderer = Renderer::with_backend(CpuBackend::new(), &config).unwrap();
+        let cache = renderer.prepare_font(1.0, &config).unwrap();
+        let cell_size = App::compute_cell_size(cache.metrics(), &config.font, 1.0);
+
+        renderer.prepare(cell_size, config.font.gamma);
+
+        Self {
+            renderer,
+            cache,
+            cell_size,
+        }
+    }
+
+    fn pane_size(&self, cells: Cells) -> Size<f32> {
+        Size::new(cells.x as f32 * self.cell_size.x, cells.y as f32 * self.cell_size.y)
+    }
+
+    /// draws the content into the frame of a new pane.
+    fn draw(&mut self, content: &PaneContent) -> PaneFrame {
+        let mut frame = PaneFrame::new(self.pane_size(content.cells));
+        self.renderer.begin_pane(&mut frame).unwrap();
+        self.renderer.draw_pane(&mut self.cache, content, &Damage::full()).unwrap();
+
+        frame
+    }
+
+    fn image(&self, frame: &PaneFrame) -> RgbaImage {
+        let target = frame.target.unwrap();
+        self.renderer.backend().target_image(target).unwrap().clone()
+    }
+}
+
+fn content<'a>(lines: &'a [String], cursor: Position) -> PaneContent<'a> {
+    PaneContent {
+        lines,
+        first_line: 0,
+        view_offset: 0,
+        cursor,
+        other_cursors: &[],
+        cursor_style: CursorStyle::Solid(CursorMode::Box),
+        cells: Cells::new(32, 4),
+        selection: None,
+        highlights: &[],
+        tab_width: 4,
+        style: TextStyle::default(),
+    }
+}
+
+/// a pane of the window at (x, 0) showing the lines.
+fn snapshot(id: PaneID, x: f32, size: Size<f32>, lines: &[String], cursor: Position) -> PaneSnapshot {
+    PaneSnapshot {
+        id,
+        x,
+        y: 0.0,
+        size,
+        lines: lines.to_vec(),
+        first_line: 0,
+        view_offset: 0,
+        cursor,
+        other_cursors: Vec::new(),
+        cursor_style: CursorStyle::Solid(CursorMode::Box),
+        cells: Cells::new(16, 4),
+        selection: None,
+        highlights: Vec::new(),
+        tab_width: 4,
+        style: TextStyle::default(),
+        damage: Damage::full(),
+    }
+}
+
+fn source() -> Vec<String> {
+    SOURCE.iter().map(|line| line.to_string()).collect()
+}
+
+/// compares the image with its golden image, the golden image is only written when it is blessed
+/// with REM_BLESS_GOLDEN.
+fn assert_golden(name: &str, image: &RgbaImage) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let path = dir.join(format!("{}.png", name));
+
+    if env::var_os("REM_BLESS_GOLDEN").is_some() {
+        std::fs::create_dir_all(&dir).unwrap();
+        image.save(&path).unwrap();
+        return;
+    }
+
+    if !path.exists() {
+        let actual = env::temp_dir().join(format!("{}.actual.png", name));
+        image.save(&actual).unwrap();
+        panic!("{} has no golden image, drawn to {:?}; run with REM_BLESS_GOLDEN=1 to add it", name, actual);
+    }
+
+    let golden = image::open(&path).unwrap().to_rgba();
+    assert_eq!(golden.dimensions(), image.dimensions(), "{} changed size", name);
+
+    let different = golden
+        .pixels()
+        .zip(image.pixels())
+        .filter(|(a, b)| (0..4).any(|c| (a[c] as i32 - b[c] as i32).abs() > CHANNEL_TOLERANCE))
+        .count();
+
+    let allowed = (golden.width() * golden.height()) as f32 * PIXEL_TOLERANCE;
+    if different as f32 > allowed {
+        let actual = env::temp_dir().join(format!("{}.actual.png", name));
+        image.save(&actual).unwrap();
+        panic!("{} differs from its golden image in {} pixels, drawn to {:?}", name, different, actual);
+    }
+}
+
+#[test]
+fn text() {
+    let mut harness = Harness::new();
+    let lines = source();
+
+    let frame = harness.draw(&content(&lines, Position::new(0, 3)));
+    assert_golden("text", &harness.image(&frame));
+}
+
+#[test]
+fn cursor_on_a_ligature() {
+    let mut harness = Harness::new();
+    let lines = source();
+
+    // the cursor is on the '-' of "->", which is drawn a character at a time.
+    let frame = harness.draw(&content(&lines, Position::new(20, 1)));
+    assert_golden("cursor", &harness.image(&frame));
+}
+
+#[test]
+fn cursor_modes() {
+    let mut harness = Harness::new();
+    let lines: Vec<String> = vec!["let x = 1;".to_string()];
+    let size = harness.pane_size(Cells::new(16, 4));
+
+    // a pane for each way the cursor is drawn, side by side.
+    let styles = [
+        CursorStyle::Solid(CursorMode::Line),
+        CursorStyle::Solid(CursorMode::Underline),
+        CursorStyle::Hollow,
+        CursorStyle::Hidden,
+    ];
+
+    let panes = styles
+        .iter()
+        .enumerate()
+        .map(|(i, style)| {
+            let mut pane = snapshot(PaneID::next(), i as f32 * size.x, size, &lines, Position::new(4, 0));
+            pane.cursor_style = *style;
+            pane
+        })
+        .collect();
+
+    let frame = FrameSnapshot {
+        window_size: Size::new(size.x * styles.len() as f32, size.y),
+        panes,
+        separators: Vec::new(),
+        overlay: Vec::new(),
+    };
+
+    let mut compositor = Compositor::new();
+    compositor.draw(&mut harness.renderer, &mut harness.cache, &frame).unwrap();
+
+    assert_golden("cursor_modes", harness.renderer.backend().window_image());
+}
+
+#[test]
+fn selection_across_lines() {
+    let mut harness = Harness::new();
+    let lines = source();
+
+    let mut content = content(&lines, Position::new(1, 2));
+    content.selection = Some((Position::new(3, 0), Position::new(9, 1)));
+
+    let frame = harness.draw(&content);
+    assert_golden("selection", &harness.image(&frame));
+}
+
+#[test]
+fn split_panes() {
+    let mut harness = Harness::new();
+    let left_lines = source();
+    let right_lines: Vec<String> = vec!["// the other pane".to_string(), "let x = 1;".to_string()];
+
+    // the border between the panes is not on a whole pixel.
+    let height = harness.pane_size(Cells::new(16, 4)).y;
+    let width = harness.pane_size(Cells::new(16, 4)).x + 0.5;
+    let pane_size = Size::new(width, height);
+
+    let frame = FrameSnapshot {
+        window_size: Size::new((width * 2.0).round(), height),
+        panes: vec![
+            snapshot(PaneID::next(), 0.0, pane_size, &left_lines, Position::new(0, 0)),
+            snapshot(PaneID::next(), width, pane_size, &right_lines, Position::new(4, 1)),
+        ],
+        separators: vec![Separator {
+            loc: glm::vec2(width, 0.0),
+            length: height,
+            vertical: true,
+        }],
+        overlay: Vec::new(),
+    };
+
+    let mut compositor = Compositor::new();
+    compositor.draw(&mut harness.renderer, &mut harness.cache, &frame).unwrap();
+
+    assert_golden("split", harness.renderer.backend().window_image());
+}
+
+#[test]
+fn finder_overlay() {
+    let mut harness = Harness::new();
+    let lines = source();
+    let size = harness.pane_size(Cells::new(16, 4));
+    let overlay_size = harness.pane_size(Cells::new(8, 3));
+
+    // the matched characters of the results are highlighted and the selected one is marked.
+    let results: Vec<String> = vec!["> mn".to_string(), "main.rs".to_string(), "mod.rs".to_string()];
+    let mut list = snapshot(PaneID::next(), 8.0, overlay_size, &results, Position::new(4, 0));
+    list.y = 8.0;
+    list.cells = Cells::new(8, 3);
+    list.cursor_style = CursorStyle::Solid(CursorMode::Line);
+    list.highlights = vec![Position::new(0, 1), Position::new(2, 1), Position::new(0, 2)];
+    list.selection = Some((Position::new(0, 1), Position::new(0, 2)));
+
+    let mut preview = list.clone();
+    preview.id = PaneID::next();
+    preview.x = 8.0 + overlay_size.x;
+    preview.lines = lines.clone();
+    preview.cursor_style = CursorStyle::Hidden;
+    preview.highlights = Vec::new();
+    preview.selection = None;
+
+    let frame = FrameSnapshot {
+        window_size: Size::new(size.x * 2.0, size.y),
+        panes: vec![snapshot(PaneID::next(), 0.0, Size::new(size.x * 2.0, size.y), &lines, Position::new(0, 0))],
+        separators: Vec::new(),
+        overlay: vec![list, preview],
+    };
+
+    let mut compositor = Compositor::new();
+    compositor.draw(&mut harness.renderer, &mut harness.cache, &frame).unwrap();
+
+    assert_golden("finder", harness.renderer.backend().window_image());
+}
+
+#[test]
+fn closed_panes_free_their_frames() {
+    let mut harness = Harness::new();
+    let lines = source();
+    let size = harness.pane_size(Cells::new(16, 4));
+
+    let (left, right) = (PaneID::next(), PaneID::next());
+    let mut frame = FrameSnapshot {
+        window_size: Size::new(size.x * 2.0, size.y),
+        panes: vec![
+            snapshot(left, 0.0, size, &lines, Position::new(0, 0)),
+            snapshot(right, size.x, size, &lines, Position::new(0, 0)),
+        ],
+        separators: Vec::new(),
+        overlay: Vec::new(),
+    };
+
+    let mut compositor = Compositor::new();
+    compositor.draw(&mut harness.renderer, &mut harness.cache, &frame).unwrap();
+    let target = compositor.frame(right).unwrap().target.unwrap();
+
+    // the left pane takes the whole window after the right one is closed.
+    frame.panes.pop();
+    frame.panes[0].size = Size::new(size.x * 2.0, size.y);
+    compositor.draw(&mut harness.renderer, &mut harness.cache, &frame).unwrap();
+
+    assert!(compositor.frame(right).is_none());
+    assert!(harness.renderer.backend().target_image(target).is_none());
+    assert_eq!(compositor.frame(left).unwrap().size.x, size.x * 2.0);
+}
+
+#[test]
+fn damaged_rows_match_a_full_redraw() {
+    let mut harness = Harness::new();
+    let mut lines = source();
+    let mut frame = harness.draw(&content(&lines, Position::new(0, 0)));
+
+    // an edit on the second line that moves the cursor there from the first.
+    lines[1] = "\tprintln!(\"edited\");".to_string();
+    let edited = content(&lines, Position::new(6, 1));
+
+    let mut damage = Damage::new();
+    damage.add_rows(0..2);
+
+    assert!(!harness.renderer.begin_pane(&mut frame).unwrap());
+    harness.renderer.draw_pane(&mut harness.cache, &edited, &damage).unwrap();
+
+    let full = harness.draw(&edited);
+    assert!(harness.image(&frame).into_raw() == harness.image(&full).into_raw());
+}
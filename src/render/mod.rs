@@ -1,15 +1,28 @@
+pub mod backend;
 pub mod caches;
+pub mod compositor;
+pub mod damage;
 pub mod framebuffer;
+// the renderer is named for what it is, not for the module around it.
+#[allow(clippy::module_inception)]
 pub mod render;
+pub mod shaping;
+pub mod thread;
 
-#[macro_use]
 pub use render::*;
 pub use caches::*;
+pub use compositor::Compositor;
+pub use damage::Damage;
+pub use thread::{FrameSnapshot, PaneSnapshot, RenderThread};
 
+pub(crate) mod packer;
 pub(crate) mod shader;
 
+#[cfg(test)]
+mod golden;
+
 use crate::font;
-use crate::font::GlyphKey;
+use crate::font::{GlyphIndexKey, GlyphKey};
 
 #[derive(Debug, Clone)]
 pub enum Error {
@@ -18,11 +31,32 @@ pub enum Error {
     RenderError(String),
     AtlasError(String),
     CacheMissChar(GlyphKey),
+    CacheMissGlyph(GlyphIndexKey),
     FrameBufferError(framebuffer::Error),
+    /// the render thread stopped, after it failed to start or panicked.
+    ThreadExited,
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
+/// How text is drawn, requested by the highlighting of a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextStyle {
+    pub style: font::Style,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl TextStyle {
+    /// the lines drawn across the text.
+    pub fn decorations(&self) -> impl Iterator<Item = font::Decoration> {
+        let underline = if self.underline { Some(font::Decoration::Underline) } else { None };
+        let strike = if self.strikethrough { Some(font::Decoration::Strikethrough) } else { None };
+
+        underline.into_iter().chain(strike)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Glyph {
     /// Character this glyph represents
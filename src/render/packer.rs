@@ -0,0 +1,184 @@
+/// A segment of the skyline, the top edge of the rectangles packed below it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// Packs rectangles into an area by keeping the outline of the packed rectangles (the skyline)
+/// and placing each rectangle where its top edge is lowest. Unlike packing rows, short glyphs
+/// next to tall ones do not waste the space above them.
+#[derive(Debug, Clone)]
+pub struct Skyline {
+    width: u32,
+    height: u32,
+    /// the skyline from left to right, it always covers the entire width.
+    segments: Vec<Segment>,
+}
+
+impl Skyline {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            segments: vec![Segment { x: 0, y: 0, width }],
+        }
+    }
+
+    /// finds a place for the rectangle and reserves it, None if it does not fit.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        // the segment the rectangle starts at, and the bottom and top of the rectangle there.
+        let mut best: Option<(usize, u32, u32)> = None;
+
+        for i in 0..self.segments.len() {
+            if let Some(y) = self.fits(i, width, height) {
+                let better = match best {
+                    Some((_, _, top)) => y + height < top,
+                    None => true,
+                };
+
+                if better {
+                    best = Some((i, y, y + height));
+                }
+            }
+        }
+
+        let (i, y, _) = best?;
+        let x = self.segments[i].x;
+        self.add(i, x, y, width, height);
+
+        Some((x, y))
+    }
+
+    /// the height of the tallest column of packed rectangles, nothing has been packed above it.
+    pub fn used_height(&self) -> u32 {
+        self.segments.iter().map(|s| s.y).max().unwrap_or(0)
+    }
+
+    /// empties the area.
+    pub fn clear(&mut self) {
+        self.segments = vec![Segment {
+            x: 0,
+            y: 0,
+            width: self.width,
+        }];
+    }
+
+    /// the lowest the rectangle can be placed starting at the segment, if it fits there.
+    fn fits(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.segments[index].x;
+        if x + width > self.width {
+            return None;
+        }
+
+        // the rectangle rests on the highest segment it covers.
+        let mut y = 0;
+        let mut remaining = width as i64;
+        for segment in &self.segments[index..] {
+            if remaining <= 0 {
+                break;
+            }
+
+            y = y.max(segment.y);
+            if y + height > self.height {
+                return None;
+            }
+
+            remaining -= segment.width as i64;
+        }
+
+        Some(y)
+    }
+
+    /// raises the skyline over the new rectangle.
+    fn add(&mut self, index: usize, x: u32, y: u32, width: u32, height: u32) {
+        self.segments.insert(
+            index,
+            Segment {
+                x,
+                y: y + height,
+                width,
+            },
+        );
+
+        // shorten or remove the segments now under the rectangle.
+        let right = x + width;
+        let next = index + 1;
+        while next < self.segments.len() {
+            let segment = &mut self.segments[next];
+            if segment.x >= right {
+                break;
+            }
+
+            let covered = right - segment.x;
+            if segment.width <= covered {
+                self.segments.remove(next);
+            } else {
+                segment.x += covered;
+                segment.width -= covered;
+                break;
+            }
+        }
+
+        // join neighbours of the same height.
+        let mut i = 0;
+        while i + 1 < self.segments.len() {
+            if self.segments[i].y == self.segments[i + 1].y {
+                self.segments[i].width += self.segments[i + 1].width;
+                self.segments.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn packs_beside_and_above() {
+        let mut skyline = Skyline::new(10, 10);
+
+        assert_eq!(skyline.pack(4, 6), Some((0, 0)));
+        assert_eq!(skyline.pack(6, 2), Some((4, 0)));
+        // the lowest place is on top of the short rectangle.
+        assert_eq!(skyline.pack(6, 3), Some((4, 2)));
+        assert_eq!(skyline.pack(10, 4), Some((0, 6)));
+    }
+
+    #[test]
+    fn full_area_rejects_rectangles() {
+        let mut skyline = Skyline::new(8, 8);
+
+        assert_eq!(skyline.pack(8, 8), Some((0, 0)));
+        assert_eq!(skyline.pack(1, 1), None);
+        assert_eq!(skyline.pack(9, 1), None);
+
+        skyline.clear();
+        assert_eq!(skyline.pack(1, 1), Some((0, 0)));
+    }
+
+    #[test]
+    fn used_height_is_the_tallest_column() {
+        let mut skyline = Skyline::new(10, 10);
+        assert_eq!(skyline.used_height(), 0);
+
+        skyline.pack(4, 6);
+        skyline.pack(6, 2);
+        assert_eq!(skyline.used_height(), 6);
+
+        skyline.clear();
+        assert_eq!(skyline.used_height(), 0);
+    }
+
+    #[test]
+    fn fills_the_area_with_cells() {
+        let mut skyline = Skyline::new(16, 16);
+        let packed = (0..).take_while(|_| skyline.pack(4, 4).is_some()).count();
+
+        assert_eq!(packed, 16);
+    }
+}
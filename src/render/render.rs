@@ -1,22 +1,13 @@
-use gl;
-use gl::types::*;
-use nalgebra_glm as glm;
-
-use std::collections::HashMap;
-use std::ffi::CString;
-use std::mem;
-use std::ptr;
-
 use crate::config;
-use crate::pane::{self, Cursor, Pane, PaneID};
+use crate::editor_core;
+use crate::pane::{self, Cells, CellSize, Cursor, PaneID, Position, Selection};
 use crate::size;
-use crate::window::Window;
 
+use super::backend::{Backend, GlBackend};
 use super::caches::Atlas;
-use super::font::{self, FontDesc, FontKey, FontSize, GlyphKey, RasterizedGlyph, Rasterizer};
-use super::framebuffer::{self, FrameBuffer};
-use super::shader::{RectShader, TextShader};
-use super::{Error, Glyph, GlyphCache, Result};
+use super::shaping::Cluster;
+use super::font::{self, Rasterizer};
+use super::{Damage, Error, Glyph, GlyphCache, Result, TextStyle};
 
 // static mut CURRENT_TIME: std::time::Instant = std::time::Instant::new(0, 0); // = std::time::Instant::now();
 
@@ -24,6 +15,8 @@ use super::{Error, Glyph, GlyphCache, Result};
 macro_rules! glCheck {
     () => {{
         if cfg!(debug_assertions) {
+            // the check is also made inside of unsafe blocks.
+            #[allow(unused_unsafe)]
             let err = unsafe { gl::GetError() };
             // println!("Error {:?}", err);
             if err != gl::NO_ERROR {
@@ -50,41 +43,82 @@ pub struct PaneState {
     pub pane: PaneID,
     /// The cursor of this pane
     pub cursor: Cursor,
+    /// the other cursors of the pane as (column, line) positions of its document, every edit
+    /// is made at each of them too.
+    pub cursors: Vec<Position>,
     /// is the pane active.
     pub active: bool,
-    /// the pane needs to be redrawn.
-    pub dirty: bool,
-    /// the cached rendered pane
-    pub frame: FrameBuffer,
+    /// the rows of the pane that need to be redrawn.
+    pub damage: Damage,
+    /// the size of the pane in physical pixels.
+    pub size: size::Size<f32>,
     /// the first line of the document that is visible.
     pub start_line: usize,
     /// the the column of the text that is left most.
     pub view_offset: usize,
+    /// the selected text of this pane, if any.
+    pub selection: Option<Selection>,
 }
 
 impl PaneState {
-    pub fn new(size: size::Size<f32>, id: PaneID) -> ::std::result::Result<Self, framebuffer::Error> {
-
-        Ok(Self {
+    pub fn new(size: size::Size<f32>, id: PaneID, mode: pane::CursorMode) -> Self {
+        Self {
             pane: id,
-            cursor: Cursor::new(id, pane::CursorMode::Box),
+            cursor: Cursor::new(id, mode),
+            cursors: Vec::new(),
             active: false,
-            dirty: true,
-            frame: FrameBuffer::with_size(size)?,
+            damage: Damage::full(),
+            size,
             start_line: 0,
             view_offset: 0,
-        })
+            selection: None,
+        }
     }
 
-    fn viewed_at(mut self, start_line: usize, view_offset: usize) -> Self {
-        self.start_line = start_line;
-        self.view_offset = view_offset;
+    /// the pane is redrawn at its new size if it changed size.
+    pub fn resize(&mut self, size: size::Size<f32>) {
+        if self.size.x == size.x && self.size.y == size.y {
+            return;
+        }
 
-        self
+        self.size = size;
+        self.damage.add_all();
     }
 }
 
-static BATCH_SIZE: usize = 1024;
+/// The image a pane is drawn into, kept between frames so panes that did not change are
+/// only copied onto the window.
+#[derive(Debug, Clone, Copy)]
+pub struct PaneFrame {
+    /// the target of the backend, allocated when the pane is first drawn.
+    pub target: Option<u32>,
+    /// the size of the pane in physical pixels.
+    pub size: size::Size<f32>,
+}
+
+impl PaneFrame {
+    pub fn new(size: size::Size<f32>) -> Self {
+        Self { target: None, size }
+    }
+}
+
+pub(crate) const BATCH_SIZE: usize = 1024;
+
+/// the color panes are cleared to.
+pub const PANE_BACKGROUND: [f32; 3] = [33f32 / 255f32, 33f32 / 255f32, 33f32 / 255f32];
+
+/// the color behind selected text.
+pub const SELECTION_BACKGROUND: [f32; 3] = [68f32 / 255f32, 84f32 / 255f32, 112f32 / 255f32];
+
+/// the color of the window where no pane is drawn.
+pub const WINDOW_BACKGROUND: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+const TEXT_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+/// the color of highlighted text, like the characters a fuzzy query matched.
+const HIGHLIGHT_COLOR: [f32; 3] = [229f32 / 255f32, 192f32 / 255f32, 123f32 / 255f32];
+/// a box cursor inverts the colors of the pane, the other cursors are drawn in the text color.
+const CURSOR_TEXT_COLOR: [f32; 3] = PANE_BACKGROUND;
+const CURSOR_BACKGROUND: [f32; 3] = TEXT_COLOR;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct InstanceData {
@@ -116,6 +150,60 @@ pub struct InstanceData {
     pub texture_id: i32,
 }
 
+impl InstanceData {
+    /// an instance drawing the glyph in the cell with the given text color, on a baseline at
+    /// the bottom of the cell. The background is the color under the glyph, the glyph is blended against it.
+    pub fn glyph(x: f32, y: f32, glyph: &Glyph, color: [f32; 3], background: [f32; 3]) -> Self {
+        Self {
+            x,
+            y,
+
+            // text metrics offsets for the character
+            width: glyph.width,
+            height: glyph.height,
+            offset_x: glyph.bearing_x, // - 1.0,
+            offset_y: glyph.bearing_y,
+
+            // texture coordinates
+            uv_x: glyph.uv_x,
+            uv_y: glyph.uv_y,
+            uv_dx: glyph.uv_dx,
+            uv_dy: glyph.uv_dy,
+
+            tr: color[0],
+            tg: color[1],
+            tb: color[2],
+
+            br: background[0],
+            bg: background[1],
+            bb: background[2],
+            ba: 1.0,
+
+            texture_id: glyph.atlas as i32,
+        }
+    }
+
+    /// an instance filling the cell with a color.
+    pub fn background(x: f32, y: f32, color: [f32; 3]) -> Self {
+        Self {
+            x,
+            y,
+            br: color[0],
+            bg: color[1],
+            bb: color[2],
+            ba: 1.0,
+            ..Self::default()
+        }
+    }
+
+    /// moves the glyph within its cell by a number of pixels, y moves it up.
+    pub fn offset(mut self, x: f32, y: f32) -> Self {
+        self.offset_x += x;
+        self.offset_y += y;
+        self
+    }
+}
+
 pub struct Batch {
     texture_id: u32,
     instances: Vec<InstanceData>,
@@ -137,6 +225,11 @@ impl Batch {
         self.instances.is_empty()
     }
 
+    /// the instance can be drawn with the batch, a batch draws from a single atlas.
+    pub fn accepts(&self, data: &InstanceData) -> bool {
+        self.is_empty() || (!self.is_full() && self.texture_id == data.texture_id as u32)
+    }
+
     pub fn push(&mut self, data: InstanceData) -> bool {
         if self.is_empty() {
             self.texture_id = data.texture_id as u32
@@ -149,277 +242,235 @@ impl Batch {
         self.instances.len() == BATCH_SIZE
     }
 
-    pub fn push_background_pass_data(&mut self, x: f32, y: f32, r: f32, g: f32, b: f32, a: f32) {
-        let mut data = InstanceData::default();
-        data.x = x;
-        data.y = y;
-        data.br = r;
-        data.bg = g;
-        data.bb = b;
-        data.ba = a;
-
-        self.instances.push(data);
-    }
-
     pub fn clear(&mut self) {
         self.instances.clear()
     }
 }
 
-pub struct Renderer {
-    // vertex buffer object
-    vbo: u32,
-    // index buffer object
-    ibo: u32,
-    // vertex attribute object
-    vao: u32,
-    // the shader for rendering text
-    text_shader: TextShader,
-    // the shader for rendering text
-    rect_shader: RectShader,
-    // all of the vertex atlases
-    atlases: Vec<Atlas>,
+impl Default for Batch {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Renderer {
-    pub fn new(config: &config::Config) -> Result<Self> {
-        let mut vao = 0;
-
-        let mut bufs = [0, 0];
-        // bufs[0] is vbo
-        // bufs[1] is ibo
-
-        let index_data = [0, 1, 2, 0, 2, 3];
-
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao);
-            // generate both with a single call
-            gl::GenBuffers(2, bufs.as_mut_ptr());
-
-            gl::BindVertexArray(vao);
-
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, bufs[1]);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                (mem::size_of::<u32>() * index_data.len()) as isize,
-                index_data.as_ptr() as *const _,
-                gl::STATIC_DRAW,
-            );
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, bufs[0]);
-
-            let size = mem::size_of::<InstanceData>() as usize;
-
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (size * BATCH_SIZE) as isize,
-                ptr::null(),
-                gl::STATIC_DRAW,
-            );
-
-            let float_size = mem::size_of::<f32>();
-
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(0, 2 as i32, gl::FLOAT, gl::FALSE, size as i32, ptr::null());
-            gl::VertexAttribDivisor(0, 1);
-            glCheck!();
-
-            let mut stride = 2;
-
-            gl::EnableVertexAttribArray(1);
-            gl::VertexAttribPointer(
-                1,
-                4 as i32,
-                gl::FLOAT,
-                gl::FALSE,
-                size as i32,
-                (stride * float_size) as *const _,
-            );
-            gl::VertexAttribDivisor(1, 1);
-            glCheck!();
-
-            stride += 4;
-
-            // color attribute
-            gl::EnableVertexAttribArray(2);
-            gl::VertexAttribPointer(
-                2,
-                4 as i32,
-                gl::FLOAT,
-                gl::FALSE,
-                size as i32,
-                (stride * float_size) as *const _,
-            );
-            gl::VertexAttribDivisor(2, 1);
-            glCheck!();
-
-            stride += 4;
-
-            gl::EnableVertexAttribArray(3);
-            gl::VertexAttribPointer(
-                3,
-                3 as i32,
-                gl::FLOAT,
-                gl::FALSE,
-                size as i32,
-                (stride * float_size) as *const _,
-            );
-            gl::VertexAttribDivisor(3, 1);
-            glCheck!();
-
-            stride += 3;
-
-            gl::EnableVertexAttribArray(4);
-            gl::VertexAttribPointer(
-                4,
-                4 as i32,
-                gl::FLOAT,
-                gl::FALSE,
-                size as i32,
-                (stride * float_size) as *const _,
-            );
-            gl::VertexAttribDivisor(4, 1);
-
-            glCheck!();
+
+/// How the cursor of a pane is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorStyle {
+    /// the shape of the cursor's mode, in the focused pane while the blink shows it.
+    Solid(pane::CursorMode),
+    /// the outline of a box, in the panes without focus.
+    Hollow,
+    /// blinked off.
+    Hidden,
+}
+
+/// What is drawn in an edit pane.
+#[derive(Debug, Clone)]
+pub struct PaneContent<'a> {
+    /// the visible lines of the document without their line endings.
+    pub lines: &'a [String],
+    /// the line of the document the first line is.
+    pub first_line: usize,
+    /// the cells left of the view offset are not shown.
+    pub view_offset: usize,
+    /// the cell of the pane the cursor is on.
+    pub cursor: Position,
+    /// the cells of the other cursors, drawn like the cursor.
+    pub other_cursors: &'a [Position],
+    pub cursor_style: CursorStyle,
+    /// the size of the pane in cells.
+    pub cells: Cells,
+    /// the selected range of the document as ordered (column, line) positions, the end is not selected.
+    pub selection: Option<(Position, Position)>,
+    /// the (column, line) positions of the document whose characters are drawn in the highlight color.
+    pub highlights: &'a [Position],
+    pub tab_width: u32,
+    pub style: TextStyle,
+}
+
+impl<'a> PaneContent<'a> {
+    /// the character of the line is in the selection.
+    fn is_selected(&self, line: usize, column: usize) -> bool {
+        match self.selection {
+            Some((start, end)) => {
+                let pos = (line, column);
+                pos >= (start.y as usize, start.x as usize) && pos < (end.y as usize, end.x as usize)
+            }
+            None => false,
         }
+    }
 
-        let text_shader = TextShader::new()?;
-        let rect_shader = RectShader::new()?;
+    /// the cells of the pane covered by the selection.
+    fn selected_cells(&self) -> Vec<Position> {
+        if self.selection.is_none() {
+            return Vec::new();
+        }
 
-        let atlas_size = size::Size::new(config.atlas.size, config.atlas.size);
-        let atlas = Atlas::new(atlas_size)?;
+        self.cells_where(|line, column| self.is_selected(line, column))
+    }
 
-        Ok(Self {
-            vao,
-            vbo: bufs[0],
-            ibo: bufs[1],
-            text_shader,
-            rect_shader,
-            atlases: vec![atlas],
+    /// the cells of the pane with highlighted characters.
+    fn highlighted_cells(&self) -> Vec<Position> {
+        if self.highlights.is_empty() {
+            return Vec::new();
+        }
+
+        self.cells_where(|line, column| {
+            self.highlights.iter().any(|pos| pos.y as usize == line && pos.x as usize == column)
         })
     }
 
-    pub fn prepare(&self) {
-        unsafe {
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC1_COLOR, gl::ONE_MINUS_SRC1_COLOR);
-            gl::Enable(gl::MULTISAMPLE);
-            gl::DepthMask(gl::FALSE);
-            gl::ClearColor(1.0, 1.0, 1.0, 1.0);
+    /// the visible cells of the characters at the (line, column) positions of the document the predicate is true for.
+    fn cells_where<F: Fn(usize, usize) -> bool>(&self, predicate: F) -> Vec<Position> {
+        let mut cells = Vec::new();
+
+        for (row, line) in self.lines.iter().enumerate().take(self.cells.y as usize) {
+            let mut cell = 0;
+
+            for (column, c) in line.chars().enumerate() {
+                let width = editor_core::char_width(c, self.tab_width);
+
+                if predicate(self.first_line + row, column) {
+                    let visible = (cell..cell + width)
+                        .filter(|x| *x as usize >= self.view_offset)
+                        .map(|x| x - self.view_offset as u32)
+                        .filter(|x| *x < self.cells.x);
+
+                    cells.extend(visible.map(|x| Position::new(x, row as u32)));
+                }
+
+                cell += width;
+            }
         }
+
+        cells
+    }
+
+    /// the cursor fills its cell, the other shapes leave the cell's colors alone.
+    fn is_box_cursor(&self) -> bool {
+        self.cursor_style == CursorStyle::Solid(pane::CursorMode::Box)
     }
 
-    pub fn text_shader(&self) -> &TextShader {
-        &self.text_shader
+    /// the cells of every cursor of the pane.
+    fn cursors(&self) -> impl Iterator<Item = Position> + '_ {
+        Some(self.cursor).into_iter().chain(self.other_cursors.iter().cloned())
     }
 
-    pub fn rect_shader(&self) -> &RectShader {
-        &self.rect_shader
+    fn is_cursor(&self, x: u32, y: u32) -> bool {
+        self.cursors().any(|cursor| cursor.x == x && cursor.y == y)
     }
 
-    pub fn push_atlas(&mut self, atlas: Atlas) -> u32 {
-        self.atlases.push(atlas);
-        (self.atlases.len() - 1) as u32
+    /// a box cursor is on one of the cells of the cluster.
+    fn box_cursor_in(&self, cluster: &Cluster, row: u32) -> bool {
+        let view_offset = self.view_offset as u32;
+        self.is_box_cursor() && self.cursors().any(|c| c.y == row && cluster.contains(c.x + view_offset))
     }
 
-    pub fn set_view_port(&self, width: f32, height: f32) {
-        self.set_view_port_at(width, height, 0.0, 0.0);
+    /// the rectangles a cursor is drawn with when it does not fill its cell, in pixels
+    /// from the top left of the pane.
+    fn cursor_rects(&self, cursor: Position, cell_size: CellSize, thickness: f32) -> Vec<[f32; 4]> {
+        let (x, y) = (cursor.x as f32 * cell_size.x, cursor.y as f32 * cell_size.y);
+        let (w, h) = (cell_size.x, cell_size.y);
+
+        match self.cursor_style {
+            CursorStyle::Solid(pane::CursorMode::Line) => vec![[x, y, thickness * 2.0, h]],
+            CursorStyle::Solid(pane::CursorMode::Underline) => {
+                vec![[x, y + h - thickness * 2.0, w, thickness * 2.0]]
+            }
+            CursorStyle::Hollow => vec![
+                [x, y, w, thickness],
+                [x, y + h - thickness, w, thickness],
+                [x, y, thickness, h],
+                [x + w - thickness, y, thickness, h],
+            ],
+            CursorStyle::Solid(pane::CursorMode::Box) | CursorStyle::Hidden => Vec::new(),
+        }
     }
 
-    pub fn set_view_port_at(&self, width: f32, height: f32, x: f32, y: f32) {
-        unsafe {
-            gl::Viewport(x as i32, y as i32, width as i32, height as i32);
+    /// the text and background colors of a cell of the pane.
+    fn colors(&self, marked: &MarkedCells, x: u32, y: u32) -> ([f32; 3], [f32; 3]) {
+        let has = |cells: &[Position]| cells.iter().any(|cell| cell.x == x && cell.y == y);
+
+        if self.is_box_cursor() && self.is_cursor(x, y) {
+            (CURSOR_TEXT_COLOR, CURSOR_BACKGROUND)
+        } else {
+            let text = if has(&marked.highlighted) { HIGHLIGHT_COLOR } else { TEXT_COLOR };
+            let background = if has(&marked.selected) { SELECTION_BACKGROUND } else { PANE_BACKGROUND };
+            (text, background)
         }
     }
+}
 
-    pub fn draw_batch(&self, batch: &Batch) -> Result<()> {
-        if !batch.is_empty() {
-            glCheck!();
-            self.text_shader.activate();
-            glCheck!();
-
-            self.text_shader
-                .set_font_atlas_texture(batch.texture_id as i32);
-            glCheck!();
-
-            unsafe {
-                gl::ActiveTexture(gl::TEXTURE0 + batch.texture_id);
-                glCheck!();
-                gl::BindTexture(gl::TEXTURE_2D, batch.texture_id);
-                glCheck!();
-
-                gl::BindVertexArray(self.vao);
-                glCheck!();
-
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ibo);
-                glCheck!();
-
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-                glCheck!();
-
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,
-                    (mem::size_of::<InstanceData>() * BATCH_SIZE) as isize,
-                    batch.instances.as_ptr() as *const _,
-                    gl::STREAM_DRAW,
-                );
-                glCheck!();
-
-                //let mut fbo = 0;
-                //gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut fbo);
-
-                self.text_shader.set_background_pass(0);
-                gl::DrawElementsInstanced(
-                    gl::TRIANGLES,
-                    6,
-                    gl::UNSIGNED_INT,
-                    ptr::null(),
-                    batch.instances.len() as i32,
-                );
-
-                // self.text_shader.set_background_pass(1);
-                // gl::DrawElementsInstanced(gl::TRIANGLES, 6, gl::UNSIGNED_INT, ptr::null(), batch.instances.len() as i32);
-
-                glCheck!();
-
-                gl::BindVertexArray(0);
-                gl::BindTexture(gl::TEXTURE_2D, 0);
-            }
+/// The cells of a pane that are not drawn in the default colors.
+struct MarkedCells {
+    selected: Vec<Position>,
+    highlighted: Vec<Position>,
+}
 
-            self.text_shader.deactivate();
+pub struct Renderer<B: Backend = GlBackend> {
+    // does the drawing
+    backend: B,
+    // all of the vertex atlases
+    atlases: Vec<Atlas>,
+    // the size of each atlas
+    atlas_size: size::Size<f32>,
+    // the most atlases before glyphs are evicted
+    atlas_limit: usize,
+    // the size of a cell in physical pixels
+    cell_size: CellSize,
+}
+
+impl Renderer<GlBackend> {
+    pub fn new(config: &config::Config) -> Result<Self> {
+        Self::with_backend(GlBackend::new()?, config)
+    }
+}
+
+impl<B: Backend> Renderer<B> {
+    pub fn with_backend(mut backend: B, config: &config::Config) -> Result<Self> {
+        let atlas_size = size::Size::new(config.atlas.size, config.atlas.size);
+        let atlas = Atlas::new(atlas_size, &mut backend)?;
+
+        Ok(Self {
+            backend,
+            atlases: vec![atlas],
+            atlas_size,
+            atlas_limit: config.atlas.limit,
+            cell_size: config.cell_size,
+        })
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// sets the size of a cell and the gamma text is blended with.
+    pub fn prepare(&mut self, cell_size: CellSize, gamma: f32) {
+        self.cell_size = cell_size;
+        self.backend.prepare(cell_size, gamma);
+    }
+
+    pub fn draw_batch(&mut self, batch: &Batch) -> Result<()> {
+        if !batch.is_empty() {
+            self.backend.draw_glyphs(batch.texture_id, &batch.instances);
         }
 
         Ok(())
     }
 
-    pub fn render_background_pass(&self, batch: &Batch) -> Result<()> {
-        self.text_shader.activate();
-
-        unsafe {
-            gl::BindVertexArray(self.vao);
-            glCheck!();
-
-            // gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ibo);
-            // glCheck!();
-            // gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-            // glCheck!();
-
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (mem::size_of::<InstanceData>() * BATCH_SIZE) as isize,
-                batch.instances.as_ptr() as *const _,
-                gl::STREAM_DRAW,
-            );
-            glCheck!();
-            gl::DrawElementsInstanced(
-                gl::TRIANGLES,
-                6,
-                gl::UNSIGNED_INT,
-                ptr::null(),
-                batch.instances.len() as i32,
-            );
+    /// adds the instance to the batch, drawing and clearing the batch once it is full
+    /// or when the instance is from a different atlas.
+    pub fn push_instance(&mut self, batch: &mut Batch, instance: InstanceData) -> Result<()> {
+        if !batch.accepts(&instance) {
+            self.draw_batch(batch)?;
+            batch.clear();
+        }
+
+        if batch.push(instance) {
+            self.draw_batch(batch)?;
+            batch.clear();
         }
-        self.text_shader.deactivate();
 
         Ok(())
     }
@@ -429,123 +480,228 @@ impl Renderer {
         dpi: f32,
         config: &config::Config,
     ) -> Result<super::GlyphCache<font::FreeTypeRasterizer>> {
-        let mut rasterizer = font::FreeTypeRasterizer::new(dpi).map_err(|e| Error::FontError(e))?;
+        let rasterizer = font::FreeTypeRasterizer::new(dpi).map_err(Error::FontError)?;
 
         let mut cache = GlyphCache::new(
             rasterizer,
             config.font.clone(),
-            dpi,
-            super::CacheMissProto::ErrorOnMiss,
+            super::CacheMissProto::RasterizeChar,
         )?;
 
-        let mut loader = super::LoadApi::new(&mut self.atlases);
-        cache.load_glyphs(&mut loader);
+        let mut loader = self.loader();
+        cache.load_glyphs(&mut loader)?;
 
         Ok(cache)
     }
 
-    /// maybe give this function a framebuffer object that is should clear.
-    /// This is to explicity giving the function a specific buffer to clear
-    /// not just the currently one bound.
-    pub fn clear_frame(&self, frame: Option<&FrameBuffer>) {
-        let mut saved_fbo = 0;
-        unsafe {
-            // this is so the state can be restored after this call.
-            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut saved_fbo);
+    /// the loader for adding glyphs to the atlases of this renderer.
+    pub fn loader(&mut self) -> super::LoadApi<'_> {
+        super::LoadApi::new(&mut self.atlases, &mut self.backend, self.atlas_size, self.atlas_limit)
+    }
+
+    /// binds the frame of the pane, allocating the frame if the pane has not been drawn at
+    /// its current size. Returns true when the frame was allocated and has to be drawn in full.
+    pub fn begin_pane(&mut self, frame: &mut PaneFrame) -> Result<bool> {
+        let size = frame.size;
+
+        let allocated = |frame| match self.backend.target_size(frame) {
+            Some(current) => current.x == size.x && current.y == size.y,
+            None => false,
+        };
+
+        let (target, allocated) = match frame.target {
+            Some(target) if allocated(target) => (target, false),
+            target => {
+                if let Some(target) = target {
+                    self.backend.delete_target(target);
+                }
+
+                let target = self.backend.create_target(size)?;
+                frame.target = Some(target);
+                (target, true)
+            }
+        };
+
+        self.backend.bind_target(target);
+
+        Ok(allocated)
+    }
+
+    /// draws the damaged rows of a pane into the bound frame, the other rows keep what was
+    /// drawn in earlier frames.
+    pub fn draw_pane<R: Rasterizer>(
+        &mut self,
+        cache: &mut GlyphCache<R>,
+        content: &PaneContent,
+        damage: &Damage,
+    ) -> Result<()> {
+        let style = content.style;
+
+        // the baseline is raised by the descent so glyphs stay in their row, which can then be
+        // redrawn without touching its neighbours.
+        let baseline = -cache.metrics().descent;
+
+        let [r, g, b] = PANE_BACKGROUND;
+        if damage.is_full() {
+            self.backend.clear([r, g, b, 1.0]);
+        } else {
+            // the cells do not always fill the pane, the partial cell at the end of a row is cleared too.
+            let width = (content.cells.x + 1) as f32 * self.cell_size.x;
+
+            for row in damage.rows() {
+                let y = row as f32 * self.cell_size.y;
+                self.backend.clear_rect(0.0, y, width, self.cell_size.y, [r, g, b, 1.0]);
+            }
         }
 
-        match frame {
-            Some(frame) => {
-                frame.bind_write();
+        // shape the lines and rasterize the glyphs that have not been seen before so every
+        // glyph is in the cache while drawing.
+        let mut shaped = Vec::with_capacity(content.lines.len());
+        cache.next_frame();
+        {
+            let mut loader = self.loader();
+            for (row, line) in content.lines.iter().enumerate() {
+                if !damage.contains(row as u32) {
+                    continue;
+                }
+
+                let line = cache.shape_line(line, style.style, content.tab_width)?;
+
+                for cluster in line.clusters.iter() {
+                    for glyph in cluster.glyphs.iter() {
+                        cache.get_or_load_indexed(glyph.key, &mut loader)?;
+                    }
+
+                    // a ligature under a box cursor is drawn a character at a time.
+                    let under_cursor = content.box_cursor_in(cluster, row as u32);
+
+                    if cluster.is_unshaped() || under_cursor {
+                        for (c, _) in cluster.chars.iter().filter(|(c, _)| !c.is_whitespace()) {
+                            cache.get_or_load(*c as u32, style.style, &mut loader)?;
+                        }
+                    }
+                }
+
+                shaped.push((row as u32, line));
+            }
+
+            for decoration in style.decorations() {
+                cache.load_decoration(decoration, &mut loader)?;
             }
-            _ => { /* clear currently active draw buffer*/ }
         }
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
-            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, saved_fbo as u32);
+
+        // the cursor and the selection are drawn behind the text.
+        let marked = MarkedCells {
+            selected: content.selected_cells(),
+            highlighted: content.highlighted_cells(),
+        };
+        let mut backgrounds: Vec<InstanceData> = marked
+            .selected
+            .iter()
+            .filter(|cell| damage.contains(cell.y))
+            .map(|cell| InstanceData::background(cell.x as f32, cell.y as f32, SELECTION_BACKGROUND))
+            .collect();
+
+        if content.is_box_cursor() {
+            let cursors = content.cursors().filter(|cursor| damage.contains(cursor.y));
+            backgrounds.extend(cursors.map(|c| InstanceData::background(c.x as f32, c.y as f32, CURSOR_BACKGROUND)));
         }
-    }
+        self.backend.draw_backgrounds(&backgrounds);
+
+        let mut batch = Batch::new();
+
+        // cells left of the view offset are skipped.
+        let view_offset = content.view_offset as u32;
 
-    /// draws the background of a pane.
-    /// I am passing in batch to reduce the number of allocations
-    pub fn draw_pane_background(&self, batch: &mut Batch, pane: &Pane, cursor: &Cursor) {
-        // temporary background color
-        const R: f32 = 33f32 / 255f32;
-        const G: f32 = 33f32 / 255f32;
-        const B: f32 = 33f32 / 255f32;
+        for (y, line) in shaped.iter() {
+            let y = *y;
 
-        let pane_size = pane.cells();
+            for cluster in line.clusters.iter() {
+                if cluster.cell < view_offset {
+                    continue;
+                }
 
-        self.text_shader.activate();
-        self.text_shader.set_background_pass(1);
-        self.text_shader.deactivate();
+                let x = cluster.cell - view_offset;
+                let under_cursor = content.box_cursor_in(cluster, y);
+
+                if cluster.is_unshaped() || under_cursor {
+                    // every visible character was loaded above, missing characters are drawn as the missing glyph.
+                    for (c, cell) in cluster.chars.iter().filter(|(c, _)| !c.is_whitespace()) {
+                        let glyph = cache.get_styled(*c as u32, style.style)?;
+                        let (text_color, background) = content.colors(&marked, cell - view_offset, y);
+
+                        let instance =
+                            InstanceData::glyph((cell - view_offset) as f32, y as f32, glyph, text_color, background)
+                                .offset(0.0, baseline);
+                        self.push_instance(&mut batch, instance)?;
+                    }
+                } else {
+                    let (text_color, background) = content.colors(&marked, x, y);
+
+                    for shaped_glyph in cluster.glyphs.iter() {
+                        let glyph = cache.get_indexed(shaped_glyph.key)?;
+
+                        let instance = InstanceData::glyph(x as f32, y as f32, glyph, text_color, background)
+                            .offset(shaped_glyph.x, baseline + shaped_glyph.y);
+                        self.push_instance(&mut batch, instance)?;
+                    }
+                }
 
-        for i in 0..=pane_size.x {
-            for j in 0..=pane_size.y {
-                batch.push_background_pass_data(i as f32, j as f32, R, G, B, 1.0);
+                // decorations are a cell wide so clusters get one per cell.
+                if cluster.chars.iter().all(|(c, _)| c.is_whitespace()) {
+                    continue;
+                }
 
-                if batch.is_full() {
-                    self.render_background_pass(&batch);
-                    batch.clear();
+                for decoration in style.decorations() {
+                    if let Some(bar) = cache.get_decoration(decoration) {
+                        for i in 0..cluster.cells {
+                            let (text_color, background) = content.colors(&marked, x + i, y);
+                            let instance = InstanceData::glyph((x + i) as f32, y as f32, bar, text_color, background)
+                                .offset(0.0, baseline);
+                            self.push_instance(&mut batch, instance)?;
+                        }
+                    }
                 }
             }
         }
 
-        // if it is full then draw it.
-        if batch.is_full() {
-            self.render_background_pass(&batch);
-            batch.clear();
+        self.draw_batch(&batch)?;
+
+        // the other cursor shapes are drawn over the text.
+        let thickness = cache.metrics().underline_thickness.round().max(1.0);
+        let [r, g, b] = CURSOR_BACKGROUND;
+
+        for cursor in content.cursors().filter(|cursor| damage.contains(cursor.y)) {
+            for [x, y, width, height] in content.cursor_rects(cursor, self.cell_size, thickness) {
+                self.backend.clear_rect(x, y, width, height, [r, g, b, 1.0]);
+            }
         }
 
-        // if duration.as_millis() >= CURSOR_TIME.as_millis() {
-        batch.push_background_pass_data(
-            cursor.pos().x as f32,
-            cursor.pos().y as f32,
-            1.0,
-            1.0,
-            1.0,
-            1.0,
-        );
-        // }
-
-        self.render_background_pass(&batch);
-        batch.clear();
-    }
-
-    /// assumes the frame buffer has been rendered to and ready to be drawn.
-    pub fn draw_rendered_pane(&self, window: &Window, pane: &Pane, state: &PaneState) {
-        let (w, h): (i32, i32) = if let Some(s) = window.get_inner_size() {
-            let s = s.to_physical(window.dpi_factor());
-            (s.width as i32, s.height as i32)
-        } else {
-            unreachable!();
-        };
+        Ok(())
+    }
+
+    /// binds the window, which has the given size in physical pixels, and clears it.
+    pub fn begin_window(&mut self, size: size::Size<f32>) {
+        self.backend.bind_window(size);
+        self.backend.clear(WINDOW_BACKGROUND);
+    }
+
+    /// frees the target of a pane that was closed.
+    pub fn release_pane(&mut self, frame: &mut PaneFrame) {
+        if let Some(target) = frame.target.take() {
+            self.backend.delete_target(target);
+        }
+    }
+
+    /// fills a rectangle of the bound image, in pixels from its top left.
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        self.backend.clear_rect(x, y, width, height, color);
+    }
 
-        // println!("{} {}", w, h);
-        let pane_size = pane.size();
-        // println!("{:?}", pane_size);
-        let pane_loc = pane.loc();
-
-        state.frame.bind_read();
-
-        unsafe {
-            // bind the window as the target draw.
-            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
-
-            gl::BlitFramebuffer(
-                pane_loc.x as i32,
-                pane_loc.y as i32,
-                w,
-                h,
-                0,
-                0,
-                pane_size.x as i32,
-                pane_size.y as i32,
-                gl::COLOR_BUFFER_BIT,
-                gl::LINEAR,
-            );
-            // reset the state.
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    /// copies the frame of a pane onto the window at its location.
+    pub fn present_pane(&mut self, frame: &PaneFrame, x: f32, y: f32) {
+        if let Some(target) = frame.target {
+            self.backend.present(target, x, y);
         }
     }
 }
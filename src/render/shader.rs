@@ -3,23 +3,16 @@ use nalgebra_glm as glm;
 use std::convert::AsRef;
 use std::ffi::CString;
 use std::fs;
-use std::path::PathBuf;
 use std::ptr;
 use std::str;
 
 use crate::pane::CellSize;
 
-use super::Atlas;
 use super::Result;
 
 pub fn load_file<P: AsRef<std::path::Path>>(filename: P) -> String {
-    let content = fs::read_to_string(&filename).expect(
-        format!(
-            "Failed to load file: '{}'",
-            filename.as_ref().to_str().unwrap()
-        )
-        .as_str(),
-    );
+    let content = fs::read_to_string(&filename)
+        .unwrap_or_else(|_| panic!("Failed to load file: '{}'", filename.as_ref().display()));
     content
 }
 
@@ -43,8 +36,8 @@ pub fn compile_shader(src: &str, ty: GLenum) -> GLuint {
             let mut len = 0;
             gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
 
-            let mut buf = Vec::with_capacity(len as usize);
-            buf.set_len((len as usize) - 1);
+            // the log is written with a trailing nul, which is not shown.
+            let mut buf = vec![0u8; len.max(1) as usize];
             gl::GetShaderInfoLog(
                 shader,
                 len,
@@ -55,9 +48,7 @@ pub fn compile_shader(src: &str, ty: GLenum) -> GLuint {
             panic!(
                 "{:?} {}",
                 ty,
-                str::from_utf8(&buf)
-                    .ok()
-                    .expect("ShaderInfoLog not valid utf8")
+                str::from_utf8(&buf[..buf.len() - 1]).expect("ShaderInfoLog not valid utf8")
             );
         }
     }
@@ -79,12 +70,11 @@ pub fn link_shader(vs: GLuint, fs: GLuint) -> GLuint {
         if status != (gl::TRUE as GLint) {
             let mut len = 0;
             gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
-            let mut buf = Vec::with_capacity(len as usize);
+            let mut buf = vec![0u8; len.max(1) as usize];
             //if len == 0 {
             //    println!("Status {} but len is 0", status);
             //    return program;
             //}
-            buf.set_len((len as usize) - 1);
             gl::GetProgramInfoLog(
                 program,
                 len,
@@ -94,20 +84,18 @@ pub fn link_shader(vs: GLuint, fs: GLuint) -> GLuint {
 
             panic!(
                 "{}",
-                str::from_utf8(&buf)
-                    .ok()
-                    .expect("LinkStatus not valid utf8")
+                str::from_utf8(&buf[..buf.len() - 1]).expect("LinkStatus not valid utf8")
             );
         }
         program
     }
 }
 
-static TEXT_VS_SOURCE: &'static str = "shaders/vs.glsl";
-static TEXT_FS_SOURCE: &'static str = "shaders/fs.glsl";
+static TEXT_VS_SOURCE: &str = "shaders/vs.glsl";
+static TEXT_FS_SOURCE: &str = "shaders/fs.glsl";
 
-static RECT_VS_SOURCE: &'static str = "shaders/rect.vs.glsl";
-static RECT_FS_SOURCE: &'static str = "shaders/rect.fs.glsl";
+static RECT_VS_SOURCE: &str = "shaders/rect.vs.glsl";
+static RECT_FS_SOURCE: &str = "shaders/rect.fs.glsl";
 
 pub struct TextShader {
     program: u32,
@@ -119,8 +107,12 @@ pub struct TextShader {
     cell_loc: i32,
     // the pass type.
     pass_loc: i32,
+    // gamma text is blended with
+    gamma_loc: i32,
 }
 
+// made with the backend, nothing draws plain rectangles with it yet.
+#[allow(dead_code)]
 pub struct RectShader {
     program: u32,
     // perspective location
@@ -160,6 +152,8 @@ impl TextShader {
             unsafe { gl::GetUniformLocation(program, CString::new("cell_size").unwrap().as_ptr()) };
         let pass_loc =
             unsafe { gl::GetUniformLocation(program, CString::new("bg_pass").unwrap().as_ptr()) };
+        let gamma_loc =
+            unsafe { gl::GetUniformLocation(program, CString::new("gamma").unwrap().as_ptr()) };
 
         Ok(Self {
             program,
@@ -167,6 +161,7 @@ impl TextShader {
             atlas_loc,
             cell_loc,
             pass_loc,
+            gamma_loc,
         })
     }
 
@@ -174,10 +169,6 @@ impl TextShader {
         unsafe { gl::UniformMatrix4fv(self.per_loc, 1, gl::FALSE, per.as_ptr()) };
     }
 
-    pub fn set_font_atlas(&self, atlas: &Atlas) {
-        self.set_font_atlas_texture(atlas.texture_id as i32);
-    }
-
     pub fn set_font_atlas_texture(&self, tex: i32) {
         unsafe { gl::Uniform1i(self.atlas_loc, tex) };
     }
@@ -192,6 +183,10 @@ impl TextShader {
         }
     }
 
+    pub fn set_gamma(&self, gamma: f32) {
+        unsafe { gl::Uniform1f(self.gamma_loc, gamma) };
+    }
+
     pub fn activate(&self) {
         unsafe { gl::UseProgram(self.program) };
     }
@@ -201,6 +196,7 @@ impl TextShader {
     }
 }
 
+#[allow(dead_code)]
 impl RectShader {
     pub fn new() -> Result<Self> {
         let vs_src = load_file(RECT_VS_SOURCE);
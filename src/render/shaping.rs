@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::font::{GlyphIndexKey, ShapedGlyph, Style};
+use crate::editor_core;
+
+/// the most lines kept in the cache before it is emptied.
+const MAX_LINES: usize = 4096;
+
+/// A glyph of a cluster, positioned in pixels from the left of the cluster's first cell.
+#[derive(Debug, Clone, Copy)]
+pub struct ClusterGlyph {
+    pub key: GlyphIndexKey,
+    pub x: f32,
+    /// moves the glyph up from the baseline.
+    pub y: f32,
+}
+
+/// Characters that are drawn together, such as a ligature or a letter and its marks.
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    /// the first cell of the cluster.
+    pub cell: u32,
+    /// the number of cells the characters take up, the cursor moves through each of them.
+    pub cells: u32,
+    /// the characters and their cells, used when the cluster is drawn a character at a time.
+    pub chars: Vec<(char, u32)>,
+    /// the shaped glyphs, empty when the cluster is whitespace or the font is missing a glyph.
+    pub glyphs: Vec<ClusterGlyph>,
+}
+
+impl Cluster {
+    pub fn contains(&self, cell: u32) -> bool {
+        cell >= self.cell && cell < self.cell + self.cells
+    }
+
+    /// the cluster can only be drawn a character at a time.
+    pub fn is_unshaped(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+}
+
+/// A line of a document shaped into clusters on the cell grid.
+#[derive(Debug, Clone, Default)]
+pub struct ShapedLine {
+    pub clusters: Vec<Cluster>,
+}
+
+/// The shaped lines of the documents, keyed by their text so edits do not need to invalidate it.
+#[derive(Debug, Clone, Default)]
+pub struct ShapeCache {
+    lines: HashMap<(String, Style), Rc<ShapedLine>>,
+}
+
+impl ShapeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, line: &str, style: Style) -> Option<Rc<ShapedLine>> {
+        self.lines.get(&(line.to_string(), style)).cloned()
+    }
+
+    pub fn insert(&mut self, line: &str, style: Style, shaped: ShapedLine) -> Rc<ShapedLine> {
+        if self.lines.len() >= MAX_LINES {
+            self.lines.clear();
+        }
+
+        let shaped = Rc::new(shaped);
+        self.lines.insert((line.to_string(), style), shaped.clone());
+        shaped
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+/// the runs of a line that are shaped separately and their byte offsets. Tabs are not shaped,
+/// they only move the following text to the next tab stop.
+pub fn runs(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut start = 0;
+
+    line.split('\t').filter_map(move |run| {
+        let offset = start;
+        start += run.len() + 1;
+
+        if run.is_empty() {
+            None
+        } else {
+            Some((offset, run))
+        }
+    })
+}
+
+/// places the shaped glyphs of a line on the cell grid. Each glyph is drawn from the first cell
+/// of its cluster so the cells of the characters stay where the cursor expects them, even for
+/// right to left text, which is shown in its logical order.
+pub fn place(line: &str, glyphs: &[ShapedGlyph], tab_width: u32) -> ShapedLine {
+    // the glyphs of each cluster and their positions within it.
+    let mut shaped: HashMap<usize, Vec<ClusterGlyph>> = HashMap::new();
+    let mut missing: HashMap<usize, bool> = HashMap::new();
+    let mut pen: HashMap<usize, f32> = HashMap::new();
+
+    for glyph in glyphs {
+        let x = pen.entry(glyph.cluster).or_insert(0.0);
+
+        shaped.entry(glyph.cluster).or_default().push(ClusterGlyph {
+            key: glyph.key,
+            x: *x + glyph.x_offset,
+            y: glyph.y_offset,
+        });
+        *x += glyph.x_advance;
+
+        *missing.entry(glyph.cluster).or_insert(false) |= glyph.key.index == 0;
+    }
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+    let mut cell = 0;
+
+    for (offset, c) in line.char_indices() {
+        let width = editor_core::char_width(c, tab_width);
+
+        // a character starts a cluster when glyphs were shaped from it, the rest are part of the cluster before them.
+        match clusters.last_mut() {
+            Some(cluster) if c != '\t' && !shaped.contains_key(&offset) && cluster.chars[0].0 != '\t' => {
+                cluster.cells += width;
+                cluster.chars.push((c, cell));
+            }
+            _ => {
+                let glyphs = match (shaped.remove(&offset), missing.get(&offset)) {
+                    (Some(glyphs), Some(false)) => glyphs,
+                    _ => Vec::new(),
+                };
+
+                clusters.push(Cluster {
+                    cell,
+                    cells: width,
+                    chars: vec![(c, cell)],
+                    glyphs,
+                });
+            }
+        }
+
+        cell += width;
+    }
+
+    // whitespace has nothing to draw.
+    for cluster in clusters.iter_mut() {
+        if cluster.chars.iter().all(|(c, _)| c.is_whitespace()) {
+            cluster.glyphs.clear();
+        }
+    }
+
+    ShapedLine { clusters }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::font::FontKey;
+
+    fn glyph(index: u32, cluster: usize, x_advance: f32) -> ShapedGlyph {
+        ShapedGlyph {
+            key: GlyphIndexKey {
+                font: FontKey { token: 0 },
+                index,
+                style: Style::Normal,
+            },
+            cluster,
+            x_advance,
+            x_offset: 0.0,
+            y_offset: 0.0,
+        }
+    }
+
+    #[test]
+    fn ligatures_span_the_cells_of_their_characters() {
+        // "a->b" with "->" shaped into a single glyph.
+        let glyphs = [glyph(1, 0, 10.0), glyph(2, 1, 20.0), glyph(3, 3, 10.0)];
+        let line = place("a->b", &glyphs, 4);
+
+        let cells: Vec<(u32, u32)> = line.clusters.iter().map(|c| (c.cell, c.cells)).collect();
+        assert_eq!(cells, vec![(0, 1), (1, 2), (3, 1)]);
+        assert_eq!(line.clusters[1].chars, vec![('-', 1), ('>', 2)]);
+        assert!(line.clusters[2].contains(3));
+    }
+
+    #[test]
+    fn glyphs_of_a_cluster_are_positioned_along_it() {
+        // a base letter and a mark shaped from the same cluster.
+        let glyphs = [glyph(1, 0, 10.0), glyph(2, 0, 0.0)];
+        let line = place("e\u{301}", &glyphs, 4);
+
+        assert_eq!(line.clusters.len(), 1);
+        let x: Vec<f32> = line.clusters[0].glyphs.iter().map(|g| g.x).collect();
+        assert_eq!(x, vec![0.0, 10.0]);
+    }
+
+    #[test]
+    fn missing_glyphs_and_tabs_are_unshaped() {
+        let glyphs = [glyph(0, 0, 10.0), glyph(4, 2, 10.0)];
+        let line = place("x\ty", &glyphs, 4);
+
+        let cells: Vec<(u32, u32)> = line.clusters.iter().map(|c| (c.cell, c.cells)).collect();
+        assert_eq!(cells, vec![(0, 1), (1, 4), (5, 1)]);
+        assert!(line.clusters[0].is_unshaped());
+        assert!(line.clusters[1].is_unshaped());
+        assert!(!line.clusters[2].is_unshaped());
+    }
+
+    #[test]
+    fn runs_are_split_at_tabs() {
+        let runs: Vec<(usize, &str)> = runs("ab\t\tcd\te").collect();
+        assert_eq!(runs, vec![(0, "ab"), (4, "cd"), (7, "e")]);
+    }
+}
@@ -0,0 +1,302 @@
+use gl::types::*;
+use glutin::dpi::PhysicalSize;
+use glutin::{NotCurrent, PossiblyCurrent, RawContext};
+
+use std::ptr;
+use std::str;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::config;
+use crate::pane::{CellSize, Cells, PaneID, Position, Separator};
+use crate::size;
+
+use super::font::{self, FontSize};
+use super::{Compositor, CursorStyle, Damage, Error, PaneContent, Renderer, Result, TextStyle};
+
+/// An edit pane as it is drawn, copied from the editor so the render thread never looks at
+/// the documents.
+#[derive(Debug, Clone)]
+pub struct PaneSnapshot {
+    pub id: PaneID,
+    /// the top left of the pane in the window in physical pixels.
+    pub x: f32,
+    pub y: f32,
+    /// the size of the pane in physical pixels.
+    pub size: size::Size<f32>,
+    /// the visible lines of the document without their line endings.
+    pub lines: Vec<String>,
+    pub first_line: usize,
+    pub view_offset: usize,
+    pub cursor: Position,
+    /// the cells of the pane's other cursors that are in view.
+    pub other_cursors: Vec<Position>,
+    pub cursor_style: CursorStyle,
+    pub cells: Cells,
+    pub selection: Option<(Position, Position)>,
+    /// the (column, line) positions of the characters drawn in the highlight color.
+    pub highlights: Vec<Position>,
+    pub tab_width: u32,
+    pub style: TextStyle,
+    /// the rows that changed since the last snapshot of the pane.
+    pub damage: Damage,
+}
+
+impl PaneSnapshot {
+    pub fn content(&self) -> PaneContent<'_> {
+        PaneContent {
+            lines: &self.lines,
+            first_line: self.first_line,
+            view_offset: self.view_offset,
+            cursor: self.cursor,
+            other_cursors: &self.other_cursors,
+            cursor_style: self.cursor_style,
+            cells: self.cells,
+            selection: self.selection,
+            highlights: &self.highlights,
+            tab_width: self.tab_width,
+            style: self.style,
+        }
+    }
+}
+
+/// Everything drawn in a frame of the window.
+#[derive(Debug, Clone)]
+pub struct FrameSnapshot {
+    /// the size of the window in physical pixels.
+    pub window_size: size::Size<f32>,
+    pub panes: Vec<PaneSnapshot>,
+    /// the lines between the panes.
+    pub separators: Vec<Separator>,
+    /// the panes drawn over the others with a border, like the finder.
+    pub overlay: Vec<PaneSnapshot>,
+}
+
+impl FrameSnapshot {
+    /// adds the damage of an earlier frame that was replaced before it was drawn.
+    fn include(&mut self, earlier: &FrameSnapshot) {
+        for pane in self.panes.iter_mut().chain(self.overlay.iter_mut()) {
+            let mut old_panes = earlier.panes.iter().chain(earlier.overlay.iter());
+            if let Some(old) = old_panes.find(|old| old.id == pane.id) {
+                pane.damage.extend(&old.damage);
+            }
+        }
+    }
+}
+
+enum Message {
+    Frame(FrameSnapshot),
+    Prepare(CellSize, f32),
+    SetFontSize(FontSize),
+    SetDpiFactor(f32),
+    Exit,
+}
+
+/// Draws the window on its own thread, which owns the OpenGL context, the atlases and the
+/// glyphs. The editor sends it snapshots of the panes so waiting for vsync never holds up input.
+pub struct RenderThread {
+    messages: Sender<Message>,
+    /// the font metrics after the font is loaded or changed.
+    replies: Receiver<Result<font::Metrics>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RenderThread {
+    /// starts the thread and loads the font, returning the metrics of the font.
+    /// The window of the context has to outlive the thread.
+    pub fn spawn(
+        context: RawContext<NotCurrent>,
+        config: config::Config,
+        dpi: f32,
+    ) -> Result<(Self, font::Metrics)> {
+        let (messages, receiver) = mpsc::channel();
+        let (sender, replies) = mpsc::channel();
+
+        let handle = thread::Builder::new()
+            .name("render".to_string())
+            .spawn(move || run(context, config, dpi, receiver, sender))
+            .map_err(|e| Error::RenderError(format!("Failed to start the render thread: {}", e)))?;
+
+        let thread = Self {
+            messages,
+            replies,
+            handle: Some(handle),
+        };
+
+        let metrics = thread.reply()?;
+        Ok((thread, metrics))
+    }
+
+    /// draws the frame, replacing any frame that has not been drawn yet.
+    pub fn draw(&self, frame: FrameSnapshot) -> Result<()> {
+        self.send(Message::Frame(frame))
+    }
+
+    /// sets the size of a cell and the gamma text is blended with.
+    pub fn prepare(&self, cell_size: CellSize, gamma: f32) -> Result<()> {
+        self.send(Message::Prepare(cell_size, gamma))
+    }
+
+    /// changes the size of the font, returning the metrics at the new size.
+    pub fn set_font_size(&self, size: FontSize) -> Result<font::Metrics> {
+        self.send(Message::SetFontSize(size))?;
+        self.reply()
+    }
+
+    /// re-rasterizes the glyphs for a new scale of the window, returning the metrics at the new scale.
+    pub fn set_dpi_factor(&self, dpi_factor: f32) -> Result<font::Metrics> {
+        self.send(Message::SetDpiFactor(dpi_factor))?;
+        self.reply()
+    }
+
+    fn send(&self, message: Message) -> Result<()> {
+        self.messages.send(message).map_err(|_| Error::ThreadExited)
+    }
+
+    fn reply(&self) -> Result<font::Metrics> {
+        self.replies.recv().map_err(|_| Error::ThreadExited)?
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        let _ = self.messages.send(Message::Exit);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    context: RawContext<NotCurrent>,
+    config: config::Config,
+    dpi: f32,
+    messages: Receiver<Message>,
+    replies: Sender<Result<font::Metrics>>,
+) {
+    let context = match unsafe { context.make_current() } {
+        Ok(context) => context,
+        Err((_, e)) => {
+            let _ = replies.send(Err(Error::RenderError(format!("Failed to make the context current: {:?}", e))));
+            return;
+        }
+    };
+
+    gl::load_with(|s| context.get_proc_address(s) as *const _);
+
+    unsafe {
+        gl::DebugMessageCallback(callback, ptr::null());
+    }
+
+    let (mut renderer, mut cache) = match Renderer::new(&config)
+        .and_then(|mut renderer| renderer.prepare_font(dpi, &config).map(|cache| (renderer, cache)))
+    {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            let _ = replies.send(Err(e));
+            return;
+        }
+    };
+
+    let _ = replies.send(Ok(*cache.metrics()));
+
+    let mut compositor = Compositor::new();
+
+    while let Ok(message) = messages.recv() {
+        // only the newest frame is drawn, the editor may have sent several while the last one was drawn.
+        let mut frame: Option<FrameSnapshot> = None;
+
+        for message in Some(message).into_iter().chain(messages.try_iter()) {
+            match message {
+                Message::Frame(mut snapshot) => {
+                    if let Some(earlier) = frame.take() {
+                        snapshot.include(&earlier);
+                    }
+                    frame = Some(snapshot);
+                }
+                Message::Prepare(cell_size, gamma) => renderer.prepare(cell_size, gamma),
+                Message::SetFontSize(size) => {
+                    let result = cache.set_font_size(size, &mut renderer.loader());
+                    let _ = replies.send(result.map(|_| *cache.metrics()));
+                }
+                Message::SetDpiFactor(dpi_factor) => {
+                    let result = cache.set_dpi_factor(dpi_factor, &mut renderer.loader());
+                    let _ = replies.send(result.map(|_| *cache.metrics()));
+                }
+                Message::Exit => {
+                    compositor.release(&mut renderer);
+                    return;
+                }
+            }
+        }
+
+        if let Some(snapshot) = frame {
+            if compositor.resized(snapshot.window_size) {
+                let size = snapshot.window_size;
+                context.resize(PhysicalSize::new(size.x as f64, size.y as f64));
+            }
+
+            if let Err(e) = compositor.draw(&mut renderer, &mut cache, &snapshot) {
+                println!("Failed to draw frame: {:?}", e);
+            }
+
+            swap_buffers(&context);
+        }
+    }
+}
+
+fn swap_buffers(context: &RawContext<PossiblyCurrent>) {
+    if let Err(e) = context.swap_buffers() {
+        println!("Failed to swap buffers: {:?}", e);
+    }
+}
+
+extern "system" fn callback(
+    source: GLenum,
+    gltype: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut std::ffi::c_void,
+) {
+    let sor = match source {
+        gl::DEBUG_SOURCE_API => "API",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "WINDOW SYSTEM",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "SHADER COMPILER",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "THIRD PARTY",
+        gl::DEBUG_SOURCE_APPLICATION => "APPLICATION",
+        gl::DEBUG_SOURCE_OTHER => "UNKNOWN",
+        _ => "UNKNOWN",
+    };
+
+    let ty = match gltype {
+        gl::DEBUG_TYPE_ERROR => "ERROR",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "DEPRECATED BEHAVIOR",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "UDEFINED BEHAVIOR",
+        gl::DEBUG_TYPE_PORTABILITY => "PORTABILITY",
+        gl::DEBUG_TYPE_PERFORMANCE => "PERFORMANCE",
+        gl::DEBUG_TYPE_OTHER => "OTHER",
+        gl::DEBUG_TYPE_MARKER => "MARKER",
+        _ => "UNKNOWN",
+    };
+
+    let ser = match severity {
+        gl::DEBUG_SEVERITY_HIGH => "HIGH",
+        gl::DEBUG_SEVERITY_MEDIUM => "MEDIUM",
+        gl::DEBUG_SEVERITY_LOW => "LOW",
+        gl::DEBUG_SEVERITY_NOTIFICATION => return,
+        _ => "UNKNOWN",
+    };
+
+    let message = unsafe { std::slice::from_raw_parts(message as *const u8, length as usize) };
+    println!(
+        "{}: {} of {} severity, raised from {}: {}",
+        id,
+        ty,
+        ser,
+        sor,
+        str::from_utf8(message).unwrap()
+    );
+}
@@ -0,0 +1,473 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::pane::{self, Layout, Pane, PaneID, PaneKind};
+
+/// the version written at the top of a session file, files of other versions are not read.
+const VERSION: u32 = 2;
+/// the most commands, or searches, kept in the history of a session.
+pub const HISTORY_LIMIT: usize = 100;
+
+#[derive(Debug)]
+pub enum Error {
+    FileError(io::Error),
+    /// the file was written by a different version of the editor.
+    UnsupportedVersion(String),
+    /// the line with the number could not be read.
+    InvalidLine(usize),
+    /// the file ended before every pane of a split was read.
+    MissingPane,
+}
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Where an edit pane was: the file it showed and the place in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditView {
+    /// the file of the document, None for an unnamed document.
+    pub path: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    pub start_line: usize,
+    pub view_offset: usize,
+}
+
+/// A pane of the split tree as it is saved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// a layout with the share of each child, side by side when it is vertical.
+    Split {
+        vertical: bool,
+        weights: Vec<f32>,
+        children: Vec<Node>,
+    },
+    Edit(EditView),
+    /// the file tree, its entries are read again when it is restored.
+    Tree,
+}
+
+/// The panes of the main window and the commands typed in it, written when the editor exits
+/// so the next launch can pick up where it was left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    /// the split tree of each tab. The main window has a single tab until the tab bar is drawn,
+    /// the others are kept in the file but not restored.
+    pub tabs: Vec<Node>,
+    pub active_tab: usize,
+    /// the index of the active pane among the edit panes of the active tab, in the order they
+    /// are laid out.
+    pub active: usize,
+    /// the commands typed in command input mode, oldest first.
+    pub history: Vec<String>,
+    /// the text searched for, oldest first.
+    pub search: Vec<String>,
+}
+
+impl Session {
+    /// the panes of the split tree as the only tab, `view` gives where each edit pane is.
+    pub fn from_pane<F>(root: &Pane, active: PaneID, history: &[String], search: &[String], mut view: F) -> Self
+    where
+        F: FnMut(PaneID) -> EditView,
+    {
+        let active = root.edit_panes().iter().position(|(id, _, _)| *id == active).unwrap_or(0);
+        let recent = |items: &[String]| items[items.len().saturating_sub(HISTORY_LIMIT)..].to_vec();
+
+        Self {
+            tabs: vec![Node::from_pane(root, &mut view)],
+            active_tab: 0,
+            active,
+            history: recent(history),
+            search: recent(search),
+        }
+    }
+
+    /// the split tree of the active tab.
+    pub fn layout(&self) -> Option<&Node> {
+        self.tabs.get(self.active_tab).or_else(|| self.tabs.first())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(Error::FileError)?;
+        Self::parse(&text)
+    }
+
+    /// writes the session, creating the directories it is in.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(Error::FileError)?;
+        }
+
+        fs::write(path, self.to_string()).map_err(Error::FileError)
+    }
+
+    /// reads a session from its text. The first line is the version, the panes of each tab
+    /// follow its tab line depth first, with each split before its children.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        match lines.next() {
+            Some((_, line)) if line == format!("session {}", VERSION) => {}
+            Some((_, line)) => return Err(Error::UnsupportedVersion(line.to_string())),
+            None => return Err(Error::MissingPane),
+        }
+
+        let mut active = 0;
+        let mut active_tab = 0;
+        let mut history = Vec::new();
+        let mut search = Vec::new();
+        let mut tabs: Vec<Vec<(usize, &str, &str)>> = Vec::new();
+
+        for (number, line) in lines {
+            let (key, rest) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => (line, ""),
+            };
+
+            match key {
+                "active" => active = rest.parse().map_err(|_| Error::InvalidLine(number))?,
+                "active-tab" => active_tab = rest.parse().map_err(|_| Error::InvalidLine(number))?,
+                "history" => history.push(unescape(rest)),
+                "search" => search.push(unescape(rest)),
+                "tab" => tabs.push(Vec::new()),
+                "split" | "edit" | "tree" => match tabs.last_mut() {
+                    Some(panes) => panes.push((number, key, rest)),
+                    None => return Err(Error::InvalidLine(number)),
+                },
+                // blank lines and keys of newer versions are skipped.
+                _ => {}
+            }
+        }
+
+        if tabs.is_empty() {
+            return Err(Error::MissingPane);
+        }
+        let tabs = tabs
+            .into_iter()
+            .map(|panes| Node::parse(&mut panes.into_iter()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            tabs,
+            active_tab,
+            active,
+            history,
+            search,
+        })
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = format!("session {}\nactive-tab {}\nactive {}\n", VERSION, self.active_tab, self.active);
+        for layout in self.tabs.iter() {
+            text.push_str("tab\n");
+            layout.write(&mut text);
+        }
+
+        for command in self.history.iter() {
+            text.push_str(&format!("history {}\n", escape(command)));
+        }
+        for search in self.search.iter() {
+            text.push_str(&format!("search {}\n", escape(search)));
+        }
+
+        f.write_str(&text)
+    }
+}
+
+impl Node {
+    fn from_pane<F>(pane: &Pane, view: &mut F) -> Self
+    where
+        F: FnMut(PaneID) -> EditView,
+    {
+        match pane.kind() {
+            PaneKind::Vert(layout) => Node::Split {
+                vertical: true,
+                weights: layout.weights().to_vec(),
+                children: layout.iter().map(|p| Node::from_pane(p, view)).collect(),
+            },
+            PaneKind::Hor(layout) => Node::Split {
+                vertical: false,
+                weights: layout.weights().to_vec(),
+                children: layout.iter().map(|p| Node::from_pane(p, view)).collect(),
+            },
+            PaneKind::Edit => Node::Edit(view(pane.id())),
+            PaneKind::Tree => Node::Tree,
+        }
+    }
+
+    /// builds the split tree of the node, adding the id and view of each edit pane in the order
+    /// they are laid out. The panes are sized when they are fit to the window.
+    pub fn to_pane(&self, views: &mut Vec<(PaneID, EditView)>) -> Pane {
+        match self {
+            Node::Split {
+                vertical,
+                weights,
+                children,
+            } => {
+                let children = children.iter().map(|node| node.to_pane(views)).collect();
+                Pane::split(*vertical, children, weights)
+            }
+            Node::Edit(view) => {
+                let pane = Pane::new(PaneKind::Edit, pane::Size::new(0.0, 0.0), pane::Cells::new(0, 0), glm::vec2(0.0, 0.0));
+                views.push((pane.id(), view.clone()));
+                pane
+            }
+            Node::Tree => Pane::new(PaneKind::Tree, pane::Size::new(0.0, 0.0), pane::Cells::new(0, 0), glm::vec2(0.0, 0.0)),
+        }
+    }
+
+    /// the node has a file tree in it.
+    pub fn has_tree(&self) -> bool {
+        match self {
+            Node::Split { children, .. } => children.iter().any(|node| node.has_tree()),
+            Node::Edit(_) => false,
+            Node::Tree => true,
+        }
+    }
+
+    fn write(&self, text: &mut String) {
+        match self {
+            Node::Split {
+                vertical,
+                weights,
+                children,
+            } => {
+                let weights: Vec<String> = weights.iter().map(|w| w.to_string()).collect();
+                text.push_str(&format!(
+                    "split {} {} {}\n",
+                    if *vertical { "vert" } else { "hor" },
+                    children.len(),
+                    weights.join(" ")
+                ));
+
+                children.iter().for_each(|node| node.write(text));
+            }
+            Node::Edit(view) => {
+                text.push_str(&format!(
+                    "edit {} {} {} {}",
+                    view.line, view.column, view.start_line, view.view_offset
+                ));
+                if let Some(path) = view.path.as_ref() {
+                    text.push_str(&format!(" {}", escape(&path.to_string_lossy())));
+                }
+                text.push('\n');
+            }
+            Node::Tree => text.push_str("tree\n"),
+        }
+    }
+
+    fn parse<'a, I>(panes: &mut I) -> Result<Self>
+    where
+        I: Iterator<Item = (usize, &'a str, &'a str)>,
+    {
+        let (number, key, rest) = panes.next().ok_or(Error::MissingPane)?;
+        let invalid = || Error::InvalidLine(number);
+
+        match key {
+            "split" => {
+                let mut fields = rest.split_whitespace();
+                let vertical = match fields.next() {
+                    Some("vert") => true,
+                    Some("hor") => false,
+                    _ => return Err(invalid()),
+                };
+                let count: usize = fields.next().and_then(|f| f.parse().ok()).ok_or_else(invalid)?;
+                let weights = fields
+                    .map(|f| f.parse::<f32>().map_err(|_| invalid()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                if count == 0 || weights.len() != count {
+                    return Err(invalid());
+                }
+
+                let children = (0..count).map(|_| Node::parse(panes)).collect::<Result<Vec<_>>>()?;
+
+                Ok(Node::Split {
+                    vertical,
+                    weights,
+                    children,
+                })
+            }
+            "edit" => {
+                // the path is the rest of the line, so it can have spaces in it.
+                let mut fields = rest.splitn(5, ' ');
+                let mut field = || -> Result<usize> {
+                    fields.next().and_then(|f| f.parse().ok()).ok_or_else(invalid)
+                };
+
+                let (line, column, start_line, view_offset) = (field()?, field()?, field()?, field()?);
+                let path = fields.next().filter(|p| !p.is_empty()).map(|p| PathBuf::from(unescape(p)));
+
+                Ok(Node::Edit(EditView {
+                    path,
+                    line,
+                    column,
+                    start_line,
+                    view_offset,
+                }))
+            }
+            _ => Ok(Node::Tree),
+        }
+    }
+}
+
+/// a path or command written on one line of the file, with its backslashes and line breaks
+/// escaped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// the file the session of the project directory is kept in, under the data directory of the
+/// user. The path of the project is its name, with '%' in place of each separator.
+pub fn project_session_path(project: &Path) -> Option<PathBuf> {
+    let data = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+
+    let name: String = project
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c == ':' { '%' } else { c })
+        .collect();
+
+    Some(data.join("rem/sessions").join(format!("{}.session", name)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn view(line: usize, path: Option<&str>) -> EditView {
+        EditView {
+            path: path.map(PathBuf::from),
+            line,
+            column: 4,
+            start_line: line.saturating_sub(10),
+            view_offset: 0,
+        }
+    }
+
+    fn session() -> Session {
+        Session {
+            tabs: vec![Node::Split {
+                vertical: true,
+                weights: vec![0.2, 0.8],
+                children: vec![
+                    Node::Tree,
+                    Node::Split {
+                        vertical: false,
+                        weights: vec![0.25, 0.75],
+                        children: vec![Node::Edit(view(42, Some("/src/my file.rs"))), Node::Edit(view(0, None))],
+                    },
+                ],
+            }],
+            active_tab: 0,
+            active: 1,
+            history: vec!["w".to_string(), "b main".to_string()],
+            search: vec!["fn main".to_string()],
+        }
+    }
+
+    #[test]
+    fn sessions_are_read_back_as_written() {
+        let session = session();
+        let text = session.to_string();
+
+        assert!(text.starts_with("session 2\n"));
+        assert_eq!(Session::parse(&text).unwrap(), session);
+    }
+
+    #[test]
+    fn line_breaks_in_paths_are_escaped() {
+        let mut session = session();
+        session.tabs.push(Node::Edit(view(1, Some("/src/new\nline\\n.rs"))));
+        session.history.push("e a\\b\rc".to_string());
+
+        let text = session.to_string();
+        assert_eq!(text.lines().filter(|line| line.starts_with("edit")).count(), 3);
+        assert_eq!(Session::parse(&text).unwrap(), session);
+    }
+
+    #[test]
+    fn broken_files_are_rejected() {
+        assert!(matches!(Session::parse("session 9\ntab\ntree\n"), Err(Error::UnsupportedVersion(_))));
+        assert!(matches!(Session::parse("session 2\ntab\nsplit vert 2 0.5 0.5\ntree\n"), Err(Error::MissingPane)));
+        assert!(matches!(Session::parse("session 2\ntab\nedit 1 x 0 0\n"), Err(Error::InvalidLine(3))));
+        assert!(matches!(Session::parse("session 2\ntree\n"), Err(Error::InvalidLine(2))));
+    }
+
+    #[test]
+    fn the_split_tree_is_rebuilt_with_its_weights() {
+        let session = session();
+        let mut views = Vec::new();
+        let root = session.tabs[0].to_pane(&mut views);
+
+        let edits: Vec<PaneID> = root.edit_panes().iter().map(|(id, _, _)| *id).collect();
+        assert_eq!(edits, views.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+        assert_eq!(views[0].1, view(42, Some("/src/my file.rs")));
+
+        let mut ids = views.iter();
+        let saved = Session::from_pane(&root, edits[1], &session.history, &session.search, |id| {
+            let (pane, view) = ids.next().unwrap();
+            assert_eq!(*pane, id);
+            view.clone()
+        });
+        assert_eq!(saved, session);
+    }
+
+    #[test]
+    fn missing_files_are_restored_as_new_documents() {
+        use crate::config::Config;
+        use crate::editor_core::Engine;
+        use std::rc::Rc;
+
+        let missing = std::env::temp_dir().join(format!("rem-session-missing-{}.rs", std::process::id()));
+        let mut session = session();
+        session.tabs = vec![Node::Edit(view(3, missing.to_str()))];
+        let session = Session::parse(&session.to_string()).unwrap();
+
+        let mut views = Vec::new();
+        session.tabs[0].to_pane(&mut views);
+
+        let mut engine = Engine::new(Rc::new(Config::default()));
+        let path = views[0].1.path.as_ref().unwrap();
+        let doc = engine.open_document(&path.to_string_lossy()).unwrap();
+
+        let document = engine.get_document(doc).unwrap();
+        assert_eq!(document.path(), missing.to_str());
+        assert!(document.as_str().is_empty());
+        assert!(!missing.exists());
+    }
+}
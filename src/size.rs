@@ -21,6 +21,8 @@ where
     }
 }
 
+// a tuple of type parameters cannot implement From for a local type.
+#[allow(clippy::from_over_into)]
 impl<T, D> Into<(D, D)> for Size<T>
 where
     D: From<T>,
@@ -8,7 +8,6 @@ impl Timer {
     pub fn new() -> Self {
         Self {
             last: None,
-            duration: None
         }
     }
 
@@ -19,17 +18,27 @@ impl Timer {
     pub fn stop(&mut self) -> Duration {
         if let Some(last) = self.last {
             self.last = None;
-            last.elapsed();
+            last.elapsed()
         }
         else {
             panic!("Timer.stop was called before Timer.start");
         }
     }
 
+    /// the time since the timer was started, zero when it is not running.
     pub fn duration(&self) -> Duration {
         if let Some(last) = self.last {
-            last.elapsed();
+            last.elapsed()
         }
+        else {
+            Duration::new(0, 0)
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
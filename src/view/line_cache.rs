@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use crate::color::Color;
 use crate::editor_core::DocID;
+use crate::render::TextStyle;
 
 #[derive(Debug)]
 pub enum Error {
@@ -11,19 +12,23 @@ type Result<T> = ::std::result::Result<T, Error>;
 
 /// A single token to be rendered.
 /// It knows its value and display color.
+#[allow(dead_code)] // read once views are drawn.
 pub struct Atom<'a> {
     /// the string this an atom of.
     value: &'a str,
     /// where this atom resides in the line.
     index: usize, // this is to allow for quick look ups.
     /// the render color of this text.
-    color: Color
+    color: Color,
+    /// bold, italic, underline or strikethrough.
+    style: TextStyle,
 }
 
 impl<'a> Atom<'a> {
 }
 
 /// A line that was has previously been rendered.
+#[allow(dead_code)] // read once views are drawn.
 pub struct Line<'a> {
     line_number: u32, 
     /// A sorted list of atoms. The atoms are sorted by their start index.
@@ -40,15 +45,23 @@ impl<'a> Line<'a> {
     
     /// inserts the atom into the line at the appropriate location in the vector.
     pub fn insert(&mut self, atom: Atom<'a>) -> Result<()> {
+        let at = self.atoms.partition_point(|a| a.index < atom.index);
+        self.atoms.insert(at, atom);
+        Ok(())
     }
     
     pub fn remove(&mut self, index: usize) -> Result<()> {
+        if let Ok(at) = self.atoms.binary_search_by_key(&index, |a| a.index) {
+            self.atoms.remove(at);
+        }
+        Ok(())
     }
 }
 
 /// A cache of renderable lines.
 /// Theses lines have been processed before and have not changed since the last time they
 /// were viewed.
+#[allow(dead_code)] // read once views are drawn.
 pub struct LineCache<'a> {
     /// the lines of a view that were rendered indexed by their line.
     lines: HashMap<u32, Line<'a>>,
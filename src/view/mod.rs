@@ -4,14 +4,14 @@ pub mod view_status;
 
 use crate::size::Size;
 
-pub use cells::*;
 pub use line_cache::*;
-pub use view_status::*;
 
-use crate::render::framebuffer::{FrameBuffer, FrameSize};
+use crate::render::framebuffer::FrameBuffer;
 
 /// A 'view' into a file. It allows the user to see the content of a file within a given range.
 // Is it the right way to have this view take a reference to the buffer.
+// nothing draws from views yet, the editor renders snapshots of its panes.
+#[allow(dead_code)]
 pub struct View<'a> {
     /// the the physical size of a view in pixels.
     size: Size<f32>,
@@ -19,7 +19,7 @@ pub struct View<'a> {
     // cell: CellGrid,
     /// A cache of the previously views lines.
     line_cache: LineCache<'a>,
-    /// 
+    /// the last frame drawn of the view.
     frame_cache: FrameBuffer,
 }
 
@@ -1,8 +1,5 @@
 // Needed glutin modules and structures
-pub use glutin::{
-    DeviceEvent, ElementState, Event, EventsLoop, NotCurrent, PossiblyCurrent, VirtualKeyCode,
-    WindowEvent, WindowedContext,
-};
+pub use glutin::{Event, EventsLoop, EventsLoopProxy, NotCurrent, RawContext, WindowedContext};
 
 // needed for error handling
 pub use glutin::{ContextError, CreationError};
@@ -32,9 +29,9 @@ impl WindowSize {
     }
 }
 
-impl Into<LogicalSize> for WindowSize {
-    fn into(self) -> LogicalSize {
-        LogicalSize::new(self.x.into(), self.y.into())
+impl From<WindowSize> for LogicalSize {
+    fn from(size: WindowSize) -> Self {
+        LogicalSize::new(size.x.into(), size.y.into())
     }
 }
 
@@ -46,9 +43,13 @@ pub struct Window {
 
     // The main display window. Eventually this window could be owned by an other window.
     // This will allow for integration of working project between multiple windows.
-    context: WindowedContext<PossiblyCurrent>,
+    window: glutin::Window,
+
+    // The OpenGL context of the window until it is given to the render thread.
+    context: Option<RawContext<NotCurrent>>,
 
     // Is this window the active window of the user.
+    #[allow(dead_code)] // focus changes are not tracked yet.
     is_focus: bool,
 }
 
@@ -66,13 +67,23 @@ impl Window {
     pub fn new(event_loop: EventsLoop, size: LogicalSize) -> Result<Self> {
         let context = Self::build_window(&event_loop, size)?;
 
+        // the context is drawn with on the render thread while the window stays with the event loop.
+        let (context, window) = unsafe { context.split() };
+
         Ok(Self {
             event_loop,
-            context,
+            window,
+            context: Some(context),
             is_focus: true,
         })
     }
 
+    /// the OpenGL context of the window, it can only be taken once.
+    /// The window has to outlive the context.
+    pub fn take_context(&mut self) -> Option<RawContext<NotCurrent>> {
+        self.context.take()
+    }
+
     // gets the dpi of the window, this can be be changed by user action
     // such as when the window is moved to a different monitor.
     // This is needed for font rendering.
@@ -95,7 +106,7 @@ impl Window {
     }
 
     pub fn window(&self) -> &glutin::Window {
-        self.context.window()
+        &self.window
     }
 
     pub fn set_title(&self, title: &str) {
@@ -106,7 +117,7 @@ impl Window {
     fn build_window(
         event_loop: &EventsLoop,
         size: LogicalSize,
-    ) -> Result<WindowedContext<PossiblyCurrent>> {
+    ) -> Result<WindowedContext<NotCurrent>> {
         use glutin::{ContextBuilder, WindowBuilder};
 
         let window = WindowBuilder::new()
@@ -128,23 +139,19 @@ impl Window {
             .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (4, 0)))
             .with_double_buffer(Some(true))
             .with_srgb(true)
-            // .with_vsync(true)
+            .with_vsync(true)
             // }
             .build_windowed(window, event_loop)
-            .map_err(|e| Error::NoWindow(e))?;
+            .map_err(Error::NoWindow)?;
 
-        Ok(unsafe {
-            context
-                .make_current()
-                .map_err(|(_, e)| Error::NoContext(e))?
-        })
+        Ok(context)
     }
 
     #[cfg(target_os = "macos")]
     fn build_window(
         event_loop: &EventsLoop,
         size: LogicalSize,
-    ) -> Result<WindowedContext<PossiblyCurrent>> {
+    ) -> Result<WindowedContext<NotCurrent>> {
         use super::glutin::os::macos::WindowBuilderExt;
         use super::glutin::{ContextBuilder, WindowBuilder};
 
@@ -165,29 +172,30 @@ impl Window {
             .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (4, 0)))
             .with_double_buffer(Some(true))
             .with_srgb(true)
-            // .with_vsync(true)
+            .with_vsync(true)
             // }
             .build_windowed(windowbuilder, event_loop)
             .map_err(|e| Error::NoWindow(e))?;
 
-        Ok(unsafe {
-            context
-                .make_current()
-                .map_err(|(_, e)| Error::NoContext(e))?
-        })
+        Ok(context)
+    }
+
+    /// wakes up the event loop from other threads.
+    pub fn create_proxy(&self) -> EventsLoopProxy {
+        self.event_loop.create_proxy()
     }
 
     pub fn poll_events<F: FnMut(Event)>(&mut self, f: F) {
         self.event_loop.poll_events(f);
     }
 
-    pub fn swap_buffers(&self) {
-        self.context.swap_buffers().unwrap();
-    }
+    /// blocks until there is an event, then gives it and the other pending events to f.
+    pub fn wait_events<F: FnMut(Event)>(&mut self, mut f: F) {
+        self.event_loop.run_forever(|event| {
+            f(event);
+            glutin::ControlFlow::Break
+        });
 
-    // this should never fail.
-    pub fn init_gl(&self) -> Result<()> {
-        gl::load_with(|s| self.context.get_proc_address(s) as *const _);
-        Ok(())
+        self.event_loop.poll_events(f);
     }
 }